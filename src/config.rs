@@ -1,12 +1,22 @@
-use std::{collections::HashMap, env, fs, path::Path, path::PathBuf};
+use std::{collections::HashMap, env, fs, path::Path, path::PathBuf, time::Duration};
 
-use anyhow::{anyhow, Context, Result};
-use serde::Deserialize;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::platform::DisplayInfo;
+use crate::platform::{self, Backend, BackendOptions, DisplayInfo};
 
-pub const DEFAULT_INPUTS: &[(&str, u16)] = &[("dp1", 15u16), ("usb_c", 26u16)];
+/// Presets offered when the config defines none: the MCCS-standard VCP `0x60` values, plus
+/// `0x1B`, the code most vendors use for USB-C. Real monitors often deviate, so the tray labels
+/// these as standard defaults until `inputs` is configured.
+/// Set `use_builtin_default_inputs: false` to get an empty menu instead.
+pub const DEFAULT_INPUTS: &[(&str, u16)] = &[
+    ("dp1", 0x0F),
+    ("dp2", 0x10),
+    ("hdmi1", 0x11),
+    ("hdmi2", 0x12),
+    ("usb_c", 0x1B),
+];
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -14,14 +24,143 @@ pub struct Config {
     #[serde(default)]
     pub start_with_windows: Option<bool>,
 
+    /// If set, the macOS tray shows the current input (read via `get_input`) in the menu bar
+    /// instead of the app name.
+    #[serde(default)]
+    pub show_input_in_titlebar: bool,
+
+    /// Tray clicks repeating the same input on the same display within this many milliseconds
+    /// are ignored (guards against double-registered menu clicks). `0` disables it.
+    #[serde(default = "default_tray_debounce_ms")]
+    pub tray_debounce_ms: u64,
+
+    /// After a tray switch, input items stay grayed out for this many milliseconds while the
+    /// monitor renegotiates the link. `0` disables it.
+    #[serde(default = "default_switch_cooldown_ms")]
+    pub switch_cooldown_ms: u64,
+
+    /// Without configured `inputs`, the tray offers [`DEFAULT_INPUTS`]. `false` shows no
+    /// inputs at all, just a hint to edit the config.
+    #[serde(default = "default_use_builtin_default_inputs")]
+    pub use_builtin_default_inputs: bool,
+
+    /// When the tray is started by its autostart entry, wait this many milliseconds before
+    /// looking for displays, which may not be ready right after login. `0` starts immediately.
+    #[serde(default)]
+    pub startup_delay_ms: u64,
+
+    /// After a tray switch, briefly dim the display and restore its brightness, so it's clear
+    /// which physical screen the tray acts on. Skipped where brightness can't be read.
+    #[serde(default)]
+    pub flash_on_switch: bool,
+
+    /// Show a desktop notification after each tray switch, and when a switch fails.
+    #[serde(default = "default_notifications")]
+    pub notifications: bool,
+
+    /// Leave displays out of the tray menu when they don't answer a DDC/CI read (see
+    /// `Backend::probe_ddc`), instead of listing them and failing on click.
+    #[serde(default)]
+    pub hide_unresponsive_displays: bool,
+
+    /// Default for `MonitorConfig::confirm_switch`.
+    #[serde(default)]
+    pub confirm_switch: bool,
+
     #[serde(default)]
     pub default_display: Option<String>,
 
+    /// Program that "Edit config" (tray) and `config edit` open the config with, e.g. `"code"`
+    /// or, on macOS, `"TextEdit.app"`. Overridden by `MONITORCTL_EDITOR`. When neither is set
+    /// the file opens with the OS association for `.json`.
     #[serde(default)]
-    pub inputs: HashMap<String, u16>,
+    pub editor: Option<String>,
+
+    /// Extra arguments passed to every `m1ddc` invocation (macOS). Overridden by
+    /// `MONITORCTL_M1DDC_ARGS` (whitespace-separated) when that is set.
+    #[serde(default)]
+    pub m1ddc_extra_args: Vec<String>,
+
+    /// Read a VCP code before each input write to wake the DDC/CI channel after standby.
+    #[serde(default)]
+    pub wake_before_write: bool,
+
+    /// Turn the OSD off (VCP 0xCA) around input switches to hide "No Signal" banners.
+    #[serde(default)]
+    pub suppress_osd: bool,
+
+    /// Give up on any single monitor operation (listing, a read, a write) after this many
+    /// milliseconds. `0` waits as long as it takes.
+    #[serde(default)]
+    pub operation_timeout_ms: u64,
+
+    /// Try a failed write (input switch, VCP write) this many more times before giving up.
+    /// Default [`DEFAULT_RETRIES`]; `0` fails on the first error.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+
+    /// Milliseconds before the first retry; each later retry waits twice as long.
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+
+    /// Milliseconds to wait before each VCP read (input, `dump-vcp`). `0` reads immediately.
+    #[serde(default)]
+    pub ddc_read_delay_ms: u64,
+
+    /// Minimum milliseconds between writes to the same display, enforced within one process
+    /// (the tray, or one `batch` run). Writes that come sooner are delayed. `0` disables it;
+    /// unset means off for the CLI and [`DEFAULT_TRAY_MIN_WRITE_INTERVAL_MS`] for the tray.
+    #[serde(default)]
+    pub min_write_interval_ms: Option<u64>,
+
+    /// `mccs_nibble` lets presets be written as `{ "source": 1, "index": 2 }` (see
+    /// [`encode_mccs_nibble`]) for monitors that pack the input that way. Default `raw`.
+    #[serde(default)]
+    pub input_value_encoding: InputValueEncoding,
+
+    #[serde(default, deserialize_with = "deserialize_inputs")]
+    pub inputs: HashMap<String, InputPreset>,
+
+    /// Display-only extras per preset name, e.g. `{ "usb_c": { "icon": "🔌" } }`.
+    #[serde(default)]
+    pub inputs_meta: HashMap<String, InputMeta>,
+
+    /// `get_input` readings that some monitors return when the selected input has no signal
+    /// (often `0` or `255`). They are reported as unknown instead of matched to a preset.
+    #[serde(default)]
+    pub unknown_input_values: Vec<u16>,
+
+    /// Named brightness levels (VCP 0x10) for `monitorctl brightness` and the tray, e.g.
+    /// `{ "day": 80, "night": 25 }`.
+    #[serde(default)]
+    pub brightness_presets: HashMap<String, u16>,
+
+    /// Friendly names shown in `list` and the tray, keyed by index (`"2"`), `serial:<serial>`
+    /// or `uuid:<uuid>`.
+    #[serde(default)]
+    pub display_names: HashMap<String, String>,
 
     #[serde(default)]
     pub monitors: Vec<MonitorConfig>,
+
+    /// Groups of monitors kept in sync: setting an input on one member applies it to all.
+    #[serde(default)]
+    pub linked_groups: Vec<Vec<MonitorMatch>>,
+
+    /// Named layouts for `monitorctl profile`, e.g. `"work"`: a list of monitors and the input
+    /// each should switch to, applied in order.
+    #[serde(default)]
+    pub profiles: HashMap<String, Vec<ProfileEntry>>,
+
+    /// Fail on config problems that are otherwise only warned about (unknown keys, a
+    /// `default_display` that isn't connected). Also set by `--strict`.
+    #[serde(default)]
+    pub strict: bool,
+
+    /// Problems found while loading that didn't stop it, e.g. unknown keys (see
+    /// [`check_strict`]).
+    #[serde(skip)]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -32,232 +171,2290 @@ pub struct MonitorConfig {
     #[serde(default)]
     pub display: Option<String>,
 
+    #[serde(default, deserialize_with = "deserialize_inputs")]
+    pub inputs: HashMap<String, InputPreset>,
+
+    /// Preset (or raw value) applied by `set-input --default`.
     #[serde(default)]
-    pub inputs: HashMap<String, u16>,
+    pub default_input: Option<String>,
+
+    /// Overrides the built-in panel heuristic (`DisplayInfo::internal`) for matching displays.
+    #[serde(default)]
+    pub internal: Option<bool>,
+
+    /// Ask before the tray switches this monitor's input (e.g. when it's the only screen).
+    /// Falls back to the top-level `confirm_switch`.
+    #[serde(default)]
+    pub confirm_switch: Option<bool>,
 }
 
 #[derive(Debug, Default, Clone, Deserialize)]
-pub struct MonitorMatch {
-    pub contains: Option<String>,
-    pub index: Option<u32>,
+#[serde(deny_unknown_fields)]
+pub struct InputMeta {
+    /// Prefix for the preset's tray menu label (an emoji works on both platforms).
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Name shown for the preset in the tray instead of its key, e.g. `"Laptop USB-C"`.
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct ResolvedConfig {
-    pub display_selector: String,
-    pub inputs: HashMap<String, u16>,
+/// One `inputs` entry: a plain value (`"dp1": 15`) or an object that also adjusts the monitor
+/// after switching (`"movie": { "value": 17, "brightness": 30 }`) or writes the value to
+/// another VCP code (`"pip": { "vcp": 233, "value": 1 }`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputPreset {
+    pub value: u16,
+    pub adjust: PresetAdjustments,
+    /// Written as `{ "source", "index" }`; `value` holds the packed byte. Only allowed with
+    /// `input_value_encoding: "mccs_nibble"`.
+    pub packed: bool,
 }
 
-pub fn default_inputs_map() -> HashMap<String, u16> {
-    DEFAULT_INPUTS
-        .iter()
-        .map(|&(k, v)| (k.to_string(), v))
-        .collect()
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputValueEncoding {
+    /// Preset values are the VCP 0x60 value as written.
+    #[default]
+    Raw,
+    /// Presets may also give a source type and index, packed by [`encode_mccs_nibble`].
+    MccsNibble,
 }
 
-pub fn load_optional() -> Result<Option<Config>> {
-    let Some(path) = resolve_config_path() else {
-        return Ok(None);
-    };
-    if !path.exists() {
-        return Ok(None);
+/// Packs an input source type (high nibble) and its index (low nibble) into one VCP 0x60
+/// value, for monitors that encode inputs that way.
+pub fn encode_mccs_nibble(source: u16, index: u16) -> Result<u16, String> {
+    if source > 0xF || index > 0xF {
+        return Err(format!(
+            "source and index must each be 0-15, got source {source}, index {index}"
+        ));
     }
-    let bytes = fs::read(&path).with_context(|| format!("reading config {}", path.display()))?;
-    let cfg: Config =
-        serde_json::from_slice(&bytes).with_context(|| format!("parsing {}", path.display()))?;
-    Ok(Some(cfg))
+    Ok(source << 4 | index)
 }
 
-pub fn resolve_config_path() -> Option<PathBuf> {
-    if let Ok(p) = env::var("MONITORCTL_CONFIG") {
-        if !p.trim().is_empty() {
-            return Some(PathBuf::from(p));
-        }
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetAdjustments {
+    /// VCP code the preset's value is written to instead of the input code `0x60`, for
+    /// monitors that switch inputs elsewhere (or presets that pick a picture mode).
+    pub vcp: Option<u8>,
+    pub brightness: Option<u16>,
+    pub contrast: Option<u16>,
+    pub volume: Option<u16>,
+}
+
+impl PresetAdjustments {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
     }
 
-    let local = PathBuf::from("monitorctl.json");
-    if local.exists() {
-        return Some(local);
+    /// The VCP code the preset's value goes to: `vcp`, else the input code.
+    pub fn input_code(&self) -> u8 {
+        self.vcp.unwrap_or(platform::VCP_INPUT)
     }
 
-    if let Some(appdata) = env::var_os("APPDATA") {
-        return Some(
-            PathBuf::from(appdata)
-                .join("monitorctl")
-                .join("config.json"),
-        );
+    /// Writes the adjustments to `display_selector`, in brightness/contrast/volume order. Meant
+    /// to run right after the input write that selected the preset.
+    pub fn apply(&self, backend: &dyn Backend, display_selector: &str) -> Result<()> {
+        let writes = [
+            ("brightness", platform::VCP_BRIGHTNESS, self.brightness),
+            ("contrast", platform::VCP_CONTRAST, self.contrast),
+            ("volume", platform::VCP_VOLUME, self.volume),
+        ];
+        for (name, code, value) in writes {
+            let Some(value) = value else {
+                continue;
+            };
+            backend
+                .set_vcp_raw(display_selector, code, value)
+                .with_context(|| {
+                    format!("set {name} to {value} on display '{display_selector}'")
+                })?;
+        }
+        Ok(())
     }
+}
 
-    if let Some(home) = env::var_os("HOME") {
-        return Some(
-            PathBuf::from(home)
-                .join(".config")
-                .join("monitorctl")
-                .join("config.json"),
-        );
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct MonitorMatch {
+    pub contains: Option<String>,
+    pub index: Option<u32>,
+    /// Connector label such as `"HDMI-1"` (see `DisplayInfo::connector`), case-insensitive.
+    pub connector: Option<String>,
+    /// Which `DisplayInfo` field(s) `contains` is tested against.
+    #[serde(default)]
+    pub field: MatchField,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchField {
+    #[default]
+    Name,
+    Serial,
+    Uuid,
+    Any,
+}
+
+impl MatchField {
+    fn haystacks(self, display: &DisplayInfo) -> Vec<&str> {
+        let name = display.product_name.as_deref();
+        let serial = display.serial.as_deref();
+        let uuid = display.system_uuid.as_deref();
+        let fields = match self {
+            MatchField::Name => vec![name],
+            MatchField::Serial => vec![serial],
+            MatchField::Uuid => vec![uuid],
+            MatchField::Any => vec![name, serial, uuid],
+        };
+        fields.into_iter().flatten().collect()
     }
+}
 
-    None
+/// One monitor of a profile: which display, and the preset (or raw value) to switch it to.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileEntry {
+    #[serde(default)]
+    pub r#match: MonitorMatch,
+    pub input: String,
 }
 
-pub fn ensure_config_file_exists() -> Result<PathBuf> {
-    let Some(path) = resolve_config_path() else {
-        return Err(anyhow!(
-            "No config path available (set MONITORCTL_CONFIG or ensure APPDATA/HOME is present)"
-        ));
+/// A profile resolved against the connected displays by [`resolve_profile`].
+#[derive(Debug, Clone)]
+pub struct ResolvedProfile {
+    pub steps: Vec<ProfileStep>,
+    /// Entries skipped because no connected display matches them.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileStep {
+    pub display_selector: String,
+    /// The entry's `input` as written, for reporting.
+    pub input: String,
+    pub value: u16,
+    pub adjust: PresetAdjustments,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub display_selector: String,
+    pub inputs: HashMap<String, u16>,
+    /// Adjustments of the presets in `inputs` that have any, keyed by preset name.
+    pub adjustments: HashMap<String, PresetAdjustments>,
+    /// Config problems worked around while resolving, e.g. a `default_display` that isn't
+    /// connected. Callers show them as warnings.
+    pub warnings: Vec<String>,
+}
+
+impl ResolvedConfig {
+    /// The `inputs` that switch through VCP 0x60, the only ones a `get_input` reading can be
+    /// matched against. Presets with their own `vcp` are left out.
+    pub fn readable_inputs(&self) -> HashMap<String, u16> {
+        self.inputs
+            .iter()
+            .filter(|(name, _value)| selects_input(&self.adjustments, name))
+            .map(|(name, value)| (name.clone(), *value))
+            .collect()
+    }
+}
+
+/// Whether the preset `name` switches through VCP 0x60 rather than its own `vcp` code.
+pub fn selects_input(adjustments: &HashMap<String, PresetAdjustments>, name: &str) -> bool {
+    adjustments
+        .get(name)
+        .is_none_or(|adjust| adjust.input_code() == platform::VCP_INPUT)
+}
+
+pub fn backend_options(config: Option<&Config>) -> BackendOptions {
+    let m1ddc_extra_args = match env::var("MONITORCTL_M1DDC_ARGS") {
+        Ok(args) => args.split_whitespace().map(str::to_string).collect(),
+        Err(_) => config
+            .map(|c| c.m1ddc_extra_args.clone())
+            .unwrap_or_default(),
     };
 
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("create config dir {}", parent.display()))?;
+    BackendOptions {
+        m1ddc_extra_args,
+        wake_before_write: config.is_some_and(|c| c.wake_before_write),
+        min_write_interval: Duration::from_millis(
+            config.and_then(|c| c.min_write_interval_ms).unwrap_or(0),
+        ),
+        read_delay: Duration::from_millis(config.map_or(0, |c| c.ddc_read_delay_ms)),
+        read_only: false,
+        suppress_osd: config.is_some_and(|c| c.suppress_osd),
+        operation_timeout: Duration::from_millis(config.map_or(0, |c| c.operation_timeout_ms)),
+        retries: config.map_or(DEFAULT_RETRIES, |c| c.retries),
+        retry_delay: Duration::from_millis(
+            config.map_or(DEFAULT_RETRY_DELAY_MS, |c| c.retry_delay_ms),
+        ),
+        cache_monitors: false,
     }
+}
 
-    if !path.exists() {
-        let template = serde_json::json!({
-            "start_with_windows": false,
-            "inputs": default_inputs_map()
-        });
-        let mut s = serde_json::to_string_pretty(&template).context("serialize config template")?;
-        s.push('\n');
-        fs::write(&path, s.as_bytes()).with_context(|| format!("write {}", path.display()))?;
+/// Parses a VCP value written either in decimal (`"27"`) or hex (`"0x1B"`).
+pub fn parse_vcp_value(value: &str) -> Option<u16> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse::<u16>().ok(),
     }
+}
 
-    Ok(path)
+/// `inputs` values may be JSON numbers, strings accepted by [`parse_vcp_value`], or objects
+/// with such a `value` plus optional [`PresetAdjustments`] fields.
+fn deserialize_inputs<'de, D>(deserializer: D) -> Result<HashMap<String, InputPreset>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = HashMap::<String, Value>::deserialize(deserializer)?;
+    raw.into_iter()
+        .map(|(name, value)| match parse_preset(value) {
+            Ok(preset) => Ok((name, preset)),
+            Err(e) => Err(serde::de::Error::custom(format!("input '{name}': {e}"))),
+        })
+        .collect()
 }
 
-pub fn patch_start_with_windows(enabled: bool) -> Result<PathBuf> {
-    let Some(path) = resolve_config_path() else {
-        return Err(anyhow!(
-            "No config path available (set MONITORCTL_CONFIG or ensure APPDATA/HOME is present)"
-        ));
+fn parse_preset(value: Value) -> Result<InputPreset, String> {
+    let (raw, adjust) = match value {
+        Value::Object(mut fields) if fields.contains_key("source") => {
+            let mut nibble = |key: &str| {
+                fields
+                    .remove(key)
+                    .and_then(|v| v.as_u64())
+                    .and_then(|n| u16::try_from(n).ok())
+                    .ok_or_else(|| format!("\"{key}\" must be a number next to \"source\""))
+            };
+            let value = encode_mccs_nibble(nibble("source")?, nibble("index")?)?;
+            let adjust =
+                PresetAdjustments::deserialize(Value::Object(fields)).map_err(|e| e.to_string())?;
+            return Ok(InputPreset {
+                value,
+                adjust,
+                packed: true,
+            });
+        }
+        Value::Object(mut fields) => {
+            let raw = fields
+                .remove("value")
+                .ok_or_else(|| "missing \"value\"".to_string())?;
+            let adjust =
+                PresetAdjustments::deserialize(Value::Object(fields)).map_err(|e| e.to_string())?;
+            (raw, adjust)
+        }
+        raw => (raw, PresetAdjustments::default()),
     };
+    let parsed = match &raw {
+        Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+        Value::String(s) => parse_vcp_value(s),
+        _ => None,
+    };
+    match parsed {
+        Some(value) => Ok(InputPreset {
+            value,
+            adjust,
+            packed: false,
+        }),
+        None => Err(format!(
+            "expected a number (0-65535) or a string like \"0x1B\", got {raw}"
+        )),
+    }
+}
 
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("create config dir {}", parent.display()))?;
+/// Adds `presets` to a resolved preset map, replacing same-named entries (and their
+/// adjustments) from an earlier, less specific level.
+fn merge_presets(
+    inputs: &mut HashMap<String, u16>,
+    adjustments: &mut HashMap<String, PresetAdjustments>,
+    presets: &HashMap<String, InputPreset>,
+) {
+    for (name, preset) in presets {
+        inputs.insert(name.clone(), preset.value);
+        if preset.adjust.is_empty() {
+            adjustments.remove(name);
+        } else {
+            adjustments.insert(name.clone(), preset.adjust);
+        }
     }
+}
 
-    let mut root = read_json_or_empty_object(&path)?;
-    let obj = root
-        .as_object_mut()
-        .ok_or_else(|| anyhow!("config root must be a JSON object"))?;
+pub const DEFAULT_TRAY_DEBOUNCE_MS: u64 = 300;
 
-    obj.insert("start_with_windows".to_string(), Value::Bool(enabled));
+fn default_tray_debounce_ms() -> u64 {
+    DEFAULT_TRAY_DEBOUNCE_MS
+}
 
-    let mut s = serde_json::to_string_pretty(&root).context("serialize config")?;
-    s.push('\n');
-    fs::write(&path, s.as_bytes()).with_context(|| format!("write {}", path.display()))?;
-    Ok(path)
+pub const DEFAULT_SWITCH_COOLDOWN_MS: u64 = 1000;
+
+/// `min_write_interval_ms` for the tray when the config doesn't set it. The tray keeps writing
+/// to the same displays for its whole session, so it spaces them out by default.
+pub const DEFAULT_TRAY_MIN_WRITE_INTERVAL_MS: u64 = 200;
+
+fn default_switch_cooldown_ms() -> u64 {
+    DEFAULT_SWITCH_COOLDOWN_MS
 }
 
-fn read_json_or_empty_object(path: &Path) -> Result<Value> {
-    if !path.exists() {
-        return Ok(Value::Object(Default::default()));
-    }
+fn default_use_builtin_default_inputs() -> bool {
+    true
+}
 
-    let bytes = fs::read(path).with_context(|| format!("reading config {}", path.display()))?;
-    let v: Value =
-        serde_json::from_slice(&bytes).with_context(|| format!("parsing {}", path.display()))?;
-    Ok(v)
+fn default_notifications() -> bool {
+    true
 }
 
-pub fn resolve(
-    config: Option<&Config>,
-    displays: &[DisplayInfo],
-    display_arg: Option<&str>,
-) -> ResolvedConfig {
-    let mut inputs: HashMap<String, u16> = HashMap::new();
-    let mut display_selector: Option<String> = display_arg.map(|s| s.to_string());
+pub const DEFAULT_RETRIES: u32 = 3;
 
-    let Some(cfg) = config else {
-        return ResolvedConfig {
-            display_selector: display_selector.unwrap_or_else(|| "1".to_string()),
-            inputs,
-        };
-    };
+fn default_retries() -> u32 {
+    DEFAULT_RETRIES
+}
 
-    inputs.extend(cfg.inputs.iter().map(|(k, v)| (k.to_string(), *v)));
-    if display_selector.is_none() {
-        display_selector = cfg.default_display.clone();
-    }
+pub const DEFAULT_RETRY_DELAY_MS: u64 = 250;
 
-    if display_selector.is_none() {
-        for mon_cfg in &cfg.monitors {
-            let Some((matched_display, inferred_selector)) = match_display(mon_cfg, displays)
-            else {
-                continue;
-            };
+fn default_retry_delay_ms() -> u64 {
+    DEFAULT_RETRY_DELAY_MS
+}
 
-            if let Some(explicit) = mon_cfg.display.as_deref() {
-                display_selector = Some(explicit.to_string());
-            } else {
-                display_selector = Some(inferred_selector);
-            }
+pub fn default_inputs_map() -> HashMap<String, u16> {
+    DEFAULT_INPUTS
+        .iter()
+        .map(|&(k, v)| (k.to_string(), v))
+        .collect()
+}
 
-            for (k, v) in &mon_cfg.inputs {
-                inputs.insert(k.to_string(), *v);
-            }
+/// Directory next to the config file whose `*.json` fragments are merged into it.
+pub const FRAGMENTS_DIR: &str = "config.d";
 
-            let _ = matched_display;
-            break;
+pub fn load_optional() -> Result<Option<Config>> {
+    let Some(path) = resolve_config_path() else {
+        return Ok(None);
+    };
+    let fragments = path.with_file_name(FRAGMENTS_DIR);
+    if !fragments.is_dir() {
+        if !path.exists() {
+            return Ok(None);
         }
+        let bytes =
+            fs::read(&path).with_context(|| format!("reading config {}", path.display()))?;
+        return parse(&bytes, &path.display().to_string()).map(Some);
     }
 
-    ResolvedConfig {
-        display_selector: display_selector.unwrap_or_else(|| "1".to_string()),
-        inputs,
+    let mut root = read_json_or_empty_object(&path)?;
+    for fragment in load_fragments(&fragments)? {
+        merge_json(&mut root, fragment);
     }
+    let origin = format!("{} merged with {}", path.display(), fragments.display());
+    validate(&root).with_context(|| format!("parsing {origin}"))?;
+    let warnings = unknown_key_warnings(&root);
+    let mut config: Config =
+        serde_json::from_value(root).with_context(|| format!("parsing {origin}"))?;
+    config.warnings = warnings;
+    check_encoding(config).map(Some)
 }
 
-fn match_display<'a>(
-    mon_cfg: &MonitorConfig,
-    displays: &'a [DisplayInfo],
-) -> Option<(&'a DisplayInfo, String)> {
-    if let Some(idx) = mon_cfg.r#match.index {
-        let d = displays.iter().find(|d| d.index == idx)?;
-        return Some((d, selector_for_display(d)));
-    }
+/// Like [`load_optional`], but a config file that doesn't parse (cut short by an interrupted
+/// write, or just broken) is moved aside (see [`set_aside`]) and loading goes on without it.
+/// The second value is a warning with the parse error and the new location, for the caller to
+/// show. Errors from elsewhere, such as a bad fragment, are still returned.
+pub fn load_recovering() -> Result<(Option<Config>, Option<String>)> {
+    let err = match load_optional() {
+        Ok(config) => return Ok((config, None)),
+        Err(err) => err,
+    };
+    let Some(path) = resolve_config_path() else {
+        return Err(err);
+    };
+    let Some(parse_err) = fs::read(&path)
+        .ok()
+        .and_then(|bytes| parse(&bytes, &path.display().to_string()).err())
+    else {
+        return Err(err);
+    };
+    let Some(aside) = set_aside(&path) else {
+        return Err(err);
+    };
+    let warning = format!(
+        "The config file could not be loaded ({parse_err:#}); moved it to {} and continued with defaults",
+        aside.display()
+    );
+    load_optional().map(|config| (config, Some(warning)))
+}
 
-    let needle = mon_cfg.r#match.contains.as_deref()?;
-    let needle_lc = needle.to_ascii_lowercase();
-    let d = displays.iter().find(|d| {
-        d.product_name
-            .as_deref()
-            .unwrap_or_default()
-            .to_ascii_lowercase()
-            .contains(&needle_lc)
-    })?;
-    Some((d, selector_for_display(d)))
+/// Reads the `*.json` files in `dir` in file-name order. Each must hold a JSON object.
+pub fn load_fragments(dir: &Path) -> Result<Vec<Value>> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("reading {}", dir.display()))?;
+    paths.retain(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let value = read_json_or_empty_object(path)?;
+            if !value.is_object() {
+                return Err(anyhow!(
+                    "{}: fragment must be a JSON object",
+                    path.display()
+                ));
+            }
+            Ok(value)
+        })
+        .collect()
 }
 
-fn selector_for_display(display: &DisplayInfo) -> String {
-    if let Some(uuid) = display.system_uuid.as_deref() {
-        return format!("uuid:{uuid}");
+/// Merges `overlay` into `base`: objects (like `inputs`) merge key by key, arrays (like
+/// `monitors`) are appended, anything else is replaced by the overlay.
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) => base.extend(overlay),
+        (base, overlay) => *base = overlay,
     }
-    display.index.to_string()
 }
 
-pub fn parse_input_value(value: &str, resolved: &ResolvedConfig) -> Result<u16> {
-    if let Ok(v) = value.parse::<u16>() {
-        return Ok(v);
-    }
+/// Parses config JSON from memory; `origin` names the source in errors (a path or `<stdin>`).
+pub fn parse(bytes: &[u8], origin: &str) -> Result<Config> {
+    let root: Value = serde_json::from_slice(bytes).with_context(|| format!("parsing {origin}"))?;
+    validate(&root).with_context(|| format!("parsing {origin}"))?;
+    // Deserialized from the bytes again so serde's errors keep their line numbers.
+    let mut config: Config =
+        serde_json::from_slice(bytes).with_context(|| format!("parsing {origin}"))?;
+    config.warnings = unknown_key_warnings(&root);
+    check_encoding(config).with_context(|| format!("parsing {origin}"))
+}
 
-    if let Some(v) = resolved.inputs.get(value) {
-        return Ok(*v);
-    }
+/// One warning per top-level key `Config` doesn't have, which serde silently ignores.
+fn unknown_key_warnings(root: &Value) -> Vec<String> {
+    let Some(fields) = root.as_object() else {
+        return Vec::new();
+    };
+    let known = config_keys();
+    let mut unknown = fields
+        .keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .collect::<Vec<_>>();
+    unknown.sort();
+    unknown
+        .into_iter()
+        .map(|key| match closest_preset(key, known) {
+            Some(closest) => {
+                format!("unknown config key '{key}' is ignored (did you mean '{closest}'?)")
+            }
+            None => format!("unknown config key '{key}' is ignored"),
+        })
+        .collect()
+}
+
+/// The top-level keys serde accepts for [`Config`], taken from its derived `Deserialize`.
+fn config_keys() -> &'static [&'static str] {
+    use serde::de::{self, Visitor};
+
+    /// Deserializer that only records the field list `deserialize_struct` is given.
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("only struct field names are read"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("only struct field names are read"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map enum
+            identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = Config::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// With `strict` set in `config`, fails with `warnings` instead of leaving the caller to print
+/// them.
+pub fn check_strict(config: Option<&Config>, warnings: &[String]) -> Result<()> {
+    if config.is_some_and(|c| c.strict) && !warnings.is_empty() {
+        bail!("{} (strict mode)", warnings.join("; "));
+    }
+    Ok(())
+}
+
+/// Checks the values serde would reject (or accept but never use) and reports all of them at
+/// once, each with its JSON path, instead of serde's first error only.
+fn validate(root: &Value) -> Result<()> {
+    let mut problems = Vec::new();
+    let mut check_u16 = |path: String, value: &Value| {
+        if value.as_u64().and_then(|n| u16::try_from(n).ok()).is_none() {
+            problems.push(format!("{path}: expected a number (0-65535), got {value}"));
+        }
+    };
+    if let Some(presets) = root.get("brightness_presets").and_then(Value::as_object) {
+        for (name, value) in presets {
+            check_u16(format!("brightness_presets.{name}"), value);
+        }
+    }
+    if let Some(values) = root.get("unknown_input_values").and_then(Value::as_array) {
+        for (i, value) in values.iter().enumerate() {
+            check_u16(format!("unknown_input_values[{i}]"), value);
+        }
+    }
+
+    if root
+        .get("default_display")
+        .and_then(Value::as_str)
+        .is_some_and(|s| s.trim().is_empty())
+    {
+        problems.push(
+            "default_display: must not be empty (leave it out to use the first display)"
+                .to_string(),
+        );
+    }
+
+    let mut check_inputs = |path: &str, inputs: Option<&Value>| {
+        for (name, value) in inputs.and_then(Value::as_object).into_iter().flatten() {
+            if let Err(e) = parse_preset(value.clone()) {
+                problems.push(format!("{path}.{name}: {e}"));
+            }
+        }
+    };
+    check_inputs("inputs", root.get("inputs"));
+    let monitors = root.get("monitors").and_then(Value::as_array);
+    for (i, monitor) in monitors.into_iter().flatten().enumerate() {
+        check_inputs(&format!("monitors[{i}].inputs"), monitor.get("inputs"));
+    }
+
+    let has_criterion = |entry: &Value| {
+        entry.get("match").is_some_and(|m| {
+            ["contains", "index", "connector"]
+                .iter()
+                .any(|k| m.get(k).is_some_and(|v| !v.is_null()))
+        })
+    };
+    for (i, monitor) in monitors.into_iter().flatten().enumerate() {
+        let pinned = monitor.get("display").is_some_and(|v| !v.is_null());
+        if !has_criterion(monitor) && !pinned {
+            problems.push(format!(
+                "monitors[{i}].match: needs \"contains\", \"index\" or \"connector\" (or set \"display\" instead)"
+            ));
+        }
+    }
+
+    if let Some(profiles) = root.get("profiles").and_then(Value::as_object) {
+        for (name, entries) in profiles {
+            let Some(entries) = entries.as_array() else {
+                problems.push(format!("profiles.{name}: expected a list of monitors"));
+                continue;
+            };
+            for (i, entry) in entries.iter().enumerate() {
+                if !has_criterion(entry) {
+                    problems.push(format!(
+                        "profiles.{name}[{i}].match: needs \"contains\", \"index\" or \"connector\""
+                    ));
+                }
+                if !entry.get("input").is_some_and(Value::is_string) {
+                    problems.push(format!(
+                        "profiles.{name}[{i}].input: expected a preset name or value"
+                    ));
+                }
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "{} problem(s) in the config:\n{}",
+        problems.len(),
+        problems
+            .iter()
+            .map(|p| format!("  - {p}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+/// `{ "source", "index" }` presets need `input_value_encoding: "mccs_nibble"`, so a raw-value
+/// config can't pick up a packed byte by accident.
+fn check_encoding(config: Config) -> Result<Config> {
+    if config.input_value_encoding == InputValueEncoding::MccsNibble {
+        return Ok(config);
+    }
+    let all_inputs =
+        std::iter::once(&config.inputs).chain(config.monitors.iter().map(|m| &m.inputs));
+    for inputs in all_inputs {
+        if let Some((name, _preset)) = inputs.iter().find(|(_name, p)| p.packed) {
+            bail!(
+                "input '{name}' is given as {{source, index}}, which needs \"input_value_encoding\": \"mccs_nibble\""
+            );
+        }
+    }
+    Ok(config)
+}
+
+/// Reads the whole config from `reader` instead of resolving a path (`--config-stdin`).
+pub fn load_from_reader(mut reader: impl std::io::Read) -> Result<Config> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .context("reading config from stdin")?;
+    parse(&bytes, "<stdin>")
+}
+
+/// Editor from `MONITORCTL_EDITOR`, else the config's `editor`.
+pub fn editor(config: Option<&Config>) -> Option<String> {
+    env::var("MONITORCTL_EDITOR")
+        .ok()
+        .or_else(|| config.and_then(|c| c.editor.clone()))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Command that opens `path` in `editor`. An existing path is run as-is; otherwise the value is
+/// split on whitespace into a program and leading arguments (`"code --wait"`). On macOS a
+/// name ending in `.app` is launched with `open -a`.
+pub fn editor_command(editor: &str, path: &Path) -> std::process::Command {
+    if cfg!(target_os = "macos") && editor.ends_with(".app") {
+        let mut cmd = std::process::Command::new("open");
+        cmd.arg("-a").arg(editor).arg(path);
+        return cmd;
+    }
+
+    let mut cmd = if Path::new(editor).exists() {
+        std::process::Command::new(editor)
+    } else {
+        let mut words = editor.split_whitespace();
+        let mut cmd = std::process::Command::new(words.next().unwrap_or(editor));
+        cmd.args(words);
+        cmd
+    };
+    cmd.arg(path);
+    cmd
+}
+
+/// Selector that stands for "the only external display".
+pub const ANY_DISPLAY: &str = "any";
+
+/// `set-input` selector that stands for "every external display". The command fans out over
+/// them itself; backends never see it.
+pub const ALL_DISPLAYS: &str = "all";
+
+pub fn is_all_displays(selector: &str) -> bool {
+    selector.eq_ignore_ascii_case(ALL_DISPLAYS)
+}
+
+/// Displays that aren't built-in panels (see [`is_internal`]).
+pub fn external_displays<'a>(
+    config: Option<&Config>,
+    displays: &'a [DisplayInfo],
+) -> Vec<&'a DisplayInfo> {
+    displays
+        .iter()
+        .filter(|d| !is_internal(config, d))
+        .collect()
+}
+
+/// The `--display` argument, falling back to `MONITORCTL_DISPLAY`, with [`ANY_DISPLAY`]
+/// expanded to the index of the sole external display. With none or several connected it
+/// errors and lists them instead of guessing. Errors about a value taken from the environment
+/// say so, since nothing on the command line explains it.
+pub fn display_arg(
+    config: Option<&Config>,
+    displays: &[DisplayInfo],
+    arg: Option<String>,
+) -> Result<Option<String>> {
+    let (arg, origin) = match arg {
+        Some(arg) => (arg, ""),
+        None => match display_from_env() {
+            Some(arg) => (arg, " (from MONITORCTL_DISPLAY)"),
+            None => return Ok(None),
+        },
+    };
+    if is_all_displays(&arg) {
+        bail!("Display '{ALL_DISPLAYS}'{origin} only works with set-input");
+    }
+    if !arg.eq_ignore_ascii_case(ANY_DISPLAY) {
+        return Ok(Some(arg));
+    }
+
+    match external_displays(config, displays).as_slice() {
+        [only] => Ok(Some(only.index.to_string())),
+        [] => Err(anyhow!(
+            "Display '{ANY_DISPLAY}'{origin} needs exactly one external display, but none was found"
+        )),
+        many => {
+            let options = many
+                .iter()
+                .map(|d| {
+                    format!(
+                        "{} ({})",
+                        d.index,
+                        display_name(config, d).unwrap_or("<unknown>")
+                    )
+                })
+                .collect::<Vec<_>>();
+            Err(anyhow!(
+                "Display '{ANY_DISPLAY}'{origin} needs exactly one external display, but {} were found: {}. Pass one of them to --display.",
+                many.len(),
+                options.join(", ")
+            ))
+        }
+    }
+}
+
+/// `MONITORCTL_DISPLAY`: the display selector CLI commands use when `--display` is omitted.
+/// Takes precedence over everything in the config.
+pub fn display_from_env() -> Option<String> {
+    env::var("MONITORCTL_DISPLAY")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Which rule in `resolve_config_path` picked the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSource {
+    /// `MONITORCTL_CONFIG`.
+    Env,
+    /// `monitorctl.json` in the working directory.
+    Local,
+    AppData,
+    Home,
+    /// `XDG_CONFIG_HOME`, when neither `APPDATA` nor `HOME` is set.
+    #[serde(rename = "xdg_config")]
+    XdgConfig,
+    /// `XDG_RUNTIME_DIR`, the last resort. Per-user, but cleared at logout.
+    #[serde(rename = "xdg_runtime")]
+    XdgRuntime,
+}
+
+/// Error text for when [`resolve_config_path`] finds nowhere to put the config.
+pub const NO_CONFIG_PATH: &str =
+    "No config path available (set MONITORCTL_CONFIG, or one of APPDATA, HOME, XDG_CONFIG_HOME)";
+
+pub fn resolve_config_path() -> Option<PathBuf> {
+    resolve_config_path_with_source().map(|(path, _source)| path)
+}
+
+pub fn resolve_config_path_with_source() -> Option<(PathBuf, ConfigSource)> {
+    if let Ok(p) = env::var("MONITORCTL_CONFIG") {
+        if !p.trim().is_empty() {
+            return Some((PathBuf::from(p), ConfigSource::Env));
+        }
+    }
+
+    let local = PathBuf::from("monitorctl.json");
+    if local.exists() {
+        return Some((local, ConfigSource::Local));
+    }
+
+    if let Some(appdata) = env::var_os("APPDATA") {
+        return Some((
+            PathBuf::from(appdata)
+                .join("monitorctl")
+                .join("config.json"),
+            ConfigSource::AppData,
+        ));
+    }
+
+    if let Some(home) = env::var_os("HOME") {
+        return Some((
+            PathBuf::from(home)
+                .join(".config")
+                .join("monitorctl")
+                .join("config.json"),
+            ConfigSource::Home,
+        ));
+    }
+
+    // Services/containers may run without APPDATA or HOME. Only per-user directories are
+    // considered after that: the config names programs to run (`editor`, `m1ddc_extra_args`),
+    // so it must never come from a shared, world-writable place like /tmp.
+    let xdg = [
+        ("XDG_CONFIG_HOME", ConfigSource::XdgConfig),
+        ("XDG_RUNTIME_DIR", ConfigSource::XdgRuntime),
+    ];
+    for (var, source) in xdg {
+        match env::var_os(var) {
+            Some(dir) if !dir.is_empty() => {
+                return Some((
+                    PathBuf::from(dir).join("monitorctl").join("config.json"),
+                    source,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+pub fn ensure_config_file_exists() -> Result<PathBuf> {
+    let Some(path) = resolve_config_path() else {
+        return Err(anyhow!(NO_CONFIG_PATH));
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("create config dir {}", parent.display()))?;
+    }
+
+    if !path.exists() {
+        let template = serde_json::json!({
+            "start_with_windows": false,
+            "inputs": default_inputs_map()
+        });
+        let mut s = serde_json::to_string_pretty(&template).context("serialize config template")?;
+        s.push('\n');
+        write_atomic(&path, s.as_bytes())?;
+    }
+
+    Ok(path)
+}
+
+pub fn patch_start_with_windows(enabled: bool) -> Result<PathBuf> {
+    let Some(path) = resolve_config_path() else {
+        return Err(anyhow!(NO_CONFIG_PATH));
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("create config dir {}", parent.display()))?;
+    }
+
+    let mut root = read_json_or_empty_object(&path)?;
+    let obj = root
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("config root must be a JSON object"))?;
+
+    obj.insert("start_with_windows".to_string(), Value::Bool(enabled));
+
+    let mut s = serde_json::to_string_pretty(&root).context("serialize config")?;
+    s.push('\n');
+    write_atomic(&path, s.as_bytes())?;
+    Ok(path)
+}
+
+/// Writes `bytes` to a temporary file next to `path` and renames it over `path`, so a crash
+/// mid-write leaves the old or the new file, never a truncated one.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, bytes).with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))
+}
+
+/// Moves a file that failed to parse to `<path>.corrupt`, replacing any earlier one, so it can
+/// be inspected instead of being overwritten. Returns the new location.
+pub(crate) fn set_aside(path: &Path) -> Option<PathBuf> {
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(".corrupt");
+    let aside = PathBuf::from(aside);
+    fs::rename(path, &aside).ok()?;
+    Some(aside)
+}
+
+fn read_json_or_empty_object(path: &Path) -> Result<Value> {
+    if !path.exists() {
+        return Ok(Value::Object(Default::default()));
+    }
+
+    let bytes = fs::read(path).with_context(|| format!("reading config {}", path.display()))?;
+    let v: Value =
+        serde_json::from_slice(&bytes).with_context(|| format!("parsing {}", path.display()))?;
+    Ok(v)
+}
+
+pub fn resolve(
+    config: Option<&Config>,
+    displays: &[DisplayInfo],
+    display_arg: Option<&str>,
+) -> ResolvedConfig {
+    let mut inputs: HashMap<String, u16> = HashMap::new();
+    let mut adjustments = HashMap::new();
+    let mut warnings = Vec::new();
+    let mut display_selector: Option<String> = display_arg.map(|s| s.to_string());
+
+    let Some(cfg) = config else {
+        return ResolvedConfig {
+            display_selector: backend_selector(
+                displays,
+                display_selector.unwrap_or_else(|| fallback_selector(config, displays)),
+            ),
+            inputs,
+            adjustments,
+            warnings,
+        };
+    };
+
+    merge_presets(&mut inputs, &mut adjustments, &cfg.inputs);
+    if display_selector.is_none() {
+        display_selector = cfg.default_display.clone();
+        // A stale `default_display` (say "3" with two monitors) would only fail later with a
+        // confusing selector error; fall through to `monitors` and the default instead. An
+        // empty list means enumeration failed, so there's nothing to check against.
+        if let Some(selector) = display_selector.as_deref() {
+            if !displays.is_empty()
+                && checkable_selector(selector)
+                && display_for_selector(displays, selector).is_none()
+            {
+                warnings.push(format!(
+                    "default_display '{selector}' is not connected; using another display"
+                ));
+                display_selector = None;
+            }
+        }
+    }
+
+    if display_selector.is_none() {
+        for mon_cfg in &cfg.monitors {
+            let Some((matched_display, inferred_selector)) = match_display(mon_cfg, displays)
+            else {
+                continue;
+            };
+
+            if let Some(explicit) = mon_cfg.display.as_deref() {
+                display_selector = Some(explicit.to_string());
+            } else {
+                display_selector = Some(inferred_selector);
+            }
+
+            merge_presets(&mut inputs, &mut adjustments, &mon_cfg.inputs);
+
+            let _ = matched_display;
+            break;
+        }
+    }
+
+    ResolvedConfig {
+        display_selector: backend_selector(
+            displays,
+            display_selector.unwrap_or_else(|| fallback_selector(config, displays)),
+        ),
+        inputs,
+        adjustments,
+        warnings,
+    }
+}
+
+/// Selector forms [`display_for_selector`] understands. Anything else is left for the backend
+/// to interpret, so it can't be called stale.
+fn checkable_selector(selector: &str) -> bool {
+    selector.parse::<u32>().is_ok()
+        || ["uuid:", "edid:", "name:", "connector:"]
+            .iter()
+            .any(|prefix| selector.starts_with(prefix))
+}
+
+/// Selector used when neither an argument nor the config picks a display: the first one that
+/// isn't a built-in panel (see [`is_internal`]), else `"1"`.
+fn fallback_selector(config: Option<&Config>, displays: &[DisplayInfo]) -> String {
+    displays
+        .iter()
+        .find(|d| !is_internal(config, d))
+        .map_or_else(|| "1".to_string(), |d| d.index.to_string())
+}
+
+/// Resolves the selector and presets for one specific display, applying the first `monitors`
+/// entry that matches it. The tray uses this to build one menu section per connected display.
+pub fn resolve_for_display(config: Option<&Config>, display: &DisplayInfo) -> ResolvedConfig {
+    let mut inputs: HashMap<String, u16> = HashMap::new();
+    let mut adjustments = HashMap::new();
+    let mut display_selector = selector_for_display(display);
+
+    let Some(cfg) = config else {
+        return ResolvedConfig {
+            display_selector,
+            inputs,
+            adjustments,
+            warnings: Vec::new(),
+        };
+    };
+
+    merge_presets(&mut inputs, &mut adjustments, &cfg.inputs);
+    for mon_cfg in &cfg.monitors {
+        if match_display(mon_cfg, std::slice::from_ref(display)).is_none() {
+            continue;
+        }
+        if let Some(explicit) = mon_cfg.display.as_deref() {
+            display_selector = explicit.to_string();
+        }
+        merge_presets(&mut inputs, &mut adjustments, &mon_cfg.inputs);
+        break;
+    }
+
+    ResolvedConfig {
+        display_selector,
+        inputs,
+        adjustments,
+        warnings: Vec::new(),
+    }
+}
+
+/// Turns profile `name` into concrete switches, one per entry whose `match` picks a connected
+/// display, with each input parsed against that display's presets. Entries that match nothing
+/// are skipped with a warning; an unknown profile or an invalid input fails before anything is
+/// switched.
+pub fn resolve_profile(
+    config: Option<&Config>,
+    displays: &[DisplayInfo],
+    name: &str,
+) -> Result<ResolvedProfile> {
+    let profiles = config.map(|c| &c.profiles);
+    let Some(entries) = profiles.and_then(|p| p.get(name)) else {
+        let mut known = profiles
+            .into_iter()
+            .flat_map(|p| p.keys().map(String::as_str))
+            .collect::<Vec<_>>();
+        known.sort_unstable();
+        let hint = if known.is_empty() {
+            "No profiles configured.".to_string()
+        } else {
+            let mut hint = format!("Known profiles: {}", known.join(", "));
+            if let Some(closest) = closest_preset(name, &known) {
+                hint.push_str(&format!(". Did you mean '{closest}'?"));
+            }
+            hint
+        };
+        bail!("Unknown profile '{name}'. {hint}");
+    };
+
+    let mut steps = Vec::new();
+    let mut warnings = Vec::new();
+    for entry in entries {
+        let Some(display) = find_matching(&entry.r#match, displays) else {
+            warnings.push(format!(
+                "profile '{name}': no connected display matches {}; skipped",
+                describe_match(&entry.r#match)
+            ));
+            continue;
+        };
+        let resolved = resolve_for_display(config, display);
+        let (value, adjust) = parse_input_preset(&entry.input, &resolved)
+            .with_context(|| format!("profile '{name}'"))?;
+        steps.push(ProfileStep {
+            display_selector: backend_selector(displays, resolved.display_selector),
+            input: entry.input.clone(),
+            value,
+            adjust,
+        });
+    }
+    Ok(ResolvedProfile { steps, warnings })
+}
+
+/// Name to show for `display`: a `display_names` entry if one matches, else the product name.
+pub fn display_name<'a>(config: Option<&'a Config>, display: &'a DisplayInfo) -> Option<&'a str> {
+    let names = config.map(|c| &c.display_names);
+    let keys = [
+        Some(display.index.to_string()),
+        display.serial.as_ref().map(|s| format!("serial:{s}")),
+        display.system_uuid.as_ref().map(|u| format!("uuid:{u}")),
+    ];
+    let configured = names.and_then(|names| {
+        keys.iter()
+            .flatten()
+            .find_map(|key| names.get(key).map(String::as_str))
+    });
+
+    configured.or_else(|| {
+        display
+            .product_name
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+    })
+}
+
+/// Whether `display` should be treated as a built-in panel: the first matching `monitors` entry
+/// with `internal` set wins, otherwise the backend's guess is used.
+pub fn is_internal(config: Option<&Config>, display: &DisplayInfo) -> bool {
+    let monitors = config.map(|c| c.monitors.as_slice()).unwrap_or_default();
+    monitors
+        .iter()
+        .filter(|m| match_display(m, std::slice::from_ref(display)).is_some())
+        .find_map(|m| m.internal)
+        .unwrap_or(display.internal)
+}
+
+/// The configured `default_input` of the monitor that `resolved` points at, parsed like a
+/// CLI value. Errors list the monitors that do have a default.
+pub fn default_input(
+    config: Option<&Config>,
+    displays: &[DisplayInfo],
+    resolved: &ResolvedConfig,
+) -> Result<(u16, PresetAdjustments)> {
+    let selector = resolved.display_selector.as_str();
+    let monitors = config.map(|c| c.monitors.as_slice()).unwrap_or_default();
+    let configured = monitor_for_selector(monitors, displays, selector);
+
+    if let Some(value) = configured.and_then(|m| m.default_input.as_deref()) {
+        return parse_input_preset(value, resolved)
+            .with_context(|| format!("default_input for display '{selector}'"));
+    }
+
+    let with_default = monitors
+        .iter()
+        .filter(|m| m.default_input.is_some())
+        .map(describe_monitor)
+        .collect::<Vec<_>>();
+    let hint = if with_default.is_empty() {
+        "No monitor has a default_input configured.".to_string()
+    } else {
+        format!("Monitors with a default_input: {}", with_default.join(", "))
+    };
+    Err(anyhow!(
+        "No default_input configured for display '{selector}'. {hint}"
+    ))
+}
+
+/// Whether a `monitors` entry with a `default_input` applies to `selector`.
+pub fn has_default_input(
+    config: Option<&Config>,
+    displays: &[DisplayInfo],
+    selector: &str,
+) -> bool {
+    let monitors = config.map(|c| c.monitors.as_slice()).unwrap_or_default();
+    monitor_for_selector(monitors, displays, selector).is_some_and(|m| m.default_input.is_some())
+}
+
+/// Whether switching the display addressed by `selector` should be confirmed first.
+pub fn confirm_switch(config: Option<&Config>, displays: &[DisplayInfo], selector: &str) -> bool {
+    let Some(cfg) = config else {
+        return false;
+    };
+    monitor_for_selector(&cfg.monitors, displays, selector)
+        .and_then(|m| m.confirm_switch)
+        .unwrap_or(cfg.confirm_switch)
+}
+
+/// The `monitors` entry that applies to `selector`: one pinned to it via `display`, or one
+/// whose `match` picks the display the selector addresses.
+fn monitor_for_selector<'a>(
+    monitors: &'a [MonitorConfig],
+    displays: &[DisplayInfo],
+    selector: &str,
+) -> Option<&'a MonitorConfig> {
+    let display = display_for_selector(displays, selector);
+    monitors.iter().find(|m| {
+        m.display.as_deref() == Some(selector)
+            || display.is_some_and(|d| match_display(m, std::slice::from_ref(d)).is_some())
+    })
+}
+
+fn describe_monitor(mon_cfg: &MonitorConfig) -> String {
+    if let Some(display) = mon_cfg.display.as_deref() {
+        return format!("display '{display}'");
+    }
+    describe_match(&mon_cfg.r#match)
+}
+
+fn describe_match(m: &MonitorMatch) -> String {
+    match (m.contains.as_deref(), m.index, m.connector.as_deref()) {
+        (Some(contains), Some(index), _) => format!("'{contains}' #{index}"),
+        (Some(contains), None, _) => format!("'{contains}'"),
+        (None, Some(index), _) => format!("#{index}"),
+        (None, None, Some(connector)) => format!("connector {connector}"),
+        (None, None, None) => "<any>".to_string(),
+    }
+}
+
+fn match_display<'a>(
+    mon_cfg: &MonitorConfig,
+    displays: &'a [DisplayInfo],
+) -> Option<(&'a DisplayInfo, String)> {
+    let d = find_matching(&mon_cfg.r#match, displays)?;
+    Some((d, selector_for_display(d)))
+}
 
-    let mut known = resolved
-        .inputs
-        .keys()
-        .map(|k| k.as_str())
-        .collect::<Vec<_>>();
+fn find_matching<'a>(m: &MonitorMatch, displays: &'a [DisplayInfo]) -> Option<&'a DisplayInfo> {
+    if let Some(idx) = m.index {
+        return displays.iter().find(|d| d.index == idx);
+    }
+    if let Some(connector) = m.connector.as_deref() {
+        return displays.iter().find(|d| has_connector(d, connector));
+    }
+
+    let needle = m.contains.as_deref()?;
+    let needle_lc = needle.to_ascii_lowercase();
+    displays.iter().find(|d| {
+        m.field
+            .haystacks(d)
+            .iter()
+            .any(|h| h.to_ascii_lowercase().contains(&needle_lc))
+    })
+}
+
+/// Best-effort mapping of a display selector back to an enumerated display.
+pub fn display_for_selector<'a>(
+    displays: &'a [DisplayInfo],
+    selector: &str,
+) -> Option<&'a DisplayInfo> {
+    if let Ok(idx) = selector.parse::<u32>() {
+        return displays.iter().find(|d| d.index == idx);
+    }
+    if let Some(uuid) = selector
+        .strip_prefix("uuid:")
+        .or_else(|| selector.strip_prefix("edid:"))
+    {
+        return displays.iter().find(|d| {
+            d.system_uuid
+                .as_deref()
+                .is_some_and(|u| u.eq_ignore_ascii_case(uuid))
+        });
+    }
+    if let Some(needle) = selector.strip_prefix("name:") {
+        let needle_lc = needle.trim().to_ascii_lowercase();
+        return displays.iter().find(|d| {
+            d.product_name
+                .as_deref()
+                .is_some_and(|n| n.to_ascii_lowercase().contains(&needle_lc))
+        });
+    }
+    if let Some(connector) = selector.strip_prefix("connector:") {
+        return displays.iter().find(|d| has_connector(d, connector));
+    }
+    None
+}
+
+fn has_connector(display: &DisplayInfo, connector: &str) -> bool {
+    display
+        .connector
+        .as_deref()
+        .is_some_and(|c| c.eq_ignore_ascii_case(connector.trim()))
+}
+
+/// Backends don't know connectors, so a `connector:` selector is handed to them as the index
+/// of the display it matches. Unmatched ones are passed through for the backend to reject.
+fn backend_selector(displays: &[DisplayInfo], selector: String) -> String {
+    if !selector.starts_with("connector:") {
+        return selector;
+    }
+    display_for_selector(displays, &selector).map_or(selector, |d| d.index.to_string())
+}
+
+/// Selectors of the other connected members of every `linked_groups` entry that contains the
+/// display addressed by `selector`. Members that aren't connected are skipped.
+pub fn linked_selectors(
+    groups: &[Vec<MonitorMatch>],
+    displays: &[DisplayInfo],
+    selector: &str,
+) -> Vec<String> {
+    let Some(origin) = display_for_selector(displays, selector) else {
+        return Vec::new();
+    };
+
+    let mut out: Vec<String> = Vec::new();
+    for group in groups {
+        let members = group
+            .iter()
+            .filter_map(|m| find_matching(m, displays))
+            .collect::<Vec<_>>();
+        if !members.iter().any(|d| d.index == origin.index) {
+            continue;
+        }
+        for d in members {
+            let sel = selector_for_display(d);
+            if d.index != origin.index && !out.contains(&sel) {
+                out.push(sel);
+            }
+        }
+    }
+    out
+}
+
+/// An id that follows the physical monitor across re-enumeration (unlike its index): the
+/// system UUID, else the serial. `None` when the backend reports neither.
+pub fn stable_id(display: &DisplayInfo) -> Option<String> {
+    if let Some(uuid) = display.system_uuid.as_deref() {
+        return Some(format!("uuid:{uuid}"));
+    }
+    display.serial.as_deref().map(|s| format!("serial:{s}"))
+}
+
+fn selector_for_display(display: &DisplayInfo) -> String {
+    if let Some(uuid) = display.system_uuid.as_deref() {
+        return format!("uuid:{uuid}");
+    }
+    display.index.to_string()
+}
+
+/// `value` as read from `get_input`, or `None` if the config lists it in
+/// `unknown_input_values`.
+pub fn known_input(config: Option<&Config>, value: u16) -> Option<u16> {
+    let unknown = config.is_some_and(|c| c.unknown_input_values.contains(&value));
+    (!unknown).then_some(value)
+}
+
+/// Reverse lookup of a raw value to a preset name. When several presets share the value, the
+/// alphabetically first one wins so output is stable.
+pub fn preset_for_value(inputs: &HashMap<String, u16>, value: u16) -> Option<&str> {
+    inputs
+        .iter()
+        .filter(|(_k, v)| **v == value)
+        .map(|(k, _v)| k.as_str())
+        .min()
+}
+
+/// The preset after the one whose value is `current`, in name order (the tray menu's), wrapping
+/// around. Presets repeating an earlier preset's value are skipped so the cycle always moves.
+/// Falls back to the first preset when `current` matches none (or couldn't be read).
+pub fn next_input(inputs: &HashMap<String, u16>, current: Option<u16>) -> Option<(&str, u16)> {
+    let mut presets: Vec<(&str, u16)> = inputs.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    presets.sort_unstable();
+    let mut seen = Vec::new();
+    presets.retain(|(_name, value)| {
+        let first = !seen.contains(value);
+        seen.push(*value);
+        first
+    });
+    let next = current
+        .and_then(|current| presets.iter().position(|(_name, v)| *v == current))
+        .map_or(0, |i| (i + 1) % presets.len());
+    presets.get(next).copied()
+}
+
+/// Like [`parse_input_value`], plus the adjustments of the preset `value` names (none for raw
+/// values).
+pub fn parse_input_preset(
+    value: &str,
+    resolved: &ResolvedConfig,
+) -> Result<(u16, PresetAdjustments)> {
+    let parsed = parse_input_value(value, resolved)?;
+    let adjust = match parse_vcp_value(value) {
+        Some(_) => PresetAdjustments::default(),
+        None => resolved.adjustments.get(value).copied().unwrap_or_default(),
+    };
+    Ok((parsed, adjust))
+}
+
+pub fn parse_input_value(value: &str, resolved: &ResolvedConfig) -> Result<u16> {
+    resolve_preset(&resolved.inputs, value, "input")
+}
+
+/// A brightness level from `monitorctl brightness`: a raw value or a `brightness_presets` name.
+pub fn parse_brightness_value(value: &str, config: Option<&Config>) -> Result<u16> {
+    let empty = HashMap::new();
+    let presets = config.map_or(&empty, |c| &c.brightness_presets);
+    resolve_preset(presets, value, "brightness")
+}
+
+/// `value` as a raw number (decimal or hex) or a name from `presets`. `kind` labels the error,
+/// which lists the known names and suggests the closest one.
+fn resolve_preset(presets: &HashMap<String, u16>, value: &str, kind: &str) -> Result<u16> {
+    if let Some(v) = parse_vcp_value(value) {
+        return Ok(v);
+    }
+
+    if let Some(v) = presets.get(value) {
+        return Ok(*v);
+    }
+
+    let mut known = presets.keys().map(|k| k.as_str()).collect::<Vec<_>>();
     known.sort_unstable();
     let hint = if known.is_empty() {
-        "No input presets configured.".to_string()
+        format!("No {kind} presets configured.")
     } else {
-        format!("Known presets: {}", known.join(", "))
+        let mut hint = format!("Known presets: {}", known.join(", "));
+        if let Some(closest) = closest_preset(value, &known) {
+            hint.push_str(&format!(". Did you mean '{closest}'?"));
+        }
+        hint
     };
     Err(anyhow!(
-        "Invalid input value '{value}'. Expected a number or a configured preset name. {hint}"
+        "Invalid {kind} value '{value}'. Expected a number (e.g. 27 or 0x1B) or a configured preset name. {hint}"
     ))
 }
+
+/// Nearest known preset by edit distance, if it's close enough to be a plausible typo.
+fn closest_preset<'a>(value: &str, known: &[&'a str]) -> Option<&'a str> {
+    let value_lc = value.to_ascii_lowercase();
+    let (best, distance) = known
+        .iter()
+        .map(|k| (*k, levenshtein(&value_lc, &k.to_ascii_lowercase())))
+        .min_by_key(|&(_k, d)| d)?;
+    let max_distance = (best.chars().count() / 3).clamp(1, 3);
+    (distance <= max_distance).then_some(best)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn resolved_with(inputs: &[(&str, u16)]) -> ResolvedConfig {
+        ResolvedConfig {
+            display_selector: "1".to_string(),
+            inputs: inputs.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+            adjustments: HashMap::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        assert_eq!(levenshtein("hdmi1", "hdmi1"), 0);
+        assert_eq!(levenshtein("", "dp1"), 3);
+        assert_eq!(levenshtein("dp1", ""), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("usbc", "usb_c"), 1);
+    }
+
+    #[test]
+    fn closest_preset_suggests_typos() {
+        let known = ["dp1", "hdmi1", "usb_c"];
+        assert_eq!(closest_preset("hdmi", &known), Some("hdmi1"));
+        assert_eq!(closest_preset("usbc", &known), Some("usb_c"));
+        assert_eq!(closest_preset("dp2", &known), Some("dp1"));
+    }
+
+    #[test]
+    fn closest_preset_ignores_case() {
+        assert_eq!(closest_preset("HDMI1", &["dp1", "hdmi1"]), Some("hdmi1"));
+        assert_eq!(closest_preset("Usb_C", &["usb_c"]), Some("usb_c"));
+    }
+
+    #[test]
+    fn closest_preset_rejects_distant_or_missing() {
+        assert_eq!(closest_preset("thunderbolt", &["dp1", "hdmi1"]), None);
+        assert_eq!(closest_preset("dp1", &[]), None);
+    }
+
+    #[test]
+    fn unknown_preset_errors_carry_hints() {
+        let e = parse_input_value("hdmi", &resolved_with(&[("hdmi1", 17)])).unwrap_err();
+        assert!(e.to_string().contains("Did you mean 'hdmi1'?"), "{e}");
+
+        let e = parse_input_value("hdmi", &resolved_with(&[])).unwrap_err();
+        assert!(
+            e.to_string().contains("No input presets configured."),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn parse_vcp_value_accepts_hex_and_decimal() {
+        let cases = [
+            ("0x1B", Some(0x1B)),
+            ("0X0f", Some(0x0F)),
+            (" 17 ", Some(17)),
+            ("0xFFFF", Some(u16::MAX)),
+            ("0x10000", None),
+            ("65536", None),
+            ("0x", None),
+            ("hdmi", None),
+            ("", None),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(parse_vcp_value(raw), expected, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn input_presets_accept_numbers_and_hex_strings() {
+        let config = parse(
+            br#"{ "inputs": { "dp1": 15, "usb_c": "0x1B", "hdmi1": "17", "max": 65535 } }"#,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(config.inputs["dp1"].value, 15);
+        assert_eq!(config.inputs["usb_c"].value, 0x1B);
+        assert_eq!(config.inputs["hdmi1"].value, 17);
+        assert_eq!(config.inputs["max"].value, u16::MAX);
+
+        for bad in [
+            &br#"{ "inputs": { "dp1": "0xZZ" } }"#[..],
+            br#"{ "inputs": { "dp1": "0x10000" } }"#,
+            br#"{ "inputs": { "dp1": 65536 } }"#,
+            br#"{ "inputs": { "dp1": -1 } }"#,
+            br#"{ "inputs": { "dp1": true } }"#,
+        ] {
+            let e = parse(bad, "test").unwrap_err();
+            assert!(format!("{e:#}").contains("dp1"), "{e:#}");
+        }
+    }
+
+    fn merged(layers: &[Value]) -> Value {
+        let mut base = serde_json::json!({});
+        for layer in layers {
+            merge_json(&mut base, layer.clone());
+        }
+        base
+    }
+
+    #[test]
+    fn merge_json_merges_nested_objects() {
+        let config = merged(&[
+            serde_json::json!({ "inputs": { "dp1": 15, "movie": { "value": 17, "brightness": 30 } } }),
+            serde_json::json!({ "inputs": { "usb_c": 27, "movie": { "volume": 60 } } }),
+        ]);
+        assert_eq!(
+            config,
+            serde_json::json!({ "inputs": {
+                "dp1": 15,
+                "usb_c": 27,
+                "movie": { "value": 17, "brightness": 30, "volume": 60 },
+            } })
+        );
+    }
+
+    #[test]
+    fn merge_json_appends_arrays() {
+        // Fragments add monitors rather than replacing the main file's (see `FRAGMENTS_DIR`).
+        let config = merged(&[
+            serde_json::json!({ "monitors": [{ "match": { "index": 1 } }], "unknown_input_values": [0] }),
+            serde_json::json!({ "monitors": [{ "match": { "index": 2 } }], "unknown_input_values": [255] }),
+        ]);
+        assert_eq!(
+            config["monitors"],
+            serde_json::json!([{ "match": { "index": 1 } }, { "match": { "index": 2 } }])
+        );
+        assert_eq!(config["unknown_input_values"], serde_json::json!([0, 255]));
+    }
+
+    #[test]
+    fn merge_json_later_fragments_win() {
+        let config = merged(&[
+            serde_json::json!({ "default_display": "1", "inputs": { "dp1": 15 }, "editor": "vim" }),
+            serde_json::json!({ "default_display": "2", "inputs": { "dp1": "0x10" } }),
+            serde_json::json!({ "default_display": "3", "editor": null }),
+        ]);
+        assert_eq!(config["default_display"], "3");
+        assert_eq!(config["inputs"]["dp1"], "0x10");
+        assert_eq!(config["editor"], Value::Null);
+    }
+
+    #[test]
+    fn encode_mccs_nibble_packs_boundaries() {
+        let cases = [
+            ((0, 0), 0x00),
+            ((1, 2), 0x12),
+            ((0, 15), 0x0F),
+            ((15, 0), 0xF0),
+            ((15, 15), 0xFF),
+        ];
+        for ((source, index), packed) in cases {
+            assert_eq!(
+                encode_mccs_nibble(source, index),
+                Ok(packed),
+                "{source}/{index}"
+            );
+        }
+    }
+
+    #[test]
+    fn encode_mccs_nibble_rejects_overflow() {
+        for (source, index) in [(16, 0), (0, 16), (255, 1), (u16::MAX, u16::MAX)] {
+            assert!(
+                encode_mccs_nibble(source, index).is_err(),
+                "{source}/{index}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_preset_packs_source_and_index() {
+        let cases = [
+            (serde_json::json!({ "source": 1, "index": 2 }), 0x12),
+            (serde_json::json!({ "source": 0, "index": 0 }), 0x00),
+            (serde_json::json!({ "source": 15, "index": 15 }), 0xFF),
+            (
+                serde_json::json!({ "source": 3, "index": 1, "brightness": 30 }),
+                0x31,
+            ),
+        ];
+        for (value, expected) in cases {
+            let preset = parse_preset(value.clone()).unwrap_or_else(|e| panic!("{value}: {e}"));
+            assert_eq!((preset.value, preset.packed), (expected, true), "{value}");
+        }
+        assert!(!parse_preset(serde_json::json!(0x12)).unwrap().packed);
+    }
+
+    #[test]
+    fn parse_preset_rejects_bad_source_and_index() {
+        let cases = [
+            serde_json::json!({ "source": 16, "index": 0 }),
+            serde_json::json!({ "source": 1, "index": 16 }),
+            serde_json::json!({ "source": 1 }),
+            serde_json::json!({ "source": -1, "index": 0 }),
+            serde_json::json!({ "source": "1", "index": 2 }),
+        ];
+        for value in cases {
+            assert!(parse_preset(value.clone()).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn packed_presets_need_mccs_nibble_encoding() {
+        let packed = br#"{ "inputs": { "pc": { "source": 1, "index": 2 } } }"#;
+        assert!(parse(packed, "test").is_err());
+
+        let allowed =
+            br#"{ "input_value_encoding": "mccs_nibble", "inputs": { "pc": { "source": 1, "index": 2 } } }"#;
+        let config = parse(allowed, "test").unwrap();
+        assert_eq!(config.inputs["pc"].value, 0x12);
+    }
+
+    fn presets(inputs: &[(&str, u16)]) -> HashMap<String, u16> {
+        inputs.iter().map(|&(k, v)| (k.to_string(), v)).collect()
+    }
+
+    #[test]
+    fn next_input_walks_name_order_and_wraps() {
+        let inputs = presets(&[("hdmi1", 17), ("dp1", 15), ("usb_c", 27)]);
+        assert_eq!(next_input(&inputs, Some(15)), Some(("hdmi1", 17)));
+        assert_eq!(next_input(&inputs, Some(17)), Some(("usb_c", 27)));
+        assert_eq!(next_input(&inputs, Some(27)), Some(("dp1", 15)));
+    }
+
+    #[test]
+    fn next_input_starts_over_for_unknown_current() {
+        let inputs = presets(&[("hdmi1", 17), ("dp1", 15)]);
+        assert_eq!(next_input(&inputs, Some(99)), Some(("dp1", 15)));
+        assert_eq!(next_input(&inputs, None), Some(("dp1", 15)));
+        assert_eq!(next_input(&HashMap::new(), Some(15)), None);
+    }
+
+    #[test]
+    fn next_input_skips_duplicate_values() {
+        // "dp_alt" repeats dp1's value; cycling from 15 must not land on 15 again.
+        let inputs = presets(&[("dp1", 15), ("dp_alt", 15), ("hdmi1", 17)]);
+        assert_eq!(next_input(&inputs, Some(15)), Some(("hdmi1", 17)));
+        assert_eq!(next_input(&inputs, Some(17)), Some(("dp1", 15)));
+
+        let single = presets(&[("a", 15), ("b", 15)]);
+        assert_eq!(next_input(&single, Some(15)), Some(("a", 15)));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let config = serde_json::json!({
+            "default_display": " ",
+            "brightness_presets": { "night": 70000 },
+            "unknown_input_values": [0, "x"],
+            "inputs": { "dp1": 15, "bad": "hdmi" },
+            "monitors": [
+                { "match": { "contains": "DELL" }, "inputs": { "worse": -1 } },
+                { "match": {} },
+            ],
+            "profiles": { "work": [{ "match": {} }] },
+        });
+        let message = validate(&config).unwrap_err().to_string();
+        let expected = [
+            "8 problem(s)",
+            "brightness_presets.night:",
+            "unknown_input_values[1]:",
+            "default_display:",
+            "inputs.bad:",
+            "monitors[0].inputs.worse:",
+            "monitors[1].match:",
+            "profiles.work[0].match:",
+            "profiles.work[0].input:",
+        ];
+        for needle in expected {
+            assert!(
+                message.contains(needle),
+                "missing {needle:?} in:\n{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_accepts_a_clean_config() {
+        let config = serde_json::json!({
+            "inputs": { "dp1": 15, "usb_c": "0x1B" },
+            "monitors": [{ "match": { "index": 1 }, "inputs": { "hdmi1": 17 } }],
+            "profiles": { "work": [{ "match": { "index": 1 }, "input": "dp1" }] },
+        });
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn parse_preset_reads_a_custom_vcp_code() {
+        let preset = parse_preset(serde_json::json!({ "value": "0x05", "vcp": 220 })).unwrap();
+        assert_eq!(preset.value, 5);
+        assert_eq!(preset.adjust.input_code(), 0xDC);
+
+        let plain = parse_preset(serde_json::json!(15)).unwrap();
+        assert_eq!(plain.adjust.input_code(), platform::VCP_INPUT);
+
+        for bad in [
+            serde_json::json!({ "value": 17, "vcp": 256 }),
+            serde_json::json!({ "value": 17, "vcp": "0x10" }),
+            serde_json::json!({ "vcp": 16 }),
+        ] {
+            assert!(parse_preset(bad.clone()).is_err(), "{bad}");
+        }
+    }
+
+    /// Serializes tests that change process-wide environment variables.
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Runs `f` with each of `vars` set (`Some`) or removed (`None`), then restores them.
+    pub(crate) fn with_env<T>(vars: &[(&str, Option<&str>)], f: impl FnOnce() -> T) -> T {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let saved = vars
+            .iter()
+            .map(|&(name, _)| (name, env::var_os(name)))
+            .collect::<Vec<_>>();
+        for &(name, value) in vars {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        let result = f();
+        for (name, value) in saved {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        result
+    }
+
+    /// An empty directory under the system temp dir, unique to this process and `name`.
+    pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("monitorctl-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Runs `f` with `MONITORCTL_CONFIG` pointing at a fresh `config.json` holding `contents`
+    /// (no file for `None`). Tray state lands next to it.
+    pub(crate) fn with_config<T>(
+        name: &str,
+        contents: Option<&str>,
+        f: impl FnOnce(&Path) -> T,
+    ) -> T {
+        let dir = scratch_dir(name);
+        let path = dir.join("config.json");
+        if let Some(contents) = contents {
+            fs::write(&path, contents).unwrap();
+        }
+        let result = with_env(&[("MONITORCTL_CONFIG", path.to_str())], || f(&path));
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
+    const PATH_VARS: [&str; 5] = [
+        "MONITORCTL_CONFIG",
+        "APPDATA",
+        "HOME",
+        "XDG_CONFIG_HOME",
+        "XDG_RUNTIME_DIR",
+    ];
+
+    fn config_path_with(set: &[(&str, &str)]) -> Option<(PathBuf, ConfigSource)> {
+        let vars = PATH_VARS
+            .iter()
+            .map(|&name| (name, set.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)))
+            .collect::<Vec<_>>();
+        with_env(&vars, resolve_config_path_with_source)
+    }
+
+    #[test]
+    fn config_path_follows_the_lookup_order() {
+        let all = [
+            ("MONITORCTL_CONFIG", "/env/monitorctl.json"),
+            ("APPDATA", "/appdata"),
+            ("HOME", "/home/me"),
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            ("XDG_RUNTIME_DIR", "/run/user/1000"),
+        ];
+        let expected = [
+            ("/env/monitorctl.json", ConfigSource::Env),
+            ("/appdata/monitorctl/config.json", ConfigSource::AppData),
+            (
+                "/home/me/.config/monitorctl/config.json",
+                ConfigSource::Home,
+            ),
+            (
+                "/xdg/config/monitorctl/config.json",
+                ConfigSource::XdgConfig,
+            ),
+            (
+                "/run/user/1000/monitorctl/config.json",
+                ConfigSource::XdgRuntime,
+            ),
+        ];
+        // Dropping the winning variable each time exposes the next rule.
+        for (skip, (path, source)) in expected.into_iter().enumerate() {
+            assert_eq!(
+                config_path_with(&all[skip..]),
+                Some((PathBuf::from(path), source))
+            );
+        }
+    }
+
+    #[test]
+    fn config_path_never_falls_back_to_the_temp_dir() {
+        assert_eq!(config_path_with(&[]), None);
+        assert_eq!(
+            config_path_with(&[("XDG_CONFIG_HOME", ""), ("MONITORCTL_CONFIG", "  ")]),
+            None
+        );
+        let e = with_env(
+            &PATH_VARS.map(|name| (name, None)),
+            ensure_config_file_exists,
+        )
+        .unwrap_err();
+        assert_eq!(e.to_string(), NO_CONFIG_PATH);
+    }
+
+    fn described(index: u32, name: &str, serial: &str, uuid: &str) -> DisplayInfo {
+        DisplayInfo {
+            serial: Some(serial.to_string()),
+            system_uuid: Some(uuid.to_string()),
+            ..crate::platform::mock::display(index, name)
+        }
+    }
+
+    fn matched(rule: Value, displays: &[DisplayInfo]) -> Option<u32> {
+        let rule: MonitorMatch = serde_json::from_value(rule).unwrap();
+        find_matching(&rule, displays).map(|d| d.index)
+    }
+
+    #[test]
+    fn contains_searches_the_selected_field() {
+        let displays = [
+            described(1, "DELL U2720Q", "ABC123", "uuid-1"),
+            described(2, "LG HDR", "XYZ789", "dell-uuid"),
+        ];
+        let cases = [
+            (serde_json::json!({ "contains": "dell" }), Some(1)),
+            (serde_json::json!({ "contains": "XYZ" }), None),
+            (
+                serde_json::json!({ "contains": "xyz", "field": "serial" }),
+                Some(2),
+            ),
+            (
+                serde_json::json!({ "contains": "abc1", "field": "serial" }),
+                Some(1),
+            ),
+            (
+                serde_json::json!({ "contains": "dell", "field": "uuid" }),
+                Some(2),
+            ),
+            (
+                serde_json::json!({ "contains": "U2720Q", "field": "uuid" }),
+                None,
+            ),
+            // `any` takes the first display with a hit in any field.
+            (
+                serde_json::json!({ "contains": "dell", "field": "any" }),
+                Some(1),
+            ),
+            (
+                serde_json::json!({ "contains": "789", "field": "any" }),
+                Some(2),
+            ),
+        ];
+        for (rule, expected) in cases {
+            assert_eq!(matched(rule.clone(), &displays), expected, "{rule}");
+        }
+    }
+
+    #[test]
+    fn contains_skips_displays_missing_the_field() {
+        let displays = [crate::platform::mock::display(1, "DELL")];
+        let rule = serde_json::json!({ "contains": "DELL", "field": "serial" });
+        assert_eq!(matched(rule, &displays), None);
+        assert!(
+            serde_json::from_value::<MonitorMatch>(serde_json::json!({ "field": "edid" })).is_err()
+        );
+    }
+
+    #[test]
+    fn m1ddc_args_env_overrides_config() {
+        let config = parse(br#"{ "m1ddc_extra_args": ["--verbose"] }"#, "test").unwrap();
+        let args = |value: Option<&str>, config: Option<&Config>| {
+            with_env(&[("MONITORCTL_M1DDC_ARGS", value)], || {
+                backend_options(config).m1ddc_extra_args
+            })
+        };
+        assert_eq!(args(None, Some(&config)), ["--verbose"]);
+        assert_eq!(args(None, None), Vec::<String>::new());
+        assert_eq!(args(Some(" -d  2 "), Some(&config)), ["-d", "2"]);
+        // Set but empty still wins, so the config's extras can be switched off per run.
+        assert_eq!(args(Some(""), Some(&config)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn linked_selectors_name_the_other_connected_members() {
+        let displays = [
+            described(1, "DELL U2720Q", "A1", "uuid-1"),
+            crate::platform::mock::display(2, "LG HDR"),
+            described(3, "BenQ", "B2", "uuid-3"),
+        ];
+        let group = |rules: Value| serde_json::from_value::<Vec<MonitorMatch>>(rules).unwrap();
+        let groups = [
+            group(serde_json::json!([
+                { "contains": "dell" },
+                { "index": 2 },
+                { "contains": "not connected" }
+            ])),
+            group(serde_json::json!([{ "index": 2 }, { "contains": "benq" }])),
+        ];
+
+        assert_eq!(linked_selectors(&groups, &displays, "1"), ["2"]);
+        // Display 2 sits in both groups; each partner is listed once, by UUID when known.
+        assert_eq!(
+            linked_selectors(&groups, &displays, "name:lg"),
+            ["uuid:uuid-1", "uuid:uuid-3"]
+        );
+        assert_eq!(linked_selectors(&groups, &displays, "uuid:uuid-3"), ["2"]);
+        assert!(linked_selectors(&groups, &displays, "4").is_empty());
+        assert!(linked_selectors(&[], &displays, "1").is_empty());
+    }
+
+    #[test]
+    fn config_can_be_read_from_a_reader() {
+        let config = load_from_reader(&br#"{ "inputs": { "dp1": 15 } }"#[..]).unwrap();
+        assert_eq!(config.inputs["dp1"].value, 15);
+
+        let e = load_from_reader(&b"{ \"inputs\": "[..]).unwrap_err();
+        assert!(format!("{e:#}").starts_with("parsing <stdin>"), "{e:#}");
+        assert!(load_from_reader(&b""[..]).is_err());
+    }
+
+    #[test]
+    fn display_arg_falls_back_to_the_env() {
+        let displays = [crate::platform::mock::display(1, "DELL")];
+        let arg = |cli: Option<&str>, env: Option<&str>| {
+            with_env(&[("MONITORCTL_DISPLAY", env)], || {
+                display_arg(None, &displays, cli.map(str::to_string))
+            })
+        };
+        assert_eq!(arg(None, None).unwrap(), None);
+        assert_eq!(arg(None, Some(" 2 ")).unwrap().as_deref(), Some("2"));
+        assert_eq!(arg(None, Some("  ")).unwrap(), None);
+        assert_eq!(arg(Some("3"), Some("2")).unwrap().as_deref(), Some("3"));
+        assert_eq!(arg(None, Some("any")).unwrap().as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn display_arg_errors_name_the_env_var() {
+        let arg = |cli: Option<&str>, env: Option<&str>| {
+            with_env(&[("MONITORCTL_DISPLAY", env)], || {
+                display_arg(None, &[], cli.map(str::to_string))
+                    .unwrap_err()
+                    .to_string()
+            })
+        };
+        assert_eq!(
+            arg(None, Some("all")),
+            "Display 'all' (from MONITORCTL_DISPLAY) only works with set-input"
+        );
+        assert_eq!(
+            arg(None, Some("any")),
+            "Display 'any' (from MONITORCTL_DISPLAY) needs exactly one external display, but none was found"
+        );
+        assert_eq!(
+            arg(Some("all"), Some("2")),
+            "Display 'all' only works with set-input"
+        );
+    }
+
+    #[test]
+    fn display_names_match_index_serial_or_uuid() {
+        let config = parse(
+            br#"{ "display_names": { "1": "Left", "serial:B2": "Middle", "uuid:uuid-3": "Right", "2": "Unused" } }"#,
+            "test",
+        )
+        .unwrap();
+        let name = |display: &DisplayInfo| display_name(Some(&config), display).map(str::to_string);
+        assert_eq!(
+            name(&described(1, "DELL", "A1", "uuid-1")),
+            Some("Left".into())
+        );
+        // The index key comes first; serial and UUID keys follow the monitor when it moves.
+        assert_eq!(
+            name(&described(2, "LG", "B2", "uuid-2")),
+            Some("Unused".into())
+        );
+        assert_eq!(
+            name(&described(5, "LG", "B2", "uuid-2")),
+            Some("Middle".into())
+        );
+        assert_eq!(
+            name(&described(6, "BenQ", "C3", "uuid-3")),
+            Some("Right".into())
+        );
+        assert_eq!(
+            name(&described(7, "BenQ", "C4", "uuid-4")),
+            Some("BenQ".into())
+        );
+        assert_eq!(name(&described(8, " ", "C5", "uuid-5")), None);
+        assert_eq!(
+            display_name(None, &crate::platform::mock::display(1, "DELL")),
+            Some("DELL")
+        );
+    }
+
+    #[test]
+    fn brightness_values_are_numbers_or_presets() {
+        let config = parse(
+            br#"{ "brightness_presets": { "day": 80, "night": 25 } }"#,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(parse_brightness_value("night", Some(&config)).unwrap(), 25);
+        assert_eq!(parse_brightness_value("40", Some(&config)).unwrap(), 40);
+        assert_eq!(parse_brightness_value("0x32", None).unwrap(), 50);
+
+        let e = parse_brightness_value("nght", Some(&config)).unwrap_err();
+        assert!(
+            e.to_string()
+                .ends_with("Known presets: day, night. Did you mean 'night'?"),
+            "{e}"
+        );
+        let e = parse_brightness_value("day", None).unwrap_err();
+        assert!(
+            e.to_string().ends_with("No brightness presets configured."),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn display_any_picks_the_sole_external_display() {
+        let panel = DisplayInfo {
+            internal: true,
+            ..crate::platform::mock::display(1, "Built-in Retina Display")
+        };
+        let dell = crate::platform::mock::display(2, "DELL");
+        let lg = crate::platform::mock::display(3, "LG");
+        let any = |displays: &[DisplayInfo]| {
+            with_env(&[("MONITORCTL_DISPLAY", None)], || {
+                display_arg(None, displays, Some("ANY".to_string()))
+            })
+        };
+
+        assert_eq!(
+            any(&[panel.clone(), dell.clone()]).unwrap().as_deref(),
+            Some("2")
+        );
+        let e = any(std::slice::from_ref(&panel)).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Display 'any' needs exactly one external display, but none was found"
+        );
+        let e = any(&[panel, dell, lg]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Display 'any' needs exactly one external display, but 2 were found: 2 (DELL), 3 (LG). Pass one of them to --display."
+        );
+    }
+
+    #[test]
+    fn a_truncated_config_is_set_aside_with_a_warning() {
+        with_config(
+            "recover-truncated",
+            Some(r#"{"inputs": {"mac": 15"#),
+            |path| {
+                let (config, warning) = load_recovering().unwrap();
+                assert!(config.is_none());
+                let aside = path.with_file_name("config.json.corrupt");
+                assert_eq!(
+                    fs::read_to_string(&aside).unwrap(),
+                    r#"{"inputs": {"mac": 15"#
+                );
+                assert!(!path.exists());
+                let warning = warning.unwrap();
+                assert!(warning.contains("EOF while parsing"), "{warning}");
+                assert!(warning.contains(&aside.display().to_string()), "{warning}");
+            },
+        );
+    }
+
+    #[test]
+    fn any_config_that_fails_to_parse_is_set_aside() {
+        for (name, contents) in [
+            ("recover-syntax", r#"{"inputs": {"mac": 15,}}"#),
+            ("recover-garbage", "\0\0\0\0"),
+            ("recover-types", r#"{"inputs": {"mac": "HDMI"}}"#),
+        ] {
+            with_config(name, Some(contents), |path| {
+                let (config, warning) = load_recovering().unwrap();
+                assert!(config.is_none(), "{name}");
+                assert!(warning.is_some(), "{name}");
+                assert!(!path.exists(), "{name}");
+                let aside = path.with_file_name("config.json.corrupt");
+                assert_eq!(fs::read_to_string(aside).unwrap(), contents, "{name}");
+            });
+        }
+    }
+
+    #[test]
+    fn a_good_config_is_loaded_and_left_in_place() {
+        with_config("recover-good", Some(r#"{"inputs": {"mac": 15}}"#), |path| {
+            let (config, warning) = load_recovering().unwrap();
+            assert_eq!(config.unwrap().inputs["mac"].value, 15);
+            assert!(warning.is_none());
+            assert!(path.exists());
+            assert!(!path.with_file_name("config.json.corrupt").exists());
+        });
+    }
+
+    #[test]
+    fn a_bad_fragment_is_reported_not_set_aside() {
+        with_config(
+            "recover-fragment",
+            Some(r#"{"inputs": {"mac": 15}}"#),
+            |path| {
+                let fragments = path.with_file_name(FRAGMENTS_DIR);
+                fs::create_dir(&fragments).unwrap();
+                fs::write(fragments.join("10-broken.json"), "{").unwrap();
+                assert!(load_recovering().is_err());
+                assert!(path.exists());
+            },
+        );
+    }
+
+    #[test]
+    fn atomic_writes_replace_the_file_and_leave_no_temp_file() {
+        let dir = scratch_dir("write-atomic");
+        let path = dir.join("config.json");
+        fs::write(&path, "old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn two_displays() -> [DisplayInfo; 2] {
+        [
+            crate::platform::mock::display(1, "DELL"),
+            crate::platform::mock::display(2, "LG"),
+        ]
+    }
+
+    #[test]
+    fn a_stale_default_display_falls_back_with_a_warning() {
+        let config = parse(br#"{ "default_display": "3" }"#, "test").unwrap();
+        let resolved = resolve(Some(&config), &two_displays(), None);
+        assert_eq!(resolved.display_selector, "1");
+        assert_eq!(
+            resolved.warnings,
+            ["default_display '3' is not connected; using another display"]
+        );
+        check_strict(Some(&config), &resolved.warnings).unwrap();
+    }
+
+    #[test]
+    fn strict_mode_fails_on_a_stale_default_display() {
+        let config = parse(br#"{ "default_display": "3", "strict": true }"#, "test").unwrap();
+        let resolved = resolve(Some(&config), &two_displays(), None);
+        let e = check_strict(Some(&config), &resolved.warnings).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "default_display '3' is not connected; using another display (strict mode)"
+        );
+
+        let connected = parse(br#"{ "default_display": "2", "strict": true }"#, "test").unwrap();
+        let resolved = resolve(Some(&connected), &two_displays(), None);
+        assert_eq!(resolved.display_selector, "2");
+        check_strict(Some(&connected), &resolved.warnings).unwrap();
+    }
+
+    #[test]
+    fn unknown_keys_are_warnings_unless_strict() {
+        let json = br#"{ "inputs": { "dp1": 15 }, "defualt_display": "2", "colour": "red" }"#;
+        let config = parse(json, "test").unwrap();
+        assert_eq!(
+            config.warnings,
+            [
+                "unknown config key 'colour' is ignored",
+                "unknown config key 'defualt_display' is ignored (did you mean 'default_display'?)",
+            ]
+        );
+        check_strict(Some(&config), &config.warnings).unwrap();
+
+        let mut strict = config.clone();
+        strict.strict = true;
+        let e = check_strict(Some(&strict), &strict.warnings).unwrap_err();
+        assert!(e.to_string().ends_with("(strict mode)"), "{e}");
+        assert!(parse(br#"{ "inputs": {}, "strict": false }"#, "test")
+            .unwrap()
+            .warnings
+            .is_empty());
+    }
+
+    #[test]
+    fn config_keys_come_from_the_struct() {
+        let keys = config_keys();
+        for key in ["inputs", "default_display", "monitors", "strict"] {
+            assert!(keys.contains(&key), "{key}");
+        }
+        assert!(!keys.contains(&"warnings"));
+    }
+
+    #[test]
+    fn writes_are_retried_by_default() {
+        with_env(&[("MONITORCTL_M1DDC_ARGS", None)], || {
+            let defaults = parse(b"{}", "test").unwrap();
+            let options = backend_options(Some(&defaults));
+            assert_eq!(options.retries, DEFAULT_RETRIES);
+            assert_eq!(options.retry_delay, Duration::from_millis(250));
+            assert_eq!(backend_options(None).retries, DEFAULT_RETRIES);
+
+            let off = parse(br#"{ "retries": 0 }"#, "test").unwrap();
+            assert_eq!(backend_options(Some(&off)).retries, 0);
+        });
+    }
+
+    #[test]
+    fn readable_inputs_leave_out_presets_on_their_own_vcp() {
+        let json = br#"{ "inputs": { "dp1": 15, "hdmi1": { "value": 17, "brightness": 80 }, "pip": { "vcp": 233, "value": 1 } } }"#;
+        let config = parse(json, "test").unwrap();
+        let resolved = resolve(Some(&config), &[], Some("1"));
+        assert_eq!(resolved.inputs.len(), 3);
+        assert_eq!(
+            resolved.readable_inputs(),
+            HashMap::from([("dp1".to_string(), 15), ("hdmi1".to_string(), 17)])
+        );
+        assert!(selects_input(&resolved.adjustments, "hdmi1"));
+        assert!(!selects_input(&resolved.adjustments, "pip"));
+    }
+}
@@ -1,3 +1,4 @@
 pub mod config;
 pub mod platform;
+pub mod startup;
 pub mod tray;
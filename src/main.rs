@@ -1,15 +1,103 @@
-use anyhow::{bail, Context, Result};
-use clap::{Parser, Subcommand};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::Display,
+    io::{self, BufRead, IsTerminal, Write},
+    path::PathBuf,
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use monitorctl::{config, platform};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use monitorctl::{config, platform, startup, tray};
 
 #[derive(Parser, Debug)]
 #[command(name = "monitorctl", version, about = "DDC/CI monitor input switcher")]
 struct Cli {
+    /// When to color success/error output. `auto` colors only terminals and honors `NO_COLOR`.
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Read the config JSON from stdin instead of `monitorctl.json` / `MONITORCTL_CONFIG`.
+    #[arg(long, global = true)]
+    config_stdin: bool,
+
+    /// Refuse every monitor write; only reads (`list`, `get-input`, `dump-vcp`, `doctor`, ...)
+    /// are allowed.
+    #[arg(long, global = true)]
+    probe_safe: bool,
+
+    /// Answer yes to every confirmation prompt (e.g. `set-input --confirm`). Without it,
+    /// prompts answer no when stdin isn't a terminal.
+    #[arg(short = 'y', long, global = true)]
+    assume_yes: bool,
+
+    /// Give up on any monitor operation that takes longer than this many milliseconds
+    /// (overrides `operation_timeout_ms`; `0` disables it).
+    #[arg(long, global = true, value_name = "MS")]
+    timeout: Option<u64>,
+
+    /// Fail on config problems that are otherwise only warned about, such as unknown keys or a
+    /// `default_display` that isn't connected (same as `"strict": true`).
+    #[arg(long, global = true)]
+    strict: bool,
+
     #[command(subcommand)]
     command: Command,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Output format for commands that read a value.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Just the value.
+    Plain,
+    /// `MONITORCTL_<NAME>=<value>` lines, suitable for `eval`.
+    Kv,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigAction {
+    /// Open the config (created from the template if missing) in `editor` / `$EDITOR`.
+    Edit,
+    /// Check the config and print "OK" or every problem found.
+    Validate,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum StateAction {
+    /// Print what the tray remembers.
+    Show,
+    /// Forget it; the tray re-captures its target on the next reload.
+    Clear,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum AutostartAction {
+    Enable,
+    Disable,
+    Status,
+}
+
+/// What multi-step commands do when one step fails.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OnError {
+    /// Abort at the first failing step.
+    Stop,
+    /// Attempt every step and report failures at the end.
+    Continue,
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Prints detected external displays (best-effort).
@@ -17,95 +105,2895 @@ enum Command {
         /// Show raw backend output too.
         #[arg(long)]
         raw: bool,
+        /// Print the detected displays as JSON.
+        #[arg(long, conflicts_with = "raw")]
+        json: bool,
+        /// Read the input of each display to check it answers DDC/CI (`ddc_responsive` in
+        /// the JSON). Slower, since it talks to every monitor.
+        #[arg(long)]
+        probe: bool,
+        /// Probe like `--probe` and leave out displays that don't answer. Best-effort: a
+        /// monitor can answer and still ignore writes. Backends that can't probe keep them all.
+        #[arg(long)]
+        connected_only: bool,
     },
-    /// Reads the current input source as raw VCP 0x60 value (Windows-only at the moment).
+    /// Reads the current input source as raw VCP 0x60 value (macOS needs a recent m1ddc).
     GetInput {
-        /// Display selector. On Windows this is a 1-based monitor index from `list`.
-        /// If omitted, `monitorctl.json` / config defaults may be used.
+        /// Display selector. On Windows this is a 1-based monitor index from `list`; "any"
+        /// picks the only external display.
+        /// If omitted, `MONITORCTL_DISPLAY` or `monitorctl.json` / config defaults may be used.
         #[arg(long)]
         display: Option<String>,
+        /// Output format. `kv` prints `MONITORCTL_INPUT=<n>` for `eval` in shell scripts.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+        /// Pick the display from a numbered list on the terminal.
+        #[arg(long, conflicts_with = "display")]
+        interactive: bool,
     },
     /// Sets input source to a raw VCP 0x60 value (e.g., 26 for USB-C on XG27ACS).
     SetInput {
         /// Display selector. On macOS this is passed through to `m1ddc display <selector> ...`.
-        /// Common values: "1", "uuid:<UUID>", "edid:<UUID>".
-        /// If omitted, `monitorctl.json` / config defaults may be used.
+        /// Common values: "1", "uuid:<UUID>", "edid:<UUID>", "connector:HDMI-1" (Windows, Linux), or
+        /// "any" for the only external display (errors if there are none or several), or "all"
+        /// to switch every external display.
+        /// If omitted, `MONITORCTL_DISPLAY` or `monitorctl.json` / config defaults may be used.
         #[arg(long)]
         display: Option<String>,
         /// Raw input value to set (VCP 0x60) OR a configured preset name (e.g. "dp1").
-        value: String,
+        #[arg(required_unless_present = "default")]
+        value: Option<String>,
+        /// Set the resolved monitor's configured `default_input` instead of a value.
+        #[arg(long, conflicts_with = "value")]
+        default: bool,
+        /// After writing, wait until the input has read back as the target for this many
+        /// milliseconds (tolerates KVMs that briefly report the old input). Backends that
+        /// can't read the input just sleep this long.
+        #[arg(long, value_name = "MS")]
+        settle: Option<u64>,
+        /// Ask for confirmation on the terminal before switching.
+        #[arg(long)]
+        confirm: bool,
+        /// Pick the display from a numbered list on the terminal.
+        #[arg(long, conflicts_with = "display")]
+        interactive: bool,
+        /// Give up waiting for `--settle` after this many milliseconds.
+        #[arg(long, value_name = "MS", default_value_t = 10_000, requires = "settle")]
+        settle_timeout: u64,
+        /// Print `{selector, code, value, verified}` instead of the bare value, or an array of
+        /// them when linked displays were switched too. `verified` means `--settle` read the
+        /// new input back.
+        #[arg(long)]
+        json: bool,
+        /// Switch linked displays concurrently (a few at a time) instead of one after another.
+        #[arg(long)]
+        parallel: bool,
+        /// With `--display all`: whether to switch the remaining displays after one fails.
+        /// The exit code is non-zero either way.
+        #[arg(long, value_enum, default_value_t = OnError::Continue)]
+        on_error: OnError,
+    },
+    /// Switches to the next configured input preset (in name order), wrapping around. Starts
+    /// from the first preset when the current input isn't one of them.
+    Cycle {
+        /// Display selector (same as `set-input`).
+        #[arg(long)]
+        display: Option<String>,
+    },
+    /// Switches between two inputs: to `b` when the display is on `a`, otherwise to `a`.
+    Toggle {
+        /// Display selector (same as `set-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// Preset name or raw value; chosen unless the display is already on it.
+        a: String,
+        /// Preset name or raw value to switch to from `a`.
+        b: String,
+    },
+    /// Applies a configured profile: switches each of its monitors to its input, in order.
+    /// Monitors that aren't connected are skipped with a warning.
+    Profile {
+        /// Name from `profiles` in the config (e.g. "work").
+        name: String,
+        /// Whether to switch the remaining monitors after one fails. The exit code is
+        /// non-zero either way.
+        #[arg(long, value_enum, default_value_t = OnError::Stop)]
+        on_error: OnError,
+    },
+    /// Sets brightness (VCP 0x10) to a raw value or a configured `brightness_presets` name.
+    Brightness {
+        /// Display selector (same as `set-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// Raw brightness value, or a preset name.
+        #[arg(required_unless_present = "preset")]
+        value: Option<String>,
+        /// Name from `brightness_presets` (e.g. "night"); same as passing it as the value.
+        #[arg(long, conflicts_with = "value")]
+        preset: Option<String>,
+    },
+    /// Reads the current brightness (VCP 0x10).
+    GetBrightness {
+        /// Display selector (same as `get-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// Output format. `kv` prints `MONITORCTL_BRIGHTNESS=<n>`.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+    /// Reads `selector<TAB>value` lines (or JSON objects) from stdin and sets each input.
+    ///
+    /// Displays are enumerated once for the whole batch. An empty selector falls back to
+    /// `monitorctl.json` / config defaults, like omitting `--display`.
+    Batch {
+        /// Whether to keep going after a failing entry. The exit code is non-zero either way.
+        #[arg(long, value_enum, default_value_t = OnError::Continue)]
+        on_error: OnError,
+    },
+    /// Polls the current input and prints a line whenever it changes (runs until Ctrl-C), or
+    /// with `--hotplug`, watches for displays being connected.
+    Watch {
+        /// Display selector (same as `get-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// Seconds between polls.
+        #[arg(long, default_value_t = 5)]
+        interval_secs: u64,
+        /// Emit one JSON object per event (`start`, `change`, `correction`, `connected`,
+        /// `error`) instead of human-readable text.
+        #[arg(long)]
+        json_lines: bool,
+        /// Keep the display on this input (raw value or preset): whenever a poll finds it
+        /// elsewhere, switch it back.
+        #[arg(long, value_name = "VALUE")]
+        apply: Option<String>,
+        /// With `--apply`, give up (exit non-zero) after this many corrections.
+        #[arg(long, default_value_t = 10, requires = "apply")]
+        max_corrections: u32,
+        /// With `--apply`, wait at least this many seconds between corrections.
+        #[arg(long, default_value_t = 10, requires = "apply")]
+        min_correction_secs: u64,
+        /// Instead of following one display's input, watch for displays being connected and
+        /// set each newly connected one to its configured `default_input`.
+        #[arg(long, conflicts_with_all = ["display", "apply"])]
+        hotplug: bool,
+    },
+    /// Reads several raw VCP codes in one pass and prints `code<TAB>current<TAB>max` lines.
+    DumpVcp {
+        /// Display selector (same as `get-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// Comma-separated VCP codes, decimal or hex.
+        #[arg(long, value_delimiter = ',', value_parser = parse_vcp_code, default_value = "0x10,0x12,0x60")]
+        codes: Vec<u8>,
+    },
+    /// Reads one raw VCP code and prints `current<TAB>max` (max is 0 when the backend can't tell).
+    GetVcp {
+        /// Display selector (same as `get-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// VCP code, decimal or hex (e.g. 18 or 0x12).
+        #[arg(value_parser = parse_vcp_code)]
+        code: u8,
+    },
+    /// Writes a raw value to any VCP code. Nothing checks that the monitor supports it.
+    SetVcp {
+        /// Display selector (same as `set-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// VCP code, decimal or hex (e.g. 98 or 0x62).
+        #[arg(value_parser = parse_vcp_code)]
+        code: u8,
+        /// Value to write, decimal or hex.
+        #[arg(value_parser = parse_vcp_raw_value)]
+        value: u16,
+    },
+    /// Prints the display's raw MCCS capabilities string and a summary of it, for sharing when
+    /// asking for help. On macOS, where m1ddc can't read it, prints `m1ddc display list
+    /// detailed` instead.
+    Caps {
+        /// Display selector (same as `get-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// Write to this file instead of stdout.
+        #[arg(long)]
+        out: Option<PathBuf>,
     },
     /// Checks local prerequisites and prints guidance.
-    Doctor,
-    /// Prints the config path that would be used (if any).
-    ConfigPath,
+    ///
+    /// Exit codes: 0 healthy, 2 backend tool missing, 3 no displays, 4 enumeration failed,
+    /// 5 no display answered the `--probe` read, 6 no access to the DDC/CI devices (Linux).
+    Doctor {
+        /// Print nothing; only set the exit code.
+        #[arg(long, short)]
+        quiet: bool,
+        /// Also read from every display (input on Windows, luminance on macOS) and report
+        /// which ones answer over DDC/CI. Never writes.
+        #[arg(long)]
+        probe: bool,
+        /// Print `{ok, status, message, probes, tool_path, get_input_supported, displays}` as
+        /// JSON. The exit code is the same as without it.
+        #[arg(long, conflicts_with = "quiet")]
+        json: bool,
+    },
+    /// Reads the current input, writes it back and reads again to verify DDC/CI end-to-end.
+    ///
+    /// Prints PASS/FAIL with per-step timings and exits non-zero on FAIL.
+    Selftest {
+        /// Display selector (same as `set-input`).
+        #[arg(long)]
+        display: Option<String>,
+        /// Input the display is currently on (raw value or preset). Only used when the backend
+        /// can't read the input, to run a write-only smoke test instead.
+        #[arg(long)]
+        value: Option<String>,
+    },
+    /// Times consecutive DDC reads and prints min/median/max/mean latency per display.
+    ///
+    /// Times `get-input`; on backends that can't read the input, times display enumeration
+    /// instead.
+    Bench {
+        /// Display selector (same as `get-input`). Benchmarks every display if omitted.
+        #[arg(long)]
+        display: Option<String>,
+        /// Number of timed calls per display.
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+    },
+    /// Prints the config path that would be used (errors if none is available).
+    ConfigPath {
+        /// Print `{path, source, exists}` as JSON, where `source` says which rule chose the
+        /// path (`env`, `local`, `appdata`, `home`, `xdg_config` or `xdg_runtime`).
+        #[arg(long)]
+        json: bool,
+    },
+    /// Works with the config file.
+    Config {
+        #[arg(value_enum)]
+        action: ConfigAction,
+    },
+    /// Shows or clears the tray's remembered state (`monitortray-state.json`): which physical
+    /// monitor an index selector like `default_display: "2"` was first resolved to, and the
+    /// recently used inputs.
+    State {
+        #[arg(value_enum)]
+        action: StateAction,
+        /// With `show`, print `{path, target_rule, target_id, target_name, recent_inputs}` as
+        /// JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Enables/disables starting `monitortray` at login without launching it, or shows the state.
+    ///
+    /// Registers the `monitortray` binary installed next to `monitorctl`.
+    Autostart {
+        #[arg(value_enum)]
+        action: AutostartAction,
+    },
+    /// Runs the tray app in the foreground (same as launching `monitortray`).
+    Tray,
+    /// Prints a shell completion script for subcommands and flags to stdout.
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
+
+fn main() -> ExitCode {
+    // Before anything is printed, including clap's usage errors: started from Explorer, the
+    // output would otherwise vanish with the window.
+    let console = platform::console::attach();
+    let (code, error) = match Cli::try_parse() {
+        Ok(cli) => execute(cli),
+        Err(e) => {
+            let _ = e.print();
+            let code = ExitCode::from(u8::try_from(e.exit_code()).unwrap_or(1));
+            (code, e.use_stderr().then(|| e.to_string()))
+        }
+    };
+    platform::console::finish(console, error.as_deref());
+    code
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+/// Runs the parsed command and prints its error. Returns the exit code and the error text.
+fn execute(cli: Cli) -> (ExitCode, Option<String>) {
+    let styles = Styles::new(cli.color);
 
-    match cli.command {
-        Command::List { raw } => {
-            let backend = platform::backend()?;
-            let report = backend.list_displays().context("list displays")?;
+    match run(
+        cli.command,
+        cli.config_stdin,
+        cli.probe_safe,
+        cli.assume_yes,
+        cli.timeout,
+        cli.strict,
+        styles,
+    ) {
+        Ok(()) => (ExitCode::SUCCESS, None),
+        Err(e) => match e.downcast_ref::<Exit>() {
+            Some(exit) => {
+                if let Some(message) = &exit.message {
+                    styles.error(format_args!("Error: {message}"));
+                }
+                (ExitCode::from(exit.code), exit.message.clone())
+            }
+            None => {
+                // Same text `fn main() -> Result<()>` would print, just colored.
+                styles.error(format_args!("Error: {e:?}"));
+                (ExitCode::FAILURE, Some(format!("{e:#}")))
+            }
+        },
+    }
+}
+
+/// `brightness` clamps to this; MCCS brightness is a 0-100 scale.
+const MAX_BRIGHTNESS: u16 = 100;
+
+fn run(
+    command: Command,
+    config_stdin: bool,
+    probe_safe: bool,
+    assume_yes: bool,
+    timeout: Option<u64>,
+    strict: bool,
+    styles: Styles,
+) -> Result<()> {
+    let load_config = || -> Result<Option<config::Config>> {
+        let mut config = if config_stdin {
+            Some(config::load_from_reader(io::stdin().lock())?)
+        } else {
+            let (config, warning) = config::load_recovering()?;
+            if let Some(warning) = warning {
+                styles.error(format_args!("warning: {warning}"));
+            }
+            config
+        };
+        if let Some(config) = config.as_mut() {
+            config.strict |= strict;
+            report_config_warnings(Some(config), &config.warnings, styles)?;
+        }
+        Ok(config)
+    };
+    // `config::resolve`, reporting the config problems it worked around.
+    let resolve = |cfg: Option<&config::Config>,
+                   displays: &[platform::DisplayInfo],
+                   display: Option<&str>| {
+        let resolved = config::resolve(cfg, displays, display);
+        report_config_warnings(cfg, &resolved.warnings, styles).map(|()| resolved)
+    };
+    let open_backend = |cfg: Option<&config::Config>| -> Result<Box<dyn platform::Backend>> {
+        let mut options = config::backend_options(cfg);
+        options.read_only = probe_safe;
+        if let Some(ms) = timeout {
+            options.operation_timeout = Duration::from_millis(ms);
+        }
+        platform::backend_with_options(options)
+    };
+
+    match command {
+        Command::List {
+            raw,
+            json,
+            probe,
+            connected_only,
+        } => {
+            let probe = probe || connected_only;
+            // Best-effort config: a broken config shouldn't block listing/diagnostics.
+            let cfg = load_config().ok().flatten();
+            let backend = open_backend(cfg.as_ref())?;
+            // Without `--probe` only names are needed: no DDC traffic, so skip opening monitor
+            // handles where we can.
+            let report = if probe {
+                backend.list_displays()
+            } else {
+                backend.list_displays_quick()
+            }
+            .context("list displays")?;
+            let mut displays = report.displays;
+            for d in &mut displays {
+                d.internal = config::is_internal(cfg.as_ref(), d);
+                if probe {
+                    d.ddc_responsive = backend.probe_ddc(&d.index.to_string());
+                }
+            }
+            if connected_only {
+                displays.retain(|d| d.ddc_responsive != Some(false));
+            }
+            if json {
+                let s = serde_json::to_string_pretty(&displays).context("serialize displays")?;
+                println!("{s}");
+                return Ok(());
+            }
             if raw {
                 if let Some(raw) = report.raw {
                     println!("{raw}");
                 }
             }
-            for d in report.displays {
+            for d in displays {
                 println!(
-                    "[{}] {} (system_uuid={})",
+                    "[{}] {} (system_uuid={}){}{}{}{}",
                     d.index,
-                    d.product_name.as_deref().unwrap_or("<unknown>"),
-                    d.system_uuid.as_deref().unwrap_or("<unknown>")
+                    config::display_name(cfg.as_ref(), &d).unwrap_or("<unknown>"),
+                    d.system_uuid.as_deref().unwrap_or("<unknown>"),
+                    format_mode(&d),
+                    d.connector
+                        .as_deref()
+                        .map(|c| format!(" via {c}"))
+                        .unwrap_or_default(),
+                    if d.internal { " (internal)" } else { "" },
+                    if d.ddc_responsive == Some(false) {
+                        " (no DDC/CI response)"
+                    } else {
+                        ""
+                    }
                 );
             }
         }
-        Command::SetInput { display, value } => {
-            let backend = platform::backend()?;
+        Command::SetInput {
+            display,
+            value,
+            settle,
+            settle_timeout,
+            confirm,
+            interactive,
+            json,
+            parallel,
+            on_error,
+            ..
+        } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
             let report = backend
                 .list_displays()
                 .context("list displays (for config)")?;
-            let cfg = config::load_optional()?;
-            let resolved = config::resolve(cfg.as_ref(), &report.displays, display.as_deref());
-            let value = config::parse_input_value(&value, &resolved)?;
-            backend
-                .set_input(&resolved.display_selector, value)
+            let display = if interactive {
+                Some(pick_display(cfg.as_ref(), &report.displays)?)
+            } else {
+                // Read here too so `MONITORCTL_DISPLAY=all` sweeps like `--display all`.
+                display.or_else(config::display_from_env)
+            };
+            if display.as_deref().is_some_and(config::is_all_displays) {
+                if settle.is_some() {
+                    bail!("--settle can't be combined with --display all");
+                }
+                let count = config::external_displays(cfg.as_ref(), &report.displays).len();
+                if confirm && count > 0 {
+                    let what = value.as_deref().unwrap_or("its default input");
+                    let prompt = format!("Switch all {count} displays to {what}?");
+                    if !ask_confirmation(&prompt, assume_yes)? {
+                        bail!("cancelled");
+                    }
+                }
+                return set_input_all(
+                    &*backend,
+                    cfg.as_ref(),
+                    &report.displays,
+                    value.as_deref(),
+                    SweepOptions {
+                        json,
+                        parallel,
+                        on_error,
+                    },
+                    styles,
+                );
+            }
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            // clap guarantees exactly one of `value` / `--default`.
+            let (value, adjust) = match value {
+                Some(value) => config::parse_input_preset(&value, &resolved)?,
+                None => config::default_input(cfg.as_ref(), &report.displays, &resolved)?,
+            };
+            let prompt = format!(
+                "Switch display '{}' to input {value}?",
+                resolved.display_selector
+            );
+            if confirm && !ask_confirmation(&prompt, assume_yes)? {
+                bail!("cancelled");
+            }
+            let code = adjust.input_code();
+            if settle.is_some() && code != platform::VCP_INPUT {
+                bail!(
+                    "--settle reads the input (VCP 0x60), but this preset writes VCP 0x{code:02X}"
+                );
+            }
+            platform::set_input_code(&*backend, &resolved.display_selector, code, value)
                 .with_context(|| {
                     format!(
                         "set input to {value} on display '{}'",
                         resolved.display_selector
                     )
                 })?;
-            println!("{value}");
+            let verified = match settle {
+                Some(settle) => wait_settled(
+                    &*backend,
+                    &resolved.display_selector,
+                    value,
+                    Duration::from_millis(settle),
+                    Duration::from_millis(settle_timeout),
+                )?,
+                None => false,
+            };
+            adjust.apply(&*backend, &resolved.display_selector)?;
+            if !json {
+                styles.ok(value);
+            }
+
+            let linked = set_linked(
+                &*backend,
+                cfg.as_ref(),
+                &report.displays,
+                &resolved.display_selector,
+                value,
+                adjust,
+                parallel,
+            );
+            let failed = linked.iter().filter(|(_sel, r)| r.is_err()).count();
+            let mut results = vec![SetResult::new(
+                &resolved.display_selector,
+                code,
+                value,
+                verified,
+            )];
+            for (selector, result) in linked {
+                match result {
+                    Ok(()) => results.push(SetResult::new(&selector, code, value, false)),
+                    Err(e) => styles.error(format_args!("linked display '{selector}': {e:#}")),
+                }
+            }
+            if json {
+                println!("{}", SetResult::to_json(&results)?);
+            }
+            if failed > 0 {
+                bail!("{failed} linked display(s) failed to switch");
+            }
         }
-        Command::GetInput { display } => {
-            let backend = platform::backend()?;
+        Command::GetInput {
+            display,
+            format,
+            interactive,
+        } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
             let report = backend
                 .list_displays()
                 .context("list displays (for config)")?;
-            let cfg = config::load_optional()?;
-            let resolved = config::resolve(cfg.as_ref(), &report.displays, display.as_deref());
+            let display = if interactive {
+                Some(pick_display(cfg.as_ref(), &report.displays)?)
+            } else {
+                display
+            };
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
             let value = backend
                 .get_input(&resolved.display_selector)
                 .with_context(|| format!("get input on display '{}'", resolved.display_selector))?;
-            println!("{value}");
+            print_value(format, "INPUT", config::known_input(cfg.as_ref(), value));
+        }
+        Command::Batch { on_error } => {
+            if config_stdin {
+                bail!(
+                    "--config-stdin can't be combined with batch, which reads entries from stdin"
+                );
+            }
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+
+            run_batch(
+                &*backend,
+                cfg.as_ref(),
+                &report.displays,
+                io::stdin().lock(),
+                on_error,
+                styles,
+            )?;
+        }
+        Command::Watch {
+            display,
+            interval_secs,
+            json_lines,
+            apply,
+            max_corrections,
+            min_correction_secs,
+            hotplug,
+        } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let interval = Duration::from_secs(interval_secs.max(1));
+            let emit = |event: WatchEvent<'_>| emit_watch_event(json_lines, styles, event);
+            if hotplug {
+                emit(WatchEvent::Start {
+                    display: "*",
+                    apply: None,
+                })?;
+                let mut known: Option<KnownDisplays> = None;
+                loop {
+                    let displays = match backend.list_displays() {
+                        Ok(report) => report.displays,
+                        Err(e) => {
+                            emit(WatchEvent::Error {
+                                display: "*",
+                                message: format!("list displays: {e:#}"),
+                            })?;
+                            thread::sleep(interval);
+                            continue;
+                        }
+                    };
+                    // The first poll only records what is already connected.
+                    let arrived = match known.as_mut() {
+                        Some(known) => known.update(&displays),
+                        None => {
+                            known = Some(KnownDisplays::new(&displays));
+                            Vec::new()
+                        }
+                    };
+                    for display in arrived {
+                        let selector = display.index.to_string();
+                        let name = config::display_name(cfg.as_ref(), display);
+                        let applied =
+                            if config::has_default_input(cfg.as_ref(), &displays, &selector) {
+                                let resolved = resolve(cfg.as_ref(), &displays, Some(&selector))?;
+                                let result =
+                                    config::default_input(cfg.as_ref(), &displays, &resolved)
+                                        .and_then(|(value, adjust)| {
+                                            platform::set_input_code(
+                                                &*backend,
+                                                &selector,
+                                                adjust.input_code(),
+                                                value,
+                                            )?;
+                                            adjust.apply(&*backend, &selector)?;
+                                            Ok(value)
+                                        });
+                                match result {
+                                    Ok(value) => Some(value),
+                                    Err(e) => {
+                                        emit(WatchEvent::Error {
+                                            display: &selector,
+                                            message: format!(
+                                            "apply default_input on display '{selector}': {e:#}"
+                                        ),
+                                        })?;
+                                        None
+                                    }
+                                }
+                            } else {
+                                None
+                            };
+                        emit(WatchEvent::Connected {
+                            display: &selector,
+                            name,
+                            applied,
+                        })?;
+                    }
+                    thread::sleep(interval);
+                }
+            }
+
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let selector = resolved.display_selector.as_str();
+            let desired = match apply {
+                Some(value) => {
+                    let (desired, adjust) = config::parse_input_preset(&value, &resolved)?;
+                    let code = adjust.input_code();
+                    if code != platform::VCP_INPUT {
+                        bail!("--apply reads the input (VCP 0x60), but '{value}' writes VCP 0x{code:02X}");
+                    }
+                    Some(desired)
+                }
+                None => None,
+            };
+            emit(WatchEvent::Start {
+                display: selector,
+                apply: desired,
+            })?;
+
+            let mut watcher = Watcher {
+                selector,
+                desired,
+                max_corrections,
+                min_correction: Duration::from_secs(min_correction_secs),
+                last: None,
+                corrections: 0,
+                last_correction: None,
+            };
+            // Only these can be recognized in a 0x60 reading.
+            let inputs = resolved.readable_inputs();
+            loop {
+                watcher.poll(&*backend, cfg.as_ref(), &inputs, &emit)?;
+                thread::sleep(interval);
+            }
+        }
+        Command::Cycle { display } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let selector = &resolved.display_selector;
+            let current = backend.get_input(selector).with_context(|| {
+                format!("cycle needs the current input of display '{selector}'")
+            })?;
+            let name = cycle_target(cfg.as_ref(), &resolved, current)?;
+            switch_preset(
+                &*backend,
+                cfg.as_ref(),
+                &report.displays,
+                &resolved,
+                &name,
+                styles,
+            )?;
         }
-        Command::Doctor => {
-            let backend = platform::backend()?;
-            let notes = backend.doctor().context("doctor")?;
-            if !notes.ok {
-                bail!(notes.message);
+        Command::Toggle { display, a, b } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let selector = &resolved.display_selector;
+            let target = toggle_target(&resolved, &a, &b, || {
+                backend.get_input(selector).with_context(|| {
+                    format!("toggle needs the current input of display '{selector}'")
+                })
+            })?;
+            switch_preset(
+                &*backend,
+                cfg.as_ref(),
+                &report.displays,
+                &resolved,
+                target,
+                styles,
+            )?;
+        }
+        Command::Profile { name, on_error } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for profile)")?;
+            let profile = config::resolve_profile(cfg.as_ref(), &report.displays, &name)?;
+            for warning in &profile.warnings {
+                styles.error(format_args!("warning: {warning}"));
+            }
+            if profile.steps.is_empty() {
+                bail!("profile '{name}': none of its monitors are connected");
+            }
+
+            let mut failed = 0;
+            for step in &profile.steps {
+                let selector = &step.display_selector;
+                let code = step.adjust.input_code();
+                let result = platform::set_input_code(&*backend, selector, code, step.value)
+                    .with_context(|| format!("set input to {}", step.value))
+                    .and_then(|()| step.adjust.apply(&*backend, selector));
+                match result {
+                    Ok(()) => styles.ok(format_args!(
+                        "ok\t{selector}\t{} ({})",
+                        step.input, step.value
+                    )),
+                    Err(e) => {
+                        styles.failed(format_args!("error\t{selector}\t{e:#}"));
+                        failed += 1;
+                        if on_error == OnError::Stop {
+                            break;
+                        }
+                    }
+                }
+            }
+            if failed > 0 {
+                bail!(
+                    "profile '{name}': {failed} of {} display(s) failed to switch",
+                    profile.steps.len()
+                );
+            }
+        }
+        Command::Brightness {
+            display,
+            value,
+            preset,
+        } => {
+            let cfg = load_config()?;
+            // clap guarantees exactly one of `value` / `--preset`.
+            let value = value.or(preset).unwrap_or_default();
+            let mut level = config::parse_brightness_value(&value, cfg.as_ref())?;
+            if level > MAX_BRIGHTNESS {
+                styles.error(format_args!(
+                    "warning: brightness {level} is out of range 0-{MAX_BRIGHTNESS}; using {MAX_BRIGHTNESS}"
+                ));
+                level = MAX_BRIGHTNESS;
+            }
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let selector = &resolved.display_selector;
+            backend
+                .set_vcp_raw(selector, platform::VCP_BRIGHTNESS, level)
+                .with_context(|| format!("set brightness on display '{selector}'"))?;
+
+            // Linked displays follow brightness like they follow the input.
+            let groups = cfg
+                .as_ref()
+                .map(|c| c.linked_groups.as_slice())
+                .unwrap_or_default();
+            let mut failed = 0;
+            for linked in config::linked_selectors(groups, &report.displays, selector) {
+                if let Err(e) = backend.set_vcp_raw(&linked, platform::VCP_BRIGHTNESS, level) {
+                    styles.error(format_args!("linked display '{linked}': {e:#}"));
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                bail!("{failed} linked display(s) failed to change brightness");
+            }
+        }
+        Command::GetBrightness { display, format } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let (level, _max) = backend
+                .get_vcp_raw(&resolved.display_selector, platform::VCP_BRIGHTNESS)
+                .with_context(|| {
+                    format!("get brightness on display '{}'", resolved.display_selector)
+                })?;
+            print_value(format, "BRIGHTNESS", Some(level));
+        }
+        Command::DumpVcp { display, codes } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let values = backend
+                .get_vcp_many(&resolved.display_selector, &codes)
+                .with_context(|| {
+                    format!("read VCP codes on display '{}'", resolved.display_selector)
+                })?;
+            for (code, current, maximum) in values {
+                println!("0x{code:02X}\t{current}\t{maximum}");
+            }
+        }
+        Command::GetVcp { display, code } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let (current, maximum) = backend
+                .get_vcp_raw(&resolved.display_selector, code)
+                .with_context(|| {
+                    format!(
+                        "read VCP 0x{code:02X} on display '{}'",
+                        resolved.display_selector
+                    )
+                })?;
+            println!("{current}\t{maximum}");
+        }
+        Command::SetVcp {
+            display,
+            code,
+            value,
+        } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            backend
+                .set_vcp_raw(&resolved.display_selector, code, value)
+                .with_context(|| {
+                    format!(
+                        "write VCP 0x{code:02X} on display '{}'",
+                        resolved.display_selector
+                    )
+                })?;
+            styles.ok(format_args!("0x{code:02X} = {value}"));
+        }
+        Command::Caps { display, out } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let raw = backend
+                .capabilities(&resolved.display_selector)
+                .with_context(|| {
+                    format!(
+                        "read capabilities of display '{}'",
+                        resolved.display_selector
+                    )
+                })?;
+            let mut text = format!("{}\n", raw.trim_end());
+            if let Some(summary) = capabilities_summary(&raw) {
+                text = format!("{text}\n{summary}");
+            }
+            match out {
+                Some(path) => {
+                    config::write_atomic(&path, text.as_bytes())?;
+                    styles.ok(format_args!("wrote {}", path.display()));
+                }
+                None => print!("{text}"),
+            }
+        }
+        Command::Doctor { quiet, probe, json } => {
+            // Best-effort config: a broken config shouldn't block listing/diagnostics.
+            let cfg = load_config().ok().flatten();
+            let silence = |e: anyhow::Error| -> anyhow::Error {
+                if quiet {
+                    Exit {
+                        code: 1,
+                        message: None,
+                    }
+                    .into()
+                } else {
+                    e
+                }
+            };
+            let backend = open_backend(cfg.as_ref()).map_err(silence)?;
+            let mut notes = backend.doctor().context("doctor").map_err(silence)?;
+            if probe && notes.ok() {
+                let probes = platform::probe_displays(&*backend)
+                    .context("probe displays")
+                    .map_err(silence)?;
+                add_probes(&mut notes, probes);
+            }
+            if json {
+                let mut displays = Vec::new();
+                if notes.ok() {
+                    if let Ok(report) = backend.list_displays() {
+                        displays = report.displays;
+                    }
+                }
+                for d in &mut displays {
+                    d.internal = config::is_internal(cfg.as_ref(), d);
+                }
+                let out = DoctorJson {
+                    ok: notes.ok(),
+                    report: &notes,
+                    displays,
+                };
+                let s = serde_json::to_string_pretty(&out).context("serialize doctor report")?;
+                println!("{s}");
+            }
+            if !notes.ok() {
+                let code = match notes.status {
+                    platform::DoctorStatus::Ok => 1,
+                    platform::DoctorStatus::ToolMissing => 2,
+                    platform::DoctorStatus::NoDisplays => 3,
+                    platform::DoctorStatus::EnumerationFailed => 4,
+                    platform::DoctorStatus::NoDdcResponse => 5,
+                    platform::DoctorStatus::NoDeviceAccess => 6,
+                };
+                return Err(Exit {
+                    code,
+                    message: (!quiet && !json).then_some(notes.message),
+                }
+                .into());
+            }
+            if quiet || json {
+                return Ok(());
+            }
+            styles.ok(notes.message);
+
+            if let Ok(report) = backend.list_displays() {
+                for d in report
+                    .displays
+                    .iter()
+                    .filter(|d| config::is_internal(cfg.as_ref(), d))
+                {
+                    println!(
+                        "note: display [{}] {} looks like a built-in panel; these usually can't switch inputs (override with `internal` in a monitors entry).",
+                        d.index,
+                        config::display_name(cfg.as_ref(), d).unwrap_or("<unknown>")
+                    );
+                }
+            }
+        }
+        Command::Selftest { display, value } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend
+                .list_displays()
+                .context("list displays (for config)")?;
+            let resolved = resolve(
+                cfg.as_ref(),
+                &report.displays,
+                config::display_arg(cfg.as_ref(), &report.displays, display)?.as_deref(),
+            )?;
+            let selector = resolved.display_selector.as_str();
+            let started = Instant::now();
+
+            let (read, took) = timed(|| backend.get_input(selector));
+            let (current, can_read) = match read {
+                Ok(current) => {
+                    println!("read: {current} ({took:?})");
+                    (current, true)
+                }
+                Err(e) => {
+                    println!("read: unavailable ({e:#})");
+                    let Some(value) = value else {
+                        styles.failed("FAIL: cannot read the current input; pass --value <current input> for a write-only smoke test");
+                        bail!("selftest failed on display '{selector}'");
+                    };
+                    (config::parse_input_value(&value, &resolved)?, false)
+                }
+            };
+
+            // Writing the input the display is already on is a no-op for the user.
+            let (write, took) = timed(|| backend.set_input(selector, current));
+            if let Err(e) = write {
+                styles.failed(format_args!("FAIL: write {current}: {e:#}"));
+                bail!("selftest failed on display '{selector}'");
+            }
+            println!("write: {current} ({took:?})");
+
+            if can_read {
+                let (read, took) = timed(|| backend.get_input(selector));
+                match read {
+                    Ok(v) if v == current => println!("read back: {v} ({took:?})"),
+                    Ok(v) => {
+                        styles.failed(format_args!("FAIL: read back {v}, expected {current}"));
+                        bail!("selftest failed on display '{selector}'");
+                    }
+                    Err(e) => {
+                        styles.failed(format_args!("FAIL: read back: {e:#}"));
+                        bail!("selftest failed on display '{selector}'");
+                    }
+                }
+            }
+
+            let mode = if can_read { "" } else { ", write-only" };
+            styles.ok(format_args!("PASS ({:?}{mode})", started.elapsed()));
+        }
+        Command::Bench {
+            display,
+            iterations,
+        } => {
+            let cfg = load_config()?;
+            let backend = open_backend(cfg.as_ref())?;
+            let report = backend.list_displays().context("list displays")?;
+            let selectors = match config::display_arg(cfg.as_ref(), &report.displays, display)? {
+                Some(display) => vec![display],
+                None => report
+                    .displays
+                    .iter()
+                    .map(|d| d.index.to_string())
+                    .collect(),
+            };
+
+            println!("display\top\tn\tmin\tmedian\tmax\tmean");
+            for selector in selectors {
+                let mut samples = Vec::new();
+                for _ in 0..iterations {
+                    let (read, took) = timed(|| backend.get_input(&selector));
+                    if let Err(e) = read {
+                        if samples.is_empty() {
+                            styles.error(format_args!(
+                                "display '{selector}': get-input unavailable ({e:#}), timing list"
+                            ));
+                            break;
+                        }
+                        return Err(e)
+                            .with_context(|| format!("get input on display '{selector}'"));
+                    }
+                    samples.push(took);
+                }
+                if samples.is_empty() {
+                    for _ in 0..iterations {
+                        let (list, took) = timed(|| backend.list_displays());
+                        list.context("list displays")?;
+                        samples.push(took);
+                    }
+                    println!("{selector}\tlist\t{}", latency_summary(&mut samples));
+                } else {
+                    println!("{selector}\tget-input\t{}", latency_summary(&mut samples));
+                }
             }
-            println!("{}", notes.message);
         }
-        Command::ConfigPath => {
-            if let Some(path) = config::resolve_config_path() {
+        Command::ConfigPath { json } => {
+            let Some((path, source)) = config::resolve_config_path_with_source() else {
+                bail!(config::NO_CONFIG_PATH);
+            };
+            if json {
+                let info = serde_json::json!({
+                    "path": path,
+                    "source": source,
+                    "exists": path.exists(),
+                });
+                println!("{info}");
+            } else {
                 println!("{}", path.display());
             }
         }
+        Command::Config {
+            action: ConfigAction::Edit,
+        } => {
+            let path = config::ensure_config_file_exists().context("ensure config exists")?;
+            // Best-effort config: a broken config is what people open the editor to fix.
+            let cfg = load_config().ok().flatten();
+            let editor = config::editor(cfg.as_ref()).or_else(|| env::var("EDITOR").ok());
+            let Some(editor) = editor.filter(|e| !e.trim().is_empty()) else {
+                return platform::open_path(&path);
+            };
+            let status = config::editor_command(&editor, &path)
+                .status()
+                .with_context(|| format!("launch editor '{editor}'"))?;
+            if !status.success() {
+                bail!("editor exited with {status}");
+            }
+        }
+        Command::Config {
+            action: ConfigAction::Validate,
+        } => match load_config()? {
+            Some(_) => styles.ok("OK"),
+            None => styles.ok("OK (no config file; using defaults)"),
+        },
+        Command::State {
+            action: StateAction::Clear,
+            ..
+        } => {
+            tray::state::clear()?;
+            styles.ok("state cleared");
+        }
+        Command::State {
+            action: StateAction::Show,
+            json,
+        } => {
+            // A missing file reads as the empty state.
+            let state = tray::state::load();
+            // Best-effort: the friendly name needs the display to be connected right now.
+            let target_name = state.target_id.as_deref().and_then(|id| {
+                let cfg = load_config().ok().flatten();
+                let report = open_backend(cfg.as_ref()).ok()?.list_displays().ok()?;
+                let display = report
+                    .displays
+                    .iter()
+                    .find(|d| config::stable_id(d).as_deref() == Some(id))?;
+                config::display_name(cfg.as_ref(), display).map(str::to_string)
+            });
+            if json {
+                let info = serde_json::json!({
+                    "path": tray::state::path(),
+                    "target_rule": state.target_rule,
+                    "target_id": state.target_id,
+                    "target_name": target_name,
+                    "recent_inputs": state.recent_inputs,
+                });
+                println!("{info}");
+            } else {
+                println!(
+                    "target_rule\t{}",
+                    state.target_rule.as_deref().unwrap_or("")
+                );
+                println!("target_id\t{}", state.target_id.as_deref().unwrap_or(""));
+                println!("target_name\t{}", target_name.as_deref().unwrap_or(""));
+                let recent = state
+                    .recent_inputs
+                    .iter()
+                    .map(|r| format!("{}={}", r.selector, r.value))
+                    .collect::<Vec<_>>();
+                println!("recent_inputs\t{}", recent.join(","));
+            }
+        }
+        Command::Completions { shell } => {
+            // The binary name is fixed so the script registers for `monitorctl` even when
+            // invoked through a renamed or `cargo run` binary.
+            clap_complete::generate(shell, &mut Cli::command(), "monitorctl", &mut io::stdout());
+        }
+        Command::Autostart { action } => {
+            match action {
+                AutostartAction::Enable => startup::set_enabled(true)?,
+                AutostartAction::Disable => startup::set_enabled(false)?,
+                AutostartAction::Status => {}
+            }
+            let state = if startup::is_enabled()? {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            styles.ok(format_args!("autostart: {state}"));
+        }
+        Command::Tray => {
+            if probe_safe {
+                bail!("--probe-safe forbids writes, and the tray exists to switch inputs");
+            }
+            tray::run()?
+        }
     }
 
     Ok(())
 }
+
+/// Ends the process with a specific exit code instead of the generic 1, optionally printing
+/// `message` like any other error.
+#[derive(Debug)]
+struct Exit {
+    code: u8,
+    message: Option<String>,
+}
+
+impl std::fmt::Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => f.write_str(message),
+            None => write!(f, "exit code {}", self.code),
+        }
+    }
+}
+
+impl std::error::Error for Exit {}
+
+/// Resolved `--color` decision for each stream. Success/error messages go through here so the
+/// text is identical with or without color; only the ANSI wrapping differs.
+#[derive(Debug, Clone, Copy)]
+struct Styles {
+    stdout: bool,
+    stderr: bool,
+}
+
+impl Styles {
+    const GREEN: &'static str = "32";
+    const RED: &'static str = "31";
+
+    fn new(choice: ColorChoice) -> Self {
+        // https://no-color.org: any non-empty value disables color (unless forced with `always`).
+        let no_color = matches!(std::env::var_os("NO_COLOR"), Some(v) if !v.is_empty());
+        let enabled = |is_tty: bool| match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_tty && !no_color,
+        };
+        Self {
+            stdout: enabled(io::stdout().is_terminal()),
+            stderr: enabled(io::stderr().is_terminal()),
+        }
+    }
+
+    /// A successful result on stdout (green).
+    fn ok(&self, msg: impl Display) {
+        println!("{}", paint(self.stdout, Self::GREEN, msg));
+    }
+
+    /// A per-item failure that is part of the normal stdout report, e.g. in `batch` (red).
+    fn failed(&self, msg: impl Display) {
+        println!("{}", paint(self.stdout, Self::RED, msg));
+    }
+
+    /// A diagnostic on stderr (red).
+    fn error(&self, msg: impl Display) {
+        eprintln!("{}", paint(self.stderr, Self::RED, msg));
+    }
+}
+
+fn paint(enabled: bool, code: &str, msg: impl Display) -> String {
+    if enabled {
+        format!("\x1b[{code}m{msg}\x1b[0m")
+    } else {
+        msg.to_string()
+    }
+}
+
+/// Top-level `key(value)` fields of an MCCS capabilities string such as
+/// `(prot(monitor)type(lcd)model(U2720Q)vcp(10 12 60(0F 11))mccs_ver(2.1))`.
+fn capabilities_fields(raw: &str) -> Vec<(&str, &str)> {
+    let raw = raw.trim();
+    let body = raw
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(raw);
+    let mut fields = Vec::new();
+    let (mut depth, mut key_start, mut value_start) = (0usize, 0, 0);
+    for (i, c) in body.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    value_start = i + 1;
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    fields.push((
+                        body[key_start..value_start - 1].trim(),
+                        &body[value_start..i],
+                    ));
+                    key_start = i + 1;
+                }
+            }
+            // Stray closing parenthesis: drop it rather than folding it into the next key.
+            ')' => key_start = i + 1,
+            _ => {}
+        }
+    }
+    fields
+}
+
+/// Entries of a `vcp(...)` field as `(code, allowed values)`; `60(0F 11)` lists the inputs.
+fn capabilities_vcp(vcp: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut entries: Vec<(&str, Vec<&str>)> = Vec::new();
+    let mut rest = vcp;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        rest = &rest[start..];
+        if let Some(values) = rest.strip_prefix('(') {
+            let end = values.find(')').unwrap_or(values.len());
+            if let Some((_, allowed)) = entries.last_mut() {
+                allowed.extend(values[..end].split_whitespace());
+            }
+            rest = values.get(end + 1..).unwrap_or("");
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '(')
+                .unwrap_or(rest.len());
+            entries.push((&rest[..end], Vec::new()));
+            rest = &rest[end..];
+        }
+    }
+    entries
+}
+
+/// Human-readable digest of an MCCS capabilities string, or `None` if it doesn't parse as one.
+fn capabilities_summary(raw: &str) -> Option<String> {
+    let fields = capabilities_fields(raw);
+    let mut lines = Vec::new();
+    for (key, label) in [
+        ("model", "Model"),
+        ("type", "Type"),
+        ("mccs_ver", "MCCS version"),
+    ] {
+        if let Some((_, value)) = fields.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+            lines.push(format!("{label}: {value}"));
+        }
+    }
+    if let Some((_, vcp)) = fields.iter().find(|(k, _)| k.eq_ignore_ascii_case("vcp")) {
+        let entries = capabilities_vcp(vcp);
+        let codes = entries.iter().map(|(code, _)| *code).collect::<Vec<_>>();
+        lines.push(format!("VCP codes: {}", codes.join(" ")));
+        if let Some((_, inputs)) = entries
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case("60"))
+        {
+            let inputs = inputs
+                .iter()
+                .map(|v| match u16::from_str_radix(v, 16) {
+                    Ok(n) => format!("{n} (0x{v})"),
+                    Err(_) => v.to_string(),
+                })
+                .collect::<Vec<_>>();
+            lines.push(format!("Inputs (VCP 0x60): {}", inputs.join(", ")));
+        }
+    }
+    (!lines.is_empty()).then(|| lines.join("\n") + "\n")
+}
+
+fn parse_vcp_code(s: &str) -> Result<u8> {
+    config::parse_vcp_value(s)
+        .and_then(|v| u8::try_from(v).ok())
+        .ok_or_else(|| anyhow!("invalid VCP code '{s}' (expected 0-255, e.g. 96 or 0x60)"))
+}
+
+fn parse_vcp_raw_value(s: &str) -> Result<u16> {
+    config::parse_vcp_value(s)
+        .ok_or_else(|| anyhow!("invalid VCP value '{s}' (expected 0-65535, decimal or hex)"))
+}
+
+/// Prints the numbered display list on stderr and reads a choice from the terminal. Returns
+/// the chosen display's index as a selector.
+fn pick_display(
+    cfg: Option<&config::Config>,
+    displays: &[platform::DisplayInfo],
+) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        bail!("--interactive needs a terminal on stdin; pass --display instead");
+    }
+    if displays.is_empty() {
+        bail!("No displays found to choose from.");
+    }
+
+    for d in displays {
+        eprintln!(
+            "[{}] {}{}",
+            d.index,
+            config::display_name(cfg, d).unwrap_or("<unknown>"),
+            format_mode(d)
+        );
+    }
+    loop {
+        eprint!("Display: ");
+        io::stderr().flush().context("flush stderr")?;
+        let mut answer = String::new();
+        if io::stdin()
+            .read_line(&mut answer)
+            .context("read display choice")?
+            == 0
+        {
+            bail!("no display chosen");
+        }
+        match answer.trim().parse::<u32>() {
+            Ok(index) if displays.iter().any(|d| d.index == index) => return Ok(index.to_string()),
+            _ => eprintln!("Enter one of the numbers in brackets."),
+        }
+    }
+}
+
+/// Adds `doctor --probe` results to `notes`: a line per display, and `NoDdcResponse` when
+/// displays were probed but none answered.
+fn add_probes(notes: &mut platform::DoctorReport, probes: Vec<platform::DdcProbe>) {
+    let answered = probes.iter().any(|p| p.responsive == Some(true));
+    let silent = probes.iter().any(|p| p.responsive == Some(false));
+    let lines = probes
+        .iter()
+        .map(|p| {
+            let result = match p.responsive {
+                Some(true) => "DDC/CI OK",
+                Some(false) => "no DDC/CI response",
+                None => "not probed (backend can't read)",
+            };
+            let name = p.product_name.as_deref().unwrap_or("<unknown>");
+            format!("[{}] {name}: {result}", p.index)
+        })
+        .collect::<Vec<_>>();
+    notes.probes = probes;
+    notes.message = format!("{}\n\nProbe:\n{}", notes.message, lines.join("\n"));
+    if silent && !answered {
+        notes.status = platform::DoctorStatus::NoDdcResponse;
+        notes.message = format!(
+            "{}\n\nNo display answered a DDC/CI read; check that DDC/CI is enabled in the monitor's OSD.",
+            notes.message
+        );
+    }
+}
+
+/// Prints config `warnings`, or fails with them in strict mode (see `config::check_strict`).
+fn report_config_warnings(
+    cfg: Option<&config::Config>,
+    warnings: &[String],
+    styles: Styles,
+) -> Result<()> {
+    config::check_strict(cfg, warnings)?;
+    for warning in warnings {
+        styles.error(format_args!("warning: {warning}"));
+    }
+    Ok(())
+}
+
+/// Asks `prompt` on the terminal. `assume_yes` (`-y`) answers yes without reading anything; a
+/// stdin that isn't a terminal answers no, so scripts never block on a prompt.
+fn ask_confirmation(prompt: &str, assume_yes: bool) -> Result<bool> {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    confirm_from(prompt, assume_yes, interactive, &mut stdin.lock())
+}
+
+/// [`ask_confirmation`] reading the answer from `input`, which only counts as a terminal when
+/// `interactive` says so.
+fn confirm_from(
+    prompt: &str,
+    assume_yes: bool,
+    interactive: bool,
+    input: &mut dyn BufRead,
+) -> Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+    if !interactive {
+        return Ok(false);
+    }
+    eprint!("{prompt} [y/N] ");
+    io::stderr().flush().context("flush stderr")?;
+    let mut answer = String::new();
+    input.read_line(&mut answer).context("read confirmation")?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Polls until `selector` has read back `value` continuously for `settle`. A read that flips
+/// back to another input restarts the clock; a backend without `get_input` just sleeps.
+/// Returns whether the value was actually read back.
+fn wait_settled(
+    backend: &dyn platform::Backend,
+    selector: &str,
+    value: u16,
+    settle: Duration,
+    timeout: Duration,
+) -> Result<bool> {
+    const POLL: Duration = Duration::from_millis(100);
+
+    let started = Instant::now();
+    let mut stable_since: Option<Instant> = None;
+    let mut last_seen: Option<u16> = None;
+    loop {
+        match backend.get_input(selector) {
+            Ok(v) if v == value => {
+                let since = *stable_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= settle {
+                    return Ok(true);
+                }
+            }
+            Ok(v) => {
+                stable_since = None;
+                last_seen = Some(v);
+            }
+            Err(_) if last_seen.is_none() && stable_since.is_none() => {
+                thread::sleep(settle);
+                return Ok(false);
+            }
+            Err(e) => return Err(e).context("read back input while settling"),
+        }
+
+        if started.elapsed() >= timeout {
+            let seen = last_seen.map_or("nothing".to_string(), |v| v.to_string());
+            bail!(
+                "input on display '{selector}' did not settle at {value} within {timeout:?} (last read {seen})"
+            );
+        }
+        thread::sleep(POLL);
+    }
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let started = Instant::now();
+    let out = f();
+    (out, started.elapsed())
+}
+
+/// `n  min  median  max  mean` (tab-separated, milliseconds) for a non-empty sample set.
+fn latency_summary(samples: &mut [Duration]) -> String {
+    samples.sort_unstable();
+    let ms = |d: Duration| format!("{:.1}ms", d.as_secs_f64() * 1000.0);
+    let n = samples.len();
+    let median = (samples[(n - 1) / 2] + samples[n / 2]) / 2;
+    let mean = samples.iter().sum::<Duration>() / n as u32;
+    format!(
+        "{n}\t{}\t{}\t{}\t{}",
+        ms(samples[0]),
+        ms(median),
+        ms(samples[n - 1]),
+        ms(mean)
+    )
+}
+
+/// Most displays `set-input --parallel` switches at once.
+const MAX_PARALLEL_WRITES: usize = 4;
+
+/// Runs `f` for each selector: one after another, or with `parallel` up to
+/// [`MAX_PARALLEL_WRITES`] at a time on scoped threads sharing the backend (`Backend` is
+/// `Send + Sync`; the Windows backend opens its monitor handles per call). Results keep the
+/// `selectors` order either way.
+fn for_each_display<T: Send>(
+    selectors: Vec<String>,
+    parallel: bool,
+    f: impl Fn(&str) -> Result<T> + Sync,
+) -> Vec<(String, Result<T>)> {
+    if !parallel {
+        return selectors
+            .into_iter()
+            .map(|sel| {
+                let result = f(&sel);
+                (sel, result)
+            })
+            .collect();
+    }
+
+    let mut results = Vec::with_capacity(selectors.len());
+    for chunk in selectors.chunks(MAX_PARALLEL_WRITES) {
+        thread::scope(|scope| {
+            let handles = chunk
+                .iter()
+                .map(|sel| scope.spawn(|| f(sel)))
+                .collect::<Vec<_>>();
+            for (sel, handle) in chunk.iter().zip(handles) {
+                let result = handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow!("switching display '{sel}' panicked")));
+                results.push((sel.clone(), result));
+            }
+        });
+    }
+    results
+}
+
+/// Mirrors a successful switch, preset adjustments included, onto the other members of any
+/// `linked_groups` the target is in, concurrently with `parallel` (see [`for_each_display`]).
+fn set_linked(
+    backend: &dyn platform::Backend,
+    cfg: Option<&config::Config>,
+    displays: &[platform::DisplayInfo],
+    selector: &str,
+    value: u16,
+    adjust: config::PresetAdjustments,
+    parallel: bool,
+) -> Vec<(String, Result<()>)> {
+    let groups = cfg.map(|c| c.linked_groups.as_slice()).unwrap_or_default();
+    let selectors = config::linked_selectors(groups, displays, selector);
+    for_each_display(selectors, parallel, |sel| {
+        platform::set_input_code(backend, sel, adjust.input_code(), value)
+            .with_context(|| format!("set input to {value} on display '{sel}'"))?;
+        adjust.apply(backend, sel)
+    })
+}
+
+/// How `set-input --display all` reports and paces its writes.
+#[derive(Debug, Clone, Copy)]
+struct SweepOptions {
+    /// Print the successes as a JSON array on stdout; failures go to stderr.
+    json: bool,
+    /// Switch up to [`MAX_PARALLEL_WRITES`] displays at once.
+    parallel: bool,
+    /// `Stop` leaves the displays after a failure alone. In parallel, the writes already
+    /// started alongside the failing one still finish.
+    on_error: OnError,
+}
+
+/// `set-input --display all`: sets `value` (or, without one, each display's `default_input`)
+/// on every external display, resolving presets per display. Prints an `ok`/`error` line per
+/// display and fails if any did.
+fn set_input_all(
+    backend: &dyn platform::Backend,
+    cfg: Option<&config::Config>,
+    displays: &[platform::DisplayInfo],
+    value: Option<&str>,
+    options: SweepOptions,
+    styles: Styles,
+) -> Result<()> {
+    let SweepOptions {
+        json,
+        parallel,
+        on_error,
+    } = options;
+    let targets = config::external_displays(cfg, displays);
+    if targets.is_empty() {
+        bail!(
+            "Display '{}' found no external displays",
+            config::ALL_DISPLAYS
+        );
+    }
+    // Presets are resolved per display up front; only the writes may run concurrently.
+    let plans = targets
+        .iter()
+        .map(|display| {
+            let selector = display.index.to_string();
+            let resolved = config::resolve(cfg, displays, Some(&selector));
+            let plan = match value {
+                Some(value) => config::parse_input_preset(value, &resolved),
+                None => config::default_input(cfg, displays, &resolved),
+            };
+            (selector, plan)
+        })
+        .collect::<HashMap<_, _>>();
+    let selectors = targets
+        .iter()
+        .map(|d| d.index.to_string())
+        .collect::<Vec<_>>();
+    let batch = if parallel { MAX_PARALLEL_WRITES } else { 1 };
+    let mut outcomes = Vec::new();
+    for chunk in selectors.chunks(batch) {
+        outcomes.extend(for_each_display(chunk.to_vec(), parallel, |selector| {
+            let (value, adjust) = match &plans[selector] {
+                Ok(plan) => *plan,
+                Err(e) => bail!("{e:#}"),
+            };
+            let code = adjust.input_code();
+            platform::set_input_code(backend, selector, code, value)
+                .with_context(|| format!("set input to {value}"))?;
+            adjust.apply(backend, selector)?;
+            Ok((code, value))
+        }));
+        if on_error == OnError::Stop && outcomes.iter().any(|(_sel, o)| o.is_err()) {
+            break;
+        }
+    }
+    let skipped = selectors.len() - outcomes.len();
+
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for (selector, outcome) in outcomes {
+        match outcome {
+            Ok((code, value)) => {
+                if !json {
+                    styles.ok(format_args!("ok\t{selector}\t{value}"));
+                }
+                results.push(SetResult::new(&selector, code, value, false));
+            }
+            Err(e) if json => {
+                styles.error(format_args!("error\t{selector}\t{e:#}"));
+                failed += 1;
+            }
+            Err(e) => {
+                styles.failed(format_args!("error\t{selector}\t{e:#}"));
+                failed += 1;
+            }
+        }
+    }
+    if json {
+        let s = serde_json::to_string_pretty(&results).context("serialize set result")?;
+        println!("{s}");
+    }
+    if skipped > 0 {
+        bail!(
+            "{failed} of {} display(s) failed to switch; stopped before the other {skipped}",
+            targets.len()
+        );
+    }
+    if failed > 0 {
+        bail!("{failed} of {} display(s) failed to switch", targets.len());
+    }
+    Ok(())
+}
+
+/// Sets `name` (a preset or raw value) like `set-input` does, linked displays included, and
+/// prints `name (value)`.
+/// The preset `cycle` moves to from the `current` reading. Only presets that switch through
+/// VCP 0x60 take part, since the reading can't be matched against any other.
+fn cycle_target(
+    cfg: Option<&config::Config>,
+    resolved: &config::ResolvedConfig,
+    current: u16,
+) -> Result<String> {
+    let inputs = resolved.readable_inputs();
+    let Some((name, _value)) = config::next_input(&inputs, config::known_input(cfg, current))
+    else {
+        if resolved.inputs.is_empty() {
+            bail!("No input presets to cycle through; add some to `inputs` in the config");
+        }
+        bail!("cycle reads the input (VCP 0x60), but every preset writes its own `vcp` code");
+    };
+    Ok(name.to_string())
+}
+
+/// Which of `a` and `b` `toggle` switches to: `a`, unless `read_current` says the display is
+/// on it already. Both have to switch through VCP 0x60 so the reading means something; that's
+/// checked before anything is read.
+fn toggle_target<'a>(
+    resolved: &config::ResolvedConfig,
+    a: &'a str,
+    b: &'a str,
+    read_current: impl FnOnce() -> Result<u16>,
+) -> Result<&'a str> {
+    let (value_a, adjust_a) = config::parse_input_preset(a, resolved)?;
+    let (value_b, adjust_b) = config::parse_input_preset(b, resolved)?;
+    for (name, adjust) in [(a, adjust_a), (b, adjust_b)] {
+        let code = adjust.input_code();
+        if code != platform::VCP_INPUT {
+            bail!("toggle reads the input (VCP 0x60), but '{name}' writes VCP 0x{code:02X}");
+        }
+    }
+    if value_a == value_b {
+        bail!("'{a}' and '{b}' are both input {value_a}; toggle needs two different inputs");
+    }
+    Ok(if read_current()? == value_a { b } else { a })
+}
+
+fn switch_preset(
+    backend: &dyn platform::Backend,
+    cfg: Option<&config::Config>,
+    displays: &[platform::DisplayInfo],
+    resolved: &config::ResolvedConfig,
+    name: &str,
+    styles: Styles,
+) -> Result<()> {
+    let selector = &resolved.display_selector;
+    let (value, adjust) = config::parse_input_preset(name, resolved)?;
+    let code = adjust.input_code();
+    platform::set_input_code(backend, selector, code, value)
+        .with_context(|| format!("set input to {value} on display '{selector}'"))?;
+    adjust.apply(backend, selector)?;
+    styles.ok(format_args!("{name} ({value})"));
+
+    let mut failed = 0;
+    for (selector, result) in set_linked(backend, cfg, displays, selector, value, adjust, false) {
+        if let Err(e) = result {
+            styles.error(format_args!("linked display '{selector}': {e:#}"));
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} linked display(s) failed to switch");
+    }
+    Ok(())
+}
+
+/// ` 2560x1440 @ 144Hz`-style suffix, empty when the backend doesn't report a mode.
+fn format_mode(display: &platform::DisplayInfo) -> String {
+    match (display.resolution, display.refresh_hz) {
+        (Some((w, h)), Some(hz)) => format!(" {w}x{h} @ {hz}Hz"),
+        (Some((w, h)), None) => format!(" {w}x{h}"),
+        (None, _) => String::new(),
+    }
+}
+
+/// Prints a numeric reading. In `kv` mode the variable is always `MONITORCTL_<name>` and the
+/// number is left unquoted. An unknown reading prints `<unknown>`, or an empty variable.
+fn print_value(format: OutputFormat, name: &str, value: Option<u16>) {
+    println!("{}", format_value(format, name, value));
+}
+
+fn format_value(format: OutputFormat, name: &str, value: Option<u16>) -> String {
+    match (format, value) {
+        (OutputFormat::Plain, Some(value)) => value.to_string(),
+        (OutputFormat::Plain, None) => "<unknown>".to_string(),
+        (OutputFormat::Kv, Some(value)) => format!("MONITORCTL_{name}={value}"),
+        (OutputFormat::Kv, None) => format!("MONITORCTL_{name}="),
+    }
+}
+
+/// What `watch` reports. With `--json-lines` each is one object tagged with `type` plus a
+/// `ts` (Unix seconds), so a supervising process can follow it without parsing text.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WatchEvent<'a> {
+    /// Polling began.
+    Start {
+        display: &'a str,
+        apply: Option<u16>,
+    },
+    /// The input changed between two polls.
+    Change {
+        display: &'a str,
+        from: Option<u16>,
+        to: Option<u16>,
+        preset: Option<&'a str>,
+    },
+    /// `--apply` switched the display back.
+    Correction { display: &'a str, corrected: u16 },
+    /// `--hotplug`: a display appeared; `applied` is the `default_input` set on it, if any.
+    Connected {
+        display: &'a str,
+        name: Option<&'a str>,
+        applied: Option<u16>,
+    },
+    /// A poll or a correction failed; `watch` keeps going.
+    Error { display: &'a str, message: String },
+}
+
+/// `watch` on one display: what the previous poll read and, with `--apply`, the corrections
+/// made so far.
+struct Watcher<'a> {
+    selector: &'a str,
+    /// `--apply`'s input.
+    desired: Option<u16>,
+    max_corrections: u32,
+    min_correction: Duration,
+    last: Option<u16>,
+    corrections: u32,
+    last_correction: Option<Instant>,
+}
+
+impl<'a> Watcher<'a> {
+    /// Reads the input once, reporting a change since the last poll and switching back to
+    /// `desired` when it drifted. Fails only once `max_corrections` are used up.
+    fn poll(
+        &mut self,
+        backend: &dyn platform::Backend,
+        cfg: Option<&config::Config>,
+        inputs: &'a HashMap<String, u16>,
+        emit: &dyn Fn(WatchEvent<'a>) -> Result<()>,
+    ) -> Result<()> {
+        let selector = self.selector;
+        let value = match backend.get_input(selector) {
+            Ok(value) => value,
+            Err(e) => {
+                return emit(WatchEvent::Error {
+                    display: selector,
+                    message: format!("get input on display '{selector}': {e:#}"),
+                })
+            }
+        };
+        if let Some(from) = self.last.filter(|from| *from != value) {
+            let from = config::known_input(cfg, from);
+            let to = config::known_input(cfg, value);
+            let preset = to.and_then(|to| config::preset_for_value(inputs, to));
+            emit(WatchEvent::Change {
+                display: selector,
+                from,
+                to,
+                preset,
+            })?;
+        }
+        self.last = Some(value);
+
+        let due = self
+            .last_correction
+            .is_none_or(|at| at.elapsed() >= self.min_correction);
+        let Some(desired) = self.desired.filter(|d| *d != value && due) else {
+            return Ok(());
+        };
+        if self.corrections == self.max_corrections {
+            bail!(
+                "display '{selector}' drifted from {desired} again after {} corrections; giving up",
+                self.max_corrections
+            );
+        }
+        self.corrections += 1;
+        self.last_correction = Some(Instant::now());
+        emit(match backend.set_input(selector, desired) {
+            Ok(()) => WatchEvent::Correction {
+                display: selector,
+                corrected: desired,
+            },
+            Err(e) => WatchEvent::Error {
+                display: selector,
+                message: format!("restore input {desired} on display '{selector}': {e:#}"),
+            },
+        })
+    }
+}
+
+/// Displays seen by the previous `watch --hotplug` poll, keyed by [`config::stable_id`] or,
+/// without one, the product name, so a re-enumerated display isn't taken for a new one.
+struct KnownDisplays {
+    keys: HashSet<String>,
+}
+
+impl KnownDisplays {
+    fn new(displays: &[platform::DisplayInfo]) -> Self {
+        Self {
+            keys: displays.iter().map(Self::key).collect(),
+        }
+    }
+
+    fn key(display: &platform::DisplayInfo) -> String {
+        config::stable_id(display)
+            .or_else(|| display.product_name.as_ref().map(|n| format!("name:{n}")))
+            .unwrap_or_else(|| format!("index:{}", display.index))
+    }
+
+    /// Records the current displays and returns the ones absent from the previous poll.
+    fn update<'a>(
+        &mut self,
+        displays: &'a [platform::DisplayInfo],
+    ) -> Vec<&'a platform::DisplayInfo> {
+        let previous = std::mem::replace(&mut self.keys, displays.iter().map(Self::key).collect());
+        displays
+            .iter()
+            .filter(|d| !previous.contains(&Self::key(d)))
+            .collect()
+    }
+}
+
+fn emit_watch_event(json_lines: bool, styles: Styles, event: WatchEvent) -> Result<()> {
+    let mut out = io::stdout().lock();
+    if json_lines {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        writeln!(out, "{}", watch_event_line(&event, ts)?).context("write stdout")?;
+    } else {
+        match event {
+            WatchEvent::Start { .. } => return Ok(()),
+            WatchEvent::Change {
+                display,
+                from,
+                to,
+                preset,
+            } => {
+                let label =
+                    |v: Option<u16>| v.map_or_else(|| "<unknown>".to_string(), |v| v.to_string());
+                let (from, to) = (label(from), label(to));
+                let name = preset.map(|p| format!(" ({p})")).unwrap_or_default();
+                writeln!(out, "display {display}: input {from} -> {to}{name}")
+                    .context("write stdout")?;
+            }
+            WatchEvent::Correction { display, corrected } => {
+                writeln!(out, "display {display}: restored input {corrected}")
+                    .context("write stdout")?;
+            }
+            WatchEvent::Connected {
+                display,
+                name,
+                applied,
+            } => {
+                let name = name.map(|n| format!(" ({n})")).unwrap_or_default();
+                let applied = applied
+                    .map(|v| format!("; set input {v}"))
+                    .unwrap_or_default();
+                writeln!(out, "display {display}{name}: connected{applied}")
+                    .context("write stdout")?;
+            }
+            WatchEvent::Error { message, .. } => {
+                styles.error(message);
+                return Ok(());
+            }
+        }
+    }
+    // Downstream consumers (log pipelines) should see each event immediately.
+    out.flush().context("flush stdout")
+}
+
+/// `event` as one `--json-lines` line (without the newline), stamped with `ts`.
+fn watch_event_line(event: &WatchEvent, ts: u64) -> Result<String> {
+    let mut line = serde_json::to_value(event).context("serialize watch event")?;
+    line["ts"] = ts.into();
+    Ok(line.to_string())
+}
+
+/// `doctor --json` output.
+#[derive(Debug, Serialize)]
+struct DoctorJson<'a> {
+    ok: bool,
+    #[serde(flatten)]
+    report: &'a platform::DoctorReport,
+    displays: Vec<platform::DisplayInfo>,
+}
+
+/// One `set-input --json` result.
+#[derive(Debug, Serialize)]
+struct SetResult {
+    selector: String,
+    /// VCP code written: `0x60` (input source) unless the preset sets `vcp`.
+    code: u8,
+    value: u16,
+    verified: bool,
+}
+
+impl SetResult {
+    fn new(selector: &str, code: u8, value: u16, verified: bool) -> Self {
+        Self {
+            selector: selector.to_string(),
+            code,
+            value,
+            verified,
+        }
+    }
+
+    /// A lone result as an object, several (the target plus linked displays) as an array.
+    fn to_json(results: &[Self]) -> Result<String> {
+        match results {
+            [only] => serde_json::to_string_pretty(only),
+            all => serde_json::to_string_pretty(all),
+        }
+        .context("serialize set result")
+    }
+}
+
+/// `batch`: sets each `selector<TAB>value` / JSON line of `input` like `set-input` would,
+/// printing an `ok`/`error` line per entry. Fails if any entry (or a linked display) did.
+fn run_batch(
+    backend: &dyn platform::Backend,
+    cfg: Option<&config::Config>,
+    displays: &[platform::DisplayInfo],
+    input: impl BufRead,
+    on_error: OnError,
+    styles: Styles,
+) -> Result<()> {
+    let mut total = 0usize;
+    let mut failed = 0usize;
+    for (n, line) in input.lines().enumerate() {
+        let line = line.context("reading batch input")?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        total += 1;
+
+        let entry = parse_batch_line(line).and_then(|mut entry| {
+            entry.display = config::display_arg(cfg, displays, entry.display)?;
+            Ok(entry)
+        });
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                failed += 1;
+                styles.failed(format_args!("error\tline {}\t{e:#}", n + 1));
+                if on_error == OnError::Stop {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let resolved = config::resolve(cfg, displays, entry.display.as_deref());
+        let result = report_config_warnings(cfg, &resolved.warnings, styles)
+            .and_then(|()| config::parse_input_preset(&entry.value, &resolved))
+            .and_then(|(value, adjust)| {
+                let code = adjust.input_code();
+                platform::set_input_code(backend, &resolved.display_selector, code, value)?;
+                adjust.apply(backend, &resolved.display_selector)?;
+                Ok((value, adjust))
+            });
+        match result {
+            Ok((value, adjust)) => {
+                styles.ok(format_args!("ok\t{}\t{value}", resolved.display_selector));
+                let linked = set_linked(
+                    backend,
+                    cfg,
+                    displays,
+                    &resolved.display_selector,
+                    value,
+                    adjust,
+                    false,
+                );
+                let mut linked_failed = false;
+                for (selector, result) in linked {
+                    match result {
+                        Ok(()) => styles.ok(format_args!("ok\t{selector}\t{value}")),
+                        Err(e) => {
+                            failed += 1;
+                            linked_failed = true;
+                            styles.failed(format_args!("error\t{selector}\t{e:#}"));
+                        }
+                    }
+                }
+                // The entry counts as failed when a linked display didn't follow.
+                if linked_failed && on_error == OnError::Stop {
+                    break;
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                styles.failed(format_args!("error\t{}\t{e:#}", resolved.display_selector));
+                if on_error == OnError::Stop {
+                    break;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {total} batch entries failed");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchEntry {
+    #[serde(default)]
+    display: Option<String>,
+    #[serde(deserialize_with = "deserialize_batch_value")]
+    value: String,
+}
+
+/// Accepts either `{"display": "1", "value": 26}` or `selector<TAB>value`.
+fn parse_batch_line(line: &str) -> Result<BatchEntry> {
+    if line.starts_with('{') {
+        return serde_json::from_str(line).context("parsing JSON batch entry");
+    }
+
+    let (display, value) = match line.split_once('\t') {
+        Some((display, value)) => (display.trim(), value.trim()),
+        None => ("", line),
+    };
+    if value.is_empty() {
+        return Err(anyhow!("missing input value in '{line}'"));
+    }
+
+    Ok(BatchEntry {
+        display: (!display.is_empty()).then(|| display.to_string()),
+        value: value.to_string(),
+    })
+}
+
+fn deserialize_batch_value<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => Ok(s),
+        serde_json::Value::Number(n) => Ok(n.to_string()),
+        other => Err(serde::de::Error::custom(format!(
+            "expected a number or preset name, got {other}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+#[path = "platform/mock.rs"]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockBackend;
+    use platform::Backend;
+
+    const QUIET: Styles = Styles {
+        stdout: false,
+        stderr: false,
+    };
+
+    fn test_config(json: &str) -> config::Config {
+        config::parse(json.as_bytes(), "test").unwrap()
+    }
+
+    #[test]
+    fn batch_lines_accept_tab_and_json_forms() {
+        let entry = parse_batch_line("2\thdmi1").unwrap();
+        assert_eq!(
+            (entry.display.as_deref(), entry.value.as_str()),
+            (Some("2"), "hdmi1")
+        );
+
+        let entry = parse_batch_line("0x0f").unwrap();
+        assert_eq!((entry.display, entry.value.as_str()), (None, "0x0f"));
+
+        let entry = parse_batch_line(r#"{"display": "1", "value": 26}"#).unwrap();
+        assert_eq!(
+            (entry.display.as_deref(), entry.value.as_str()),
+            (Some("1"), "26")
+        );
+
+        let entry = parse_batch_line(r#"{"value": "dp1"}"#).unwrap();
+        assert_eq!((entry.display, entry.value.as_str()), (None, "dp1"));
+    }
+
+    #[test]
+    fn batch_lines_reject_missing_or_bad_values() {
+        for line in [
+            "1\t",
+            "1\t  ",
+            r#"{"display": "1"}"#,
+            r#"{"value": true}"#,
+            "{",
+        ] {
+            assert!(parse_batch_line(line).is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn batch_applies_each_entry_and_skips_comments() {
+        let mock = MockBackend::new(2);
+        let cfg = test_config(r#"{ "inputs": { "dp1": 15, "hdmi1": 17 } }"#);
+        let input = "1\tdp1\n# comment\n\n2\thdmi1\n{\"display\": \"1\", \"value\": 27}\n";
+        run_batch(
+            &mock,
+            Some(&cfg),
+            &mock.list_displays().unwrap().displays,
+            input.as_bytes(),
+            OnError::Stop,
+            QUIET,
+        )
+        .unwrap();
+        assert_eq!(
+            mock.writes(),
+            vec![
+                ("1".to_string(), platform::VCP_INPUT, 15),
+                ("2".to_string(), platform::VCP_INPUT, 17),
+                ("1".to_string(), platform::VCP_INPUT, 27),
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_on_error_decides_whether_to_go_on() {
+        let input = "1\t15\n2\t15\nnot-a-preset\n1\t17\n";
+        for (on_error, expected) in [(OnError::Stop, 1), (OnError::Continue, 2)] {
+            let mock = MockBackend::new(2);
+            mock.fail("2");
+            let displays = mock.list_displays().unwrap().displays;
+            let e =
+                run_batch(&mock, None, &displays, input.as_bytes(), on_error, QUIET).unwrap_err();
+            assert_eq!(mock.writes().len(), expected, "{on_error:?}");
+            let failed = if on_error == OnError::Stop { 1 } else { 2 };
+            assert_eq!(
+                e.to_string(),
+                format!("{failed} of {} batch entries failed", expected + failed)
+            );
+        }
+    }
+
+    #[test]
+    fn capabilities_fields_splits_a_real_caps_string() {
+        let raw = "(prot(monitor)type(lcd)model(U2720Q)cmds(01 02 03 07 0C E3 F3)\
+                   vcp(02 04 05 08 10 12 14(05 08 0B) 60(0F 11 1B) D6(01 04))mccs_ver(2.1))";
+        let fields = capabilities_fields(raw);
+        assert_eq!(
+            fields,
+            vec![
+                ("prot", "monitor"),
+                ("type", "lcd"),
+                ("model", "U2720Q"),
+                ("cmds", "01 02 03 07 0C E3 F3"),
+                (
+                    "vcp",
+                    "02 04 05 08 10 12 14(05 08 0B) 60(0F 11 1B) D6(01 04)"
+                ),
+                ("mccs_ver", "2.1"),
+            ]
+        );
+
+        let vcp = capabilities_vcp(fields[4].1);
+        let inputs = vcp.iter().find(|(code, _)| *code == "60").unwrap();
+        assert_eq!(inputs.1, vec!["0F", "11", "1B"]);
+        assert_eq!(
+            vcp.iter().find(|(code, _)| *code == "10").unwrap().1.len(),
+            0
+        );
+    }
+
+    #[test]
+    fn capabilities_fields_tolerates_malformed_strings() {
+        // Unterminated field at the end: the complete ones are still returned.
+        assert_eq!(
+            capabilities_fields("(prot(monitor)type(lcd)vcp(10 60(0F 11)"),
+            vec![("prot", "monitor"), ("type", "lcd")]
+        );
+        // Missing outer parentheses and a stray closing one.
+        assert_eq!(
+            capabilities_fields(" model(X)) vcp(10) "),
+            vec![("model", "X"), ("vcp", "10")]
+        );
+        assert!(capabilities_fields("").is_empty());
+        assert!(capabilities_fields("garbage").is_empty());
+    }
+
+    fn sweep(on_error: OnError, parallel: bool) -> (MockBackend, Result<()>) {
+        let mock = MockBackend::new(3);
+        mock.fail("2");
+        let displays = mock.list_displays().unwrap().displays;
+        let options = SweepOptions {
+            json: false,
+            parallel,
+            on_error,
+        };
+        let result = set_input_all(&mock, None, &displays, Some("15"), options, QUIET);
+        (mock, result)
+    }
+
+    fn switched(mock: &MockBackend) -> Vec<String> {
+        let mut selectors = mock.writes().into_iter().map(|w| w.0).collect::<Vec<_>>();
+        selectors.sort();
+        selectors
+    }
+
+    #[test]
+    fn set_input_all_continues_past_a_failure_by_default() {
+        let (mock, result) = sweep(OnError::Continue, false);
+        assert_eq!(switched(&mock), ["1", "3"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "1 of 3 display(s) failed to switch"
+        );
+    }
+
+    #[test]
+    fn set_input_all_stops_at_the_first_failure() {
+        let (mock, result) = sweep(OnError::Stop, false);
+        assert_eq!(switched(&mock), ["1"]);
+        assert_eq!(mock.write_log().len(), 2, "display 3 must not be tried");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "1 of 3 display(s) failed to switch; stopped before the other 1"
+        );
+    }
+
+    #[test]
+    fn set_input_all_stop_lets_a_parallel_batch_finish() {
+        // All three fit in one parallel batch, so they were all started before the failure.
+        let (mock, result) = sweep(OnError::Stop, true);
+        assert_eq!(switched(&mock), ["1", "3"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "1 of 3 display(s) failed to switch"
+        );
+    }
+
+    #[test]
+    fn values_format_as_plain_or_kv() {
+        let cases = [
+            (OutputFormat::Plain, Some(15), "15"),
+            (OutputFormat::Plain, None, "<unknown>"),
+            (OutputFormat::Kv, Some(15), "MONITORCTL_INPUT=15"),
+            (OutputFormat::Kv, None, "MONITORCTL_INPUT="),
+        ];
+        for (format, value, expected) in cases {
+            assert_eq!(format_value(format, "INPUT", value), expected);
+        }
+    }
+
+    #[test]
+    fn get_commands_take_a_format() {
+        for command in ["get-input", "get-brightness"] {
+            let cli = Cli::try_parse_from(["monitorctl", command, "--format", "kv"]).unwrap();
+            let format = match cli.command {
+                Command::GetInput { format, .. } | Command::GetBrightness { format, .. } => format,
+                other => panic!("parsed as {other:?}"),
+            };
+            assert_eq!(format, OutputFormat::Kv);
+            assert!(Cli::try_parse_from(["monitorctl", command, "--format", "env"]).is_err());
+        }
+    }
+
+    #[test]
+    fn wait_settled_waits_for_a_stable_reading() {
+        let backend = MockBackend::new(1);
+        backend.set("1", platform::VCP_INPUT, 15);
+        let settle = Duration::from_millis(150);
+        let started = Instant::now();
+        assert!(wait_settled(&backend, "1", 15, settle, Duration::from_secs(5)).unwrap());
+        assert!(started.elapsed() >= settle);
+        assert!(wait_settled(&backend, "1", 15, Duration::ZERO, Duration::ZERO).unwrap());
+    }
+
+    #[test]
+    fn wait_settled_gives_up_on_another_input() {
+        let backend = MockBackend::new(1);
+        backend.set("1", platform::VCP_INPUT, 17);
+        let e = wait_settled(
+            &backend,
+            "1",
+            15,
+            Duration::ZERO,
+            Duration::from_millis(250),
+        )
+        .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "input on display '1' did not settle at 15 within 250ms (last read 17)"
+        );
+    }
+
+    #[test]
+    fn wait_settled_just_sleeps_without_get_input() {
+        // The mock fails reads of values it was never given.
+        let backend = MockBackend::new(1);
+        let settle = Duration::from_millis(50);
+        let started = Instant::now();
+        assert!(!wait_settled(&backend, "1", 15, settle, Duration::ZERO).unwrap());
+        assert!(started.elapsed() >= settle);
+    }
+
+    #[test]
+    fn linked_displays_get_the_preset_adjustments() {
+        let cfg = test_config(
+            r#"{ "linked_groups": [[{ "index": 1 }, { "index": 2 }]],
+                "inputs": { "game": { "value": 17, "brightness": 80, "volume": 10 } } }"#,
+        );
+        let mock = MockBackend::new(3);
+        let displays = mock.list_displays().unwrap().displays;
+        let resolved = config::resolve(Some(&cfg), &displays, Some("1"));
+        switch_preset(&mock, Some(&cfg), &displays, &resolved, "game", QUIET).unwrap();
+        let writes = |selector: &str| {
+            mock.writes()
+                .into_iter()
+                .filter(|(sel, _code, _value)| sel == selector)
+                .map(|(_sel, code, value)| (code, value))
+                .collect::<Vec<_>>()
+        };
+        let expected = [
+            (platform::VCP_INPUT, 17),
+            (platform::VCP_BRIGHTNESS, 80),
+            (platform::VCP_VOLUME, 10),
+        ];
+        assert_eq!(writes("1"), expected);
+        assert_eq!(writes("2"), expected);
+        assert!(writes("3").is_empty());
+    }
+
+    #[test]
+    fn set_results_serialize_as_object_or_array() {
+        let target = SetResult::new("1", platform::VCP_INPUT, 15, true);
+        let json: serde_json::Value =
+            serde_json::from_str(&SetResult::to_json(&[target]).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "selector": "1", "code": 96, "value": 15, "verified": true })
+        );
+
+        let results = [
+            SetResult::new("1", 0xDC, 5, false),
+            SetResult::new("uuid:A", 0xDC, 5, false),
+        ];
+        let json: serde_json::Value =
+            serde_json::from_str(&SetResult::to_json(&results).unwrap()).unwrap();
+        assert_eq!(json[0]["code"], 0xDC);
+        assert_eq!(json[1]["selector"], "uuid:A");
+        assert_eq!(json.as_array().map(Vec::len), Some(2));
+    }
+
+    fn watcher(
+        desired: Option<u16>,
+        max_corrections: u32,
+        min_correction: Duration,
+    ) -> Watcher<'static> {
+        Watcher {
+            selector: "1",
+            desired,
+            max_corrections,
+            min_correction,
+            last: None,
+            corrections: 0,
+            last_correction: None,
+        }
+    }
+
+    /// Polls once and returns the events as their `--json-lines` objects (without `ts`).
+    fn poll<'a>(
+        watcher: &mut Watcher<'a>,
+        mock: &MockBackend,
+        inputs: &'a HashMap<String, u16>,
+    ) -> Result<Vec<serde_json::Value>> {
+        let events = std::cell::RefCell::new(Vec::new());
+        let emit = |event: WatchEvent<'a>| {
+            events.borrow_mut().push(serde_json::to_value(&event)?);
+            Ok(())
+        };
+        watcher.poll(mock, None, inputs, &emit)?;
+        Ok(events.into_inner())
+    }
+
+    fn inputs() -> HashMap<String, u16> {
+        HashMap::from([("dp1".to_string(), 15), ("hdmi1".to_string(), 17)])
+    }
+
+    #[test]
+    fn watch_apply_switches_back_after_a_drift() {
+        let mock = MockBackend::new(1);
+        mock.set("1", platform::VCP_INPUT, 15);
+        let inputs = inputs();
+        let mut watcher = watcher(Some(15), 10, Duration::ZERO);
+        assert!(poll(&mut watcher, &mock, &inputs).unwrap().is_empty());
+
+        mock.set("1", platform::VCP_INPUT, 17);
+        let events = poll(&mut watcher, &mock, &inputs).unwrap();
+        assert_eq!(
+            events,
+            [
+                serde_json::json!({ "type": "change", "display": "1", "from": 15, "to": 17, "preset": "hdmi1" }),
+                serde_json::json!({ "type": "correction", "display": "1", "corrected": 15 }),
+            ]
+        );
+        assert_eq!(mock.value("1", platform::VCP_INPUT), Some(15));
+    }
+
+    #[test]
+    fn watch_apply_gives_up_after_max_corrections() {
+        let mock = MockBackend::new(1);
+        let inputs = inputs();
+        let mut watcher = watcher(Some(15), 2, Duration::ZERO);
+        for _ in 0..2 {
+            mock.set("1", platform::VCP_INPUT, 17);
+            poll(&mut watcher, &mock, &inputs).unwrap();
+        }
+        mock.set("1", platform::VCP_INPUT, 17);
+        let e = poll(&mut watcher, &mock, &inputs).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "display '1' drifted from 15 again after 2 corrections; giving up"
+        );
+    }
+
+    #[test]
+    fn watch_apply_waits_between_corrections() {
+        let mock = MockBackend::new(1);
+        let inputs = inputs();
+        let mut watcher = watcher(Some(15), 10, Duration::from_secs(3600));
+        mock.set("1", platform::VCP_INPUT, 17);
+        poll(&mut watcher, &mock, &inputs).unwrap();
+        // Sees the corrected input, then the next drift.
+        poll(&mut watcher, &mock, &inputs).unwrap();
+        mock.set("1", platform::VCP_INPUT, 17);
+        let events = poll(&mut watcher, &mock, &inputs).unwrap();
+        assert_eq!(events[0]["type"], "change");
+        assert_eq!(events.len(), 1);
+        assert_eq!(mock.writes().len(), 1);
+    }
+
+    #[test]
+    fn watch_reports_failed_corrections_and_keeps_going() {
+        let mock = MockBackend::new(1);
+        mock.set("1", platform::VCP_INPUT, 17).fail("1");
+        let inputs = inputs();
+        let mut watcher = watcher(Some(15), 10, Duration::ZERO);
+        let events = poll(&mut watcher, &mock, &inputs).unwrap();
+        assert_eq!(events[0]["type"], "error");
+        assert!(events[0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("restore input 15 on display '1'"));
+        assert_eq!(watcher.corrections, 1);
+    }
+
+    fn selectors(range: std::ops::RangeInclusive<u32>) -> Vec<String> {
+        range.map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn for_each_display_runs_in_parallel_and_keeps_the_order() {
+        let mock = MockBackend::new(4);
+        mock.delay(Duration::from_millis(100));
+        let started = Instant::now();
+        let results = for_each_display(selectors(1..=4), true, |sel| mock.set_input(sel, 15));
+        assert!(started.elapsed() < Duration::from_millis(300));
+        let order = results
+            .iter()
+            .map(|(sel, _r)| sel.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(order, ["1", "2", "3", "4"]);
+        assert!(results.iter().all(|(_sel, r)| r.is_ok()));
+
+        let started = Instant::now();
+        for_each_display(selectors(1..=4), false, |sel| mock.set_input(sel, 17));
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[test]
+    fn for_each_display_reports_failures_per_display() {
+        let results = for_each_display(selectors(1..=3), true, |sel| match sel {
+            "2" => bail!("no reply"),
+            "3" => panic!("backend bug"),
+            _ => Ok(()),
+        });
+        let errors = results
+            .iter()
+            .map(|(_sel, r)| r.as_ref().err().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                None,
+                Some("no reply".to_string()),
+                Some("switching display '3' panicked".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_linked_in_parallel_switches_every_member() {
+        let cfg = test_config(
+            r#"{ "linked_groups": [[{ "index": 1 }, { "index": 2 }, { "index": 3 }]] }"#,
+        );
+        let mock = MockBackend::new(3);
+        mock.fail("3");
+        let displays = mock.list_displays().unwrap().displays;
+        let linked = set_linked(
+            &mock,
+            Some(&cfg),
+            &displays,
+            "1",
+            15,
+            config::PresetAdjustments::default(),
+            true,
+        );
+        let outcome = linked
+            .iter()
+            .map(|(sel, r)| (sel.as_str(), r.is_ok()))
+            .collect::<Vec<_>>();
+        assert_eq!(outcome, [("2", true), ("3", false)]);
+        assert_eq!(mock.value("2", platform::VCP_INPUT), Some(15));
+    }
+
+    /// Input that fails the test if anything reads from it.
+    struct Unread;
+
+    impl io::Read for Unread {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            panic!("confirmation read stdin");
+        }
+    }
+
+    impl BufRead for Unread {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            panic!("confirmation read stdin");
+        }
+
+        fn consume(&mut self, _: usize) {}
+    }
+
+    #[test]
+    fn assume_yes_confirms_without_reading_stdin() {
+        assert!(confirm_from("Switch?", true, true, &mut Unread).unwrap());
+        assert!(confirm_from("Switch?", true, false, &mut Unread).unwrap());
+        let cli = Cli::try_parse_from(["monitorctl", "-y", "list"]).unwrap();
+        assert!(cli.assume_yes);
+    }
+
+    #[test]
+    fn non_terminal_stdin_declines_without_reading() {
+        assert!(!confirm_from("Switch?", false, false, &mut Unread).unwrap());
+    }
+
+    #[test]
+    fn only_yes_answers_confirm() {
+        let answer = |text: &str| confirm_from("Switch?", false, true, &mut text.as_bytes());
+        for yes in ["y\n", "yes\n", " YES \n", "Y"] {
+            assert!(answer(yes).unwrap(), "{yes:?}");
+        }
+        for no in ["\n", "n\n", "no\n", "yep\n", ""] {
+            assert!(!answer(no).unwrap(), "{no:?}");
+        }
+    }
+
+    #[test]
+    fn watch_events_are_one_tagged_json_object_per_line() {
+        let events = [
+            WatchEvent::Start {
+                display: "1",
+                apply: Some(15),
+            },
+            WatchEvent::Change {
+                display: "1",
+                from: Some(15),
+                to: None,
+                preset: None,
+            },
+            WatchEvent::Correction {
+                display: "1",
+                corrected: 15,
+            },
+            WatchEvent::Connected {
+                display: "2",
+                name: Some("DELL U2720Q"),
+                applied: Some(17),
+            },
+            WatchEvent::Error {
+                display: "*",
+                message: "list displays: \"busy\"\nretry".to_string(),
+            },
+        ];
+        let lines = events
+            .iter()
+            .map(|event| watch_event_line(event, 1_700_000_000).unwrap())
+            .collect::<Vec<_>>();
+        assert!(lines.iter().all(|line| !line.contains('\n')));
+        let parsed = lines
+            .iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        let ts = 1_700_000_000;
+        assert_eq!(
+            parsed,
+            [
+                serde_json::json!({ "type": "start", "display": "1", "apply": 15, "ts": ts }),
+                serde_json::json!({ "type": "change", "display": "1", "from": 15, "to": null, "preset": null, "ts": ts }),
+                serde_json::json!({ "type": "correction", "display": "1", "corrected": 15, "ts": ts }),
+                serde_json::json!({ "type": "connected", "display": "2", "name": "DELL U2720Q", "applied": 17, "ts": ts }),
+                serde_json::json!({ "type": "error", "display": "*", "message": "list displays: \"busy\"\nretry", "ts": ts }),
+            ]
+        );
+    }
+
+    #[test]
+    fn doctor_probe_reports_each_display_without_writing() {
+        let mock = MockBackend::new(2);
+        mock.set("1", platform::VCP_INPUT, 15);
+        let mut notes = mock.doctor().unwrap();
+        add_probes(&mut notes, platform::probe_displays(&mock).unwrap());
+        let probes = notes
+            .probes
+            .iter()
+            .map(|p| (p.index, p.product_name.as_deref(), p.responsive))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            probes,
+            [
+                (1, Some("Mock 1"), Some(true)),
+                (2, Some("Mock 2"), Some(false))
+            ]
+        );
+        assert_eq!(
+            notes.message,
+            "mock\n\nProbe:\n[1] Mock 1: DDC/CI OK\n[2] Mock 2: no DDC/CI response"
+        );
+        assert!(notes.ok());
+        assert!(mock.write_log().is_empty());
+    }
+
+    #[test]
+    fn doctor_probe_fails_when_no_display_answers() {
+        let mock = MockBackend::new(2);
+        let mut notes = mock.doctor().unwrap();
+        add_probes(&mut notes, platform::probe_displays(&mock).unwrap());
+        assert_eq!(notes.status, platform::DoctorStatus::NoDdcResponse);
+        assert!(notes.message.ends_with("enabled in the monitor's OSD."));
+    }
+
+    const CUSTOM_VCP: &str =
+        r#"{ "inputs": { "dp1": 15, "hdmi1": 17, "pip": { "vcp": 233, "value": 16 } } }"#;
+
+    fn resolved(json: &str) -> config::ResolvedConfig {
+        config::resolve(Some(&test_config(json)), &[], Some("1"))
+    }
+
+    #[test]
+    fn cycle_skips_presets_on_their_own_vcp() {
+        let cfg = test_config(CUSTOM_VCP);
+        let custom = resolved(CUSTOM_VCP);
+        assert_eq!(cycle_target(Some(&cfg), &custom, 15).unwrap(), "hdmi1");
+        assert_eq!(cycle_target(Some(&cfg), &custom, 17).unwrap(), "dp1");
+        assert_eq!(cycle_target(Some(&cfg), &custom, 16).unwrap(), "dp1");
+
+        let only_custom = r#"{ "inputs": { "pip": { "vcp": 233, "value": 16 } } }"#;
+        let e = cycle_target(None, &resolved(only_custom), 15).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "cycle reads the input (VCP 0x60), but every preset writes its own `vcp` code"
+        );
+        let e = cycle_target(None, &resolved("{}"), 15).unwrap_err();
+        assert!(e.to_string().starts_with("No input presets"), "{e}");
+    }
+
+    #[test]
+    fn toggle_picks_the_other_input() {
+        let custom = resolved(CUSTOM_VCP);
+        assert_eq!(
+            toggle_target(&custom, "dp1", "hdmi1", || Ok(15)).unwrap(),
+            "hdmi1"
+        );
+        assert_eq!(
+            toggle_target(&custom, "dp1", "hdmi1", || Ok(17)).unwrap(),
+            "dp1"
+        );
+        assert_eq!(
+            toggle_target(&custom, "dp1", "hdmi1", || Ok(0)).unwrap(),
+            "dp1"
+        );
+        let e = toggle_target(&custom, "dp1", "15", || Ok(15)).unwrap_err();
+        assert!(
+            e.to_string().ends_with("toggle needs two different inputs"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn toggle_rejects_presets_on_their_own_vcp_before_reading() {
+        let custom = resolved(CUSTOM_VCP);
+        let e = toggle_target(&custom, "dp1", "pip", || -> Result<u16> {
+            panic!("read the input")
+        })
+        .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "toggle reads the input (VCP 0x60), but 'pip' writes VCP 0xE9"
+        );
+    }
+}
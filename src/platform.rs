@@ -1,10 +1,39 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DisplayInfo {
     pub index: u32,
     pub product_name: Option<String>,
     pub system_uuid: Option<String>,
+    pub serial: Option<String>,
+    /// Current mode in pixels (width, height). Informational; `None` where the OS isn't queried.
+    pub resolution: Option<(u32, u32)>,
+    pub refresh_hz: Option<u32>,
+    /// Best guess that this is a laptop's built-in panel, which usually can't switch inputs.
+    /// See [`looks_internal`]; config can override it per monitor.
+    pub internal: bool,
+    /// Whether the display answered a DDC/CI read when probed (see [`Backend::probe_ddc`]).
+    /// `None` when it wasn't probed or the backend can't tell.
+    pub ddc_responsive: Option<bool>,
+    /// Port type and number the display is attached by, e.g. `"HDMI-1"` or `"DP-2"`
+    /// (Windows and Linux; `None` on macOS or when the OS doesn't say).
+    pub connector: Option<String>,
+}
+
+/// Name-based heuristic for built-in panels ("Color LCD" on macOS, "Built-in"/"Internal" on
+/// some Windows drivers). Backends don't parse EDID, so this is all we have to go on.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub(crate) fn looks_internal(product_name: Option<&str>) -> bool {
+    let Some(name) = product_name else {
+        return false;
+    };
+    let name = name.to_ascii_lowercase();
+    ["color lcd", "built-in", "built in", "internal"]
+        .iter()
+        .any(|needle| name.contains(needle))
 }
 
 #[derive(Debug, Clone)]
@@ -13,38 +42,248 @@ pub struct DisplayListReport {
     pub raw: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DoctorReport {
-    pub ok: bool,
+    pub status: DoctorStatus,
     pub message: String,
+    /// Per-display DDC/CI read results from `doctor --probe`; empty without it.
+    pub probes: Vec<DdcProbe>,
+    /// The external tool the backend found (`m1ddc`, `ddcutil`); `None` for Dxva2, or when
+    /// it's missing.
+    pub tool_path: Option<PathBuf>,
+    /// Whether the current input can be read. On macOS this depends on the `m1ddc` build;
+    /// `None` when doctor stopped before it could tell.
+    pub get_input_supported: Option<bool>,
 }
 
-pub trait Backend {
+/// Whether one display answered a DDC/CI read (see [`probe_displays`]).
+#[derive(Debug, Clone, Serialize)]
+pub struct DdcProbe {
+    pub index: u32,
+    pub product_name: Option<String>,
+    /// `None` when the backend has no read to probe with.
+    pub responsive: Option<bool>,
+}
+
+/// Probes every listed display with a read-only DDC/CI round trip. Nothing is written.
+pub fn probe_displays(backend: &dyn Backend) -> Result<Vec<DdcProbe>> {
+    let report = backend.list_displays()?;
+    Ok(report
+        .displays
+        .into_iter()
+        .map(|d| DdcProbe {
+            responsive: backend.probe_ddc(&d.index.to_string()),
+            index: d.index,
+            product_name: d.product_name,
+        })
+        .collect())
+}
+
+impl DoctorReport {
+    pub fn ok(&self) -> bool {
+        self.status == DoctorStatus::Ok
+    }
+}
+
+/// Outcome class of `doctor`, so scripts can tell failures apart by exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoctorStatus {
+    Ok,
+    /// The external tool the backend drives (e.g. `m1ddc`) wasn't found.
+    ToolMissing,
+    /// Enumeration worked but found no DDC/CI-capable displays.
+    NoDisplays,
+    /// Listing displays failed.
+    EnumerationFailed,
+    /// `doctor --probe`: displays were found, but none answered a DDC/CI read.
+    NoDdcResponse,
+    /// The OS has DDC/CI devices, but this user can't open any of them (Linux `/dev/i2c-*`).
+    NoDeviceAccess,
+}
+
+/// Backend tuning derived from config/env (see `config::backend_options`).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BackendOptions {
+    /// Extra arguments inserted right after the `m1ddc` executable, before the command verb
+    /// (`m1ddc <extra...> display 1 set input 15`). Ignored by other backends.
+    pub m1ddc_extra_args: Vec<String>,
+    /// Issue a throwaway VCP read (and pause briefly) before each input write. Some monitors
+    /// ignore the first DDC/CI command after waking from standby; the read absorbs it.
+    pub wake_before_write: bool,
+    /// Minimum gap between writes to the same display selector within this process; zero
+    /// disables it. Guards the DDC channel against command floods.
+    pub min_write_interval: std::time::Duration,
+    /// Pause before each VCP read, for monitors that answer garbage to an immediate read.
+    /// Zero keeps the plain API behavior. Only the Windows backend reads VCP codes itself.
+    pub read_delay: std::time::Duration,
+    /// Reject every write (`--probe-safe`); only reads reach the monitor.
+    pub read_only: bool,
+    /// Turn the monitor's OSD off (VCP 0xCA) around each input switch, then restore it.
+    pub suppress_osd: bool,
+    /// Give up on any backend call that takes longer than this; zero waits indefinitely.
+    pub operation_timeout: std::time::Duration,
+    /// Try a failed write this many more times; zero fails on the first error.
+    pub retries: u32,
+    /// Pause before the first retry; each later one waits twice as long as the previous.
+    pub retry_delay: std::time::Duration,
+    /// Keep enumerated monitor handles between calls instead of opening them for each one
+    /// (the tray). Only the Windows backend holds handles; see `Backend::forget_displays`.
+    pub cache_monitors: bool,
+}
+
+/// Pause between the wake-up read and the write when `wake_before_write` is set.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+const WAKE_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Input source, the code `set_input` / `get_input` use.
+pub const VCP_INPUT: u8 = 0x60;
+
+/// VCP codes a preset can adjust after switching (see `config::PresetAdjustments`).
+pub const VCP_BRIGHTNESS: u8 = 0x10;
+pub const VCP_CONTRAST: u8 = 0x12;
+pub const VCP_VOLUME: u8 = 0x62;
+
+/// Writes an input preset's `value` to `code`: through `set_input` for the input code (so
+/// wake-ups and `suppress_osd` apply), else as a plain VCP write.
+pub fn set_input_code(
+    backend: &dyn Backend,
+    display_selector: &str,
+    code: u8,
+    value: u16,
+) -> Result<()> {
+    if code == VCP_INPUT {
+        backend.set_input(display_selector, value)
+    } else {
+        backend.set_vcp_raw(display_selector, code, value)
+    }
+}
+
+/// `Send + Sync` so a call can run on a worker thread (see `operation_timeout`).
+pub trait Backend: Send + Sync {
     fn list_displays(&self) -> Result<DisplayListReport>;
+    /// Cheaper enumeration for callers that only show displays (`list`): it may skip opening
+    /// DDC handles but must report the same indices as `list_displays`.
+    fn list_displays_quick(&self) -> Result<DisplayListReport> {
+        self.list_displays()
+    }
+
     fn set_input(&self, display_selector: &str, value: u16) -> Result<()>;
     fn get_input(&self, display_selector: &str) -> Result<u16>;
     fn doctor(&self) -> Result<DoctorReport>;
+
+    /// Drops anything cached about the connected displays, so the next call enumerates them
+    /// afresh (after a config reload, or when displays may have changed).
+    fn forget_displays(&self) {}
+
+    /// Cheap check that the display answers DDC/CI at all. Never fails: `None` means the
+    /// backend has no read to probe with.
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        let _ = display_selector;
+        None
+    }
+
+    /// Reads any VCP code as `(current, maximum)`.
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        let _ = (display_selector, code);
+        anyhow::bail!("Reading arbitrary VCP codes is not supported by this backend.")
+    }
+
+    /// Writes any VCP code.
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        let _ = (display_selector, code, value);
+        anyhow::bail!("Writing arbitrary VCP codes is not supported by this backend.")
+    }
+
+    /// The display's raw MCCS capabilities string, or the closest description the backend
+    /// can offer (see `monitorctl caps`).
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        let _ = display_selector;
+        anyhow::bail!("Reading capabilities is not supported by this backend.")
+    }
+
+    /// Reads several VCP codes as `(code, current, maximum)`. The default calls `get_vcp_raw`
+    /// per code; backends that can reuse one monitor handle for all reads should override it.
+    fn get_vcp_many(&self, display_selector: &str, codes: &[u8]) -> Result<Vec<(u8, u16, u16)>> {
+        codes
+            .iter()
+            .map(|&code| {
+                let (current, maximum) = self.get_vcp_raw(display_selector, code)?;
+                Ok((code, current, maximum))
+            })
+            .collect()
+    }
 }
 
+pub mod console;
+#[cfg(target_os = "linux")]
+mod linux_ddcutil;
 #[cfg(target_os = "macos")]
 mod macos_m1ddc;
+#[cfg(test)]
+pub(crate) mod mock;
+mod open;
+mod quiet_osd;
+mod read_only;
+mod retry;
+mod throttle;
+mod timeout;
 #[cfg(target_os = "windows")]
 mod windows_dxva2;
 
+pub use open::open_path;
+
 pub fn backend() -> Result<Box<dyn Backend>> {
+    backend_with_options(BackendOptions::default())
+}
+
+pub fn backend_with_options(options: BackendOptions) -> Result<Box<dyn Backend>> {
+    let interval = options.min_write_interval;
+    let read_only = options.read_only;
+    let suppress_osd = options.suppress_osd;
+    let operation_timeout = options.operation_timeout;
+    let (retries, retry_delay) = (options.retries, options.retry_delay);
+    let mut backend = platform_backend(options)?;
+    // Innermost, so the throttle spaces out the retries along with everything else.
+    if retries > 0 {
+        backend = Box::new(retry::Retry::new(backend, retries, retry_delay));
+    }
+    if !interval.is_zero() {
+        backend = Box::new(throttle::Throttled::new(backend, interval));
+    }
+    // Outside the throttle so the OSD writes around a switch are spaced out too.
+    if suppress_osd {
+        backend = Box::new(quiet_osd::QuietOsd::new(backend));
+    }
+    if !operation_timeout.is_zero() {
+        backend = Box::new(timeout::Timeout::new(backend, operation_timeout));
+    }
+    if read_only {
+        backend = Box::new(read_only::ReadOnly::new(backend));
+    }
+    Ok(backend)
+}
+
+fn platform_backend(options: BackendOptions) -> Result<Box<dyn Backend>> {
     #[cfg(target_os = "macos")]
     {
-        Ok(Box::new(macos_m1ddc::M1DdcBackend::new()))
+        Ok(Box::new(macos_m1ddc::M1DdcBackend::new(options)))
     }
 
     #[cfg(target_os = "windows")]
     {
-        Ok(Box::new(windows_dxva2::WindowsDxva2Backend::new()))
+        Ok(Box::new(windows_dxva2::WindowsDxva2Backend::new(options)))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Box::new(linux_ddcutil::LinuxDdcutilBackend::new(options)))
     }
 
-    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
     {
         use anyhow::bail;
-        bail!("Unsupported OS (supported: macOS and Windows).");
+        let _ = options;
+        bail!("Unsupported OS (supported: macOS, Windows and Linux).");
     }
 }
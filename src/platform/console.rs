@@ -0,0 +1,87 @@
+//! `monitorctl` is a console program, but people also start it by double-clicking it in
+//! Explorer. Windows then gives it a console of its own that closes the moment it exits, and
+//! started without any console its output goes nowhere. This keeps the output readable.
+
+/// Where the process's output ends up, as found by [`attach`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Console {
+    /// A terminal shared with the caller, or redirected output: nothing to do.
+    Shared,
+    /// A console window created just for this process; it closes when the process exits.
+    Own,
+    /// No console at all; errors can only be shown in a dialog.
+    Missing,
+}
+
+/// Finds out where output goes. Without a console, attaches to the parent's if it has one.
+/// Call this before anything is printed.
+#[cfg(target_os = "windows")]
+pub fn attach() -> Console {
+    use std::io::IsTerminal;
+    use windows::Win32::System::Console::{
+        AttachConsole, GetConsoleProcessList, GetConsoleWindow, ATTACH_PARENT_PROCESS,
+    };
+
+    unsafe {
+        if GetConsoleWindow().0.is_null() {
+            return match AttachConsole(ATTACH_PARENT_PROCESS) {
+                Ok(()) => Console::Shared,
+                Err(_) => Console::Missing,
+            };
+        }
+        if !std::io::stdout().is_terminal() {
+            return Console::Shared;
+        }
+        // A shell shares its console with us; when we're the only process on it, Windows
+        // created it for us.
+        let mut pids = [0u32; 2];
+        if GetConsoleProcessList(&mut pids) == 1 {
+            Console::Own
+        } else {
+            Console::Shared
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn attach() -> Console {
+    Console::Shared
+}
+
+/// Call right before exiting, with the error that was printed, if any. An [`Console::Own`]
+/// window waits for Enter so the output can be read; with [`Console::Missing`], an error is
+/// shown in a message box instead.
+#[cfg(target_os = "windows")]
+pub fn finish(console: Console, error: Option<&str>) {
+    use std::io::Write;
+    use windows::{
+        core::PCWSTR,
+        Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_ICONERROR, MB_OK},
+    };
+
+    match (console, error) {
+        (Console::Own, _) => {
+            eprint!("\nPress Enter to close this window.");
+            let _ = std::io::stderr().flush();
+            let _ = std::io::stdin().read_line(&mut String::new());
+        }
+        (Console::Missing, Some(error)) => {
+            let wide = |s: &str| s.encode_utf16().chain([0]).collect::<Vec<u16>>();
+            let (text, caption) = (wide(error), wide("monitorctl"));
+            unsafe {
+                MessageBoxW(
+                    None,
+                    PCWSTR::from_raw(text.as_ptr()),
+                    PCWSTR::from_raw(caption.as_ptr()),
+                    MB_OK | MB_ICONERROR,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn finish(console: Console, error: Option<&str>) {
+    let _ = (console, error);
+}
@@ -0,0 +1,551 @@
+use std::{
+    fs::{self, OpenOptions},
+    path::PathBuf,
+    process::Command,
+    thread,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use super::{
+    looks_internal, Backend, BackendOptions, DisplayInfo, DisplayListReport, DoctorReport,
+    DoctorStatus,
+};
+
+/// Drives `ddcutil`, which talks DDC/CI over the kernel's `/dev/i2c-*` devices.
+pub struct LinuxDdcutilBackend {
+    wake_before_write: bool,
+}
+
+impl LinuxDdcutilBackend {
+    pub fn new(options: BackendOptions) -> Self {
+        Self {
+            wake_before_write: options.wake_before_write,
+        }
+    }
+
+    fn env_override_path() -> Option<PathBuf> {
+        let path = PathBuf::from(std::env::var_os("MONITORCTL_DDCUTIL_PATH")?);
+        path.is_file().then_some(path)
+    }
+
+    fn ddcutil_path(&self) -> Result<PathBuf> {
+        if let Some(path) = Self::env_override_path() {
+            return Ok(path);
+        }
+        std::env::var_os("PATH")
+            .iter()
+            .flat_map(std::env::split_paths)
+            .map(|dir| dir.join("ddcutil"))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Missing dependency: `ddcutil`.\n\
+Install it with your package manager (e.g. `sudo apt install ddcutil`).\n\
+Override with: MONITORCTL_DDCUTIL_PATH=/path/to/ddcutil"
+                )
+            })
+    }
+
+    fn run_ddcutil(&self, args: &[&str]) -> Result<String> {
+        let ddcutil = self.ddcutil_path()?;
+        let out = Command::new(&ddcutil)
+            .args(args)
+            .output()
+            .with_context(|| format!("running {} {}", ddcutil.display(), args.join(" ")))?;
+        if !out.status.success() {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            bail!(
+                "ddcutil failed (exit={}):\nstdout:\n{}\nstderr:\n{}",
+                out.status,
+                stdout.trim(),
+                stderr.trim()
+            );
+        }
+        String::from_utf8(out.stdout).context("ddcutil output was not UTF-8")
+    }
+
+    /// `ddcutil --display <n> <args...>` for the display the selector names.
+    fn run_for_display(&self, display_selector: &str, args: &[&str]) -> Result<String> {
+        let index = self.resolve_selector(display_selector)?.to_string();
+        let mut full = vec!["--display", index.as_str()];
+        full.extend_from_slice(args);
+        self.run_ddcutil(&full)
+    }
+
+    fn resolve_selector(&self, display_selector: &str) -> Result<u32> {
+        if let Ok(index) = display_selector.parse::<u32>() {
+            if index == 0 {
+                bail!("display selector must be >= 1");
+            }
+            return Ok(index);
+        }
+
+        if let Some(needle) = display_selector.strip_prefix("name:") {
+            let needle = needle.trim().to_ascii_lowercase();
+            if needle.is_empty() {
+                bail!("display selector 'name:' requires a non-empty substring");
+            }
+            let displays = self.list_displays()?.displays;
+            let matches: Vec<&DisplayInfo> = displays
+                .iter()
+                .filter(|d| {
+                    d.product_name
+                        .as_deref()
+                        .is_some_and(|name| name.to_ascii_lowercase().contains(&needle))
+                })
+                .collect();
+            return match matches.as_slice() {
+                [only] => Ok(only.index),
+                [] => bail!(
+                    "No monitors matched selector '{display_selector}'. Available:\n{}",
+                    format_display_list(&displays)
+                ),
+                _ => bail!(
+                    "Selector '{display_selector}' is ambiguous. Matches:\n{}\n\nUse `--display <index>` from `list`, or a more specific `name:<substring>`.",
+                    format_display_list(&matches.into_iter().cloned().collect::<Vec<_>>())
+                ),
+            };
+        }
+
+        bail!(
+            "Invalid display selector '{display_selector}'. Expected a 1-based index (e.g. '1') or `name:<substring>`."
+        );
+    }
+}
+
+fn format_display_list(displays: &[DisplayInfo]) -> String {
+    displays
+        .iter()
+        .map(|d| {
+            format!(
+                "  [{}] {}",
+                d.index,
+                d.product_name.as_deref().unwrap_or("?")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses `ddcutil detect`. Each usable display starts with an unindented `Display N` line
+/// followed by indented `Key: value` lines. Other unindented headers (`Invalid display`,
+/// `Phantom display`, ...) describe devices ddcutil can't talk to; their blocks are skipped.
+fn parse_detect(raw: &str) -> Vec<DisplayInfo> {
+    let mut displays: Vec<DisplayInfo> = Vec::new();
+    // Whether the lines being read belong to the last pushed display.
+    let mut in_display = false;
+
+    // Example:
+    // Display 1
+    //    I2C bus:  /dev/i2c-4
+    //    DRM connector:           card0-DP-1
+    //    EDID synopsis:
+    //       Mfg id:               DEL
+    //       Model:                DELL U2720Q
+    //       Serial number:        ABC1234
+    //
+    // Invalid display
+    //    I2C bus:  /dev/i2c-5
+    for line in raw.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            let index = line
+                .strip_prefix("Display ")
+                .and_then(|n| n.trim().parse::<u32>().ok());
+            in_display = index.is_some();
+            if let Some(index) = index {
+                displays.push(DisplayInfo {
+                    index,
+                    product_name: None,
+                    system_uuid: None,
+                    serial: None,
+                    resolution: None,
+                    refresh_hz: None,
+                    internal: false,
+                    ddc_responsive: None,
+                    connector: None,
+                });
+            }
+            continue;
+        }
+        if !in_display {
+            continue;
+        }
+        let Some(display) = displays.last_mut() else {
+            continue;
+        };
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match key.trim() {
+            "Model" => display.product_name = Some(value.to_string()),
+            "Serial number" => display.serial = Some(value.to_string()),
+            "DRM connector" => display.connector = Some(drm_connector_label(value)),
+            _ => {}
+        }
+    }
+
+    for display in &mut displays {
+        display.internal = looks_internal(display.product_name.as_deref())
+            || display
+                .connector
+                .as_deref()
+                .is_some_and(|c| c.starts_with("eDP") || c.starts_with("LVDS"));
+    }
+    displays
+}
+
+/// `card0-DP-1` -> `DP-1`, matching the labels the Windows backend reports.
+fn drm_connector_label(raw: &str) -> String {
+    match raw.split_once('-') {
+        Some((card, rest)) if card.starts_with("card") && !rest.is_empty() => rest.to_string(),
+        _ => raw.to_string(),
+    }
+}
+
+/// Parses `ddcutil getvcp --brief` output into `(current, maximum)`:
+/// `VCP 10 C 50 100` for continuous features, `VCP 60 SNC x0f` for simple non-continuous ones
+/// (maximum reported as 0), and `VCP 60 CNC x00 x00 x00 x0f` (mh ml sh sl) for complex ones.
+fn parse_getvcp_brief(raw: &str, code: u8) -> Result<(u16, u16)> {
+    let line = raw
+        .lines()
+        .map(str::trim)
+        .find(|l| l.starts_with("VCP "))
+        .ok_or_else(|| {
+            anyhow!(
+                "ddcutil returned no value for VCP 0x{code:02X}: {}",
+                raw.trim()
+            )
+        })?;
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let hex = |s: &str| {
+        u16::from_str_radix(s.trim_start_matches('x'), 16)
+            .with_context(|| format!("unexpected ddcutil value '{s}' in: {line}"))
+    };
+    match tokens.as_slice() {
+        [_, _, "C", current, maximum, ..] => Ok((
+            current
+                .parse()
+                .with_context(|| format!("unexpected ddcutil output: {line}"))?,
+            maximum
+                .parse()
+                .with_context(|| format!("unexpected ddcutil output: {line}"))?,
+        )),
+        [_, _, "SNC", value, ..] => Ok((hex(value)?, 0)),
+        [_, _, "CNC", _, _, sh, sl, ..] => Ok(((hex(sh)? << 8) | hex(sl)?, 0)),
+        [_, _, "ERR", ..] => bail!("The monitor did not answer a read of VCP 0x{code:02X}."),
+        _ => bail!("unexpected ddcutil output: {line}"),
+    }
+}
+
+/// i2c device nodes and how many of them this user may open read-write.
+fn i2c_device_access() -> (usize, usize) {
+    let Ok(entries) = fs::read_dir("/dev") else {
+        return (0, 0);
+    };
+    let devices: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("i2c-"))
+        .map(|e| e.path())
+        .collect();
+    let accessible = devices
+        .iter()
+        .filter(|path| OpenOptions::new().read(true).write(true).open(path).is_ok())
+        .count();
+    (devices.len(), accessible)
+}
+
+impl Backend for LinuxDdcutilBackend {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        let raw = self.run_ddcutil(&["detect"])?;
+        Ok(DisplayListReport {
+            displays: parse_detect(&raw),
+            raw: Some(raw),
+        })
+    }
+
+    fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        if self.wake_before_write {
+            // Errors are expected from a channel that is still asleep.
+            let _ = self.get_input(display_selector);
+            thread::sleep(super::WAKE_DELAY);
+        }
+
+        let value_str = value.to_string();
+        let _ = self.run_for_display(display_selector, &["setvcp", "60", &value_str])?;
+        Ok(())
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        let (current, _) = self.get_vcp_raw(display_selector, 0x60)?;
+        Ok(current)
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        Some(self.get_input(display_selector).is_ok())
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        let code_str = format!("{code:02x}");
+        let raw = self.run_for_display(display_selector, &["--brief", "getvcp", &code_str])?;
+        parse_getvcp_brief(&raw, code)
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        let code_str = format!("{code:02x}");
+        let value_str = value.to_string();
+        let _ = self.run_for_display(display_selector, &["setvcp", &code_str, &value_str])?;
+        Ok(())
+    }
+
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        // `--verbose` adds the raw string ahead of ddcutil's own interpretation.
+        let out = self.run_for_display(display_selector, &["capabilities", "--verbose"])?;
+        Ok(out
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("Unparsed capabilities string:"))
+            .map(|s| s.trim().to_string())
+            .unwrap_or(out))
+    }
+
+    fn doctor(&self) -> Result<DoctorReport> {
+        let mut messages = Vec::new();
+
+        let ddcutil = match self.ddcutil_path() {
+            Ok(p) => p,
+            Err(e) => {
+                return Ok(DoctorReport {
+                    status: DoctorStatus::ToolMissing,
+                    message: e.to_string(),
+                    probes: Vec::new(),
+                    tool_path: None,
+                    get_input_supported: None,
+                });
+            }
+        };
+        messages.push(format!("ddcutil: OK ({})", ddcutil.display()));
+
+        let no_access = match i2c_device_access() {
+            (0, _) => Some(
+                "No /dev/i2c-* devices. Load the i2c-dev module: `sudo modprobe i2c-dev`"
+                    .to_string(),
+            ),
+            (total, 0) => Some(format!(
+                "Found {total} /dev/i2c-* device(s), but none can be opened read-write by this user.\n\
+Add yourself to the `i2c` group (`sudo usermod -aG i2c $USER`) or install ddcutil's udev rule, then log in again."
+            )),
+            (total, accessible) => {
+                messages.push(format!(
+                    "i2c: OK ({accessible} of {total} /dev/i2c-* device(s) accessible)"
+                ));
+                None
+            }
+        };
+        if let Some(message) = no_access {
+            messages.push(message);
+            return Ok(DoctorReport {
+                status: DoctorStatus::NoDeviceAccess,
+                message: messages.join("\n\n"),
+                probes: Vec::new(),
+                tool_path: Some(ddcutil),
+                get_input_supported: None,
+            });
+        }
+
+        match self.list_displays() {
+            Ok(report) if report.displays.is_empty() => {
+                messages.push("ddcutil ran but found no DDC/CI-capable displays.".to_string());
+                Ok(DoctorReport {
+                    status: DoctorStatus::NoDisplays,
+                    message: messages.join("\n\n"),
+                    probes: Vec::new(),
+                    tool_path: Some(ddcutil.clone()),
+                    get_input_supported: None,
+                })
+            }
+            Ok(report) => {
+                messages.push(format!(
+                    "Detected displays:\n{}",
+                    format_display_list(&report.displays)
+                ));
+                // ddcutil reads VCP 0x60 like any other code; whether it works is up to the
+                // monitor, so ask the first one.
+                let first = report.displays[0].index.to_string();
+                let get_input_supported = self.get_input(&first).is_ok();
+                if !get_input_supported {
+                    messages.push(format!(
+                        "get-input: display {first} didn't answer a read of VCP 0x60."
+                    ));
+                }
+                Ok(DoctorReport {
+                    status: DoctorStatus::Ok,
+                    message: messages.join("\n\n"),
+                    probes: Vec::new(),
+                    tool_path: Some(ddcutil.clone()),
+                    get_input_supported: Some(get_input_supported),
+                })
+            }
+            Err(e) => {
+                messages.push(format!("ddcutil failed to list displays: {e}"));
+                Ok(DoctorReport {
+                    status: DoctorStatus::EnumerationFailed,
+                    message: messages.join("\n\n"),
+                    probes: Vec::new(),
+                    tool_path: Some(ddcutil.clone()),
+                    get_input_supported: None,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DETECT: &str = "\
+Display 1
+   I2C bus:  /dev/i2c-4
+   DRM connector:           card0-DP-1
+   EDID synopsis:
+      Mfg id:               DEL
+      Model:                DELL U2720Q
+      Serial number:        ABC1234
+   VCP version:         2.1
+
+Invalid display
+   I2C bus:  /dev/i2c-5
+   DRM connector:           card0-eDP-1
+   EDID synopsis:
+      Model:                Panel Nobody Reads
+      Serial number:        000
+   DDC communication failed
+
+Display 2
+   I2C bus:  /dev/i2c-7
+   DRM connector:           card1-HDMI-A-1
+   EDID synopsis:
+      Model:                LG HDR 4K
+      Serial number:
+   VCP version:         2.2
+";
+
+    #[test]
+    fn detect_lists_every_display_and_skips_invalid_blocks() {
+        let displays = parse_detect(DETECT);
+        assert_eq!(displays.len(), 2);
+
+        assert_eq!(displays[0].index, 1);
+        assert_eq!(displays[0].product_name.as_deref(), Some("DELL U2720Q"));
+        assert_eq!(displays[0].serial.as_deref(), Some("ABC1234"));
+        assert_eq!(displays[0].connector.as_deref(), Some("DP-1"));
+        assert!(!displays[0].internal);
+
+        // The invalid block's fields must not leak into its neighbours.
+        assert_eq!(displays[1].index, 2);
+        assert_eq!(displays[1].product_name.as_deref(), Some("LG HDR 4K"));
+        assert_eq!(displays[1].serial, None);
+        assert_eq!(displays[1].connector.as_deref(), Some("HDMI-A-1"));
+    }
+
+    #[test]
+    fn detect_handles_no_displays_and_internal_panels() {
+        assert!(parse_detect("").is_empty());
+        assert!(parse_detect("Invalid display\n   I2C bus:  /dev/i2c-5\n").is_empty());
+
+        let displays = parse_detect("Display 1\n   DRM connector:  card0-eDP-1\n");
+        assert_eq!(displays.len(), 1);
+        assert!(displays[0].internal);
+    }
+
+    #[test]
+    fn getvcp_brief_formats() {
+        let cases: &[(&str, (u16, u16))] = &[
+            ("VCP 60 SNC x0f", (0x0f, 0)),
+            ("VCP 60 SNC x1b\n", (0x1b, 0)),
+            ("VCP 10 C 50 100", (50, 100)),
+            ("VCP 12 C 0 100", (0, 100)),
+            ("VCP 60 CNC x00 x00 x01 x0f", (0x010f, 0)),
+            // Noise before the value line is ignored.
+            ("Sleeping...\n  VCP 10 C 75 100\n", (75, 100)),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(
+                parse_getvcp_brief(raw, 0x60).unwrap(),
+                *expected,
+                "parsing {raw:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn getvcp_brief_rejects_errors_and_garbage() {
+        for raw in [
+            "",
+            "No monitor detected",
+            "VCP 60 ERR",
+            "VCP 60 SNC zz",
+            "VCP 10 C fifty 100",
+            "VCP 60",
+        ] {
+            assert!(parse_getvcp_brief(raw, 0x60).is_err(), "accepted {raw:?}");
+        }
+    }
+
+    /// Runs `f` against a stand-in `ddcutil` that logs its arguments, one call per line, and
+    /// returns what `f` returned along with that log.
+    fn with_fake_ddcutil<T>(name: &str, f: impl FnOnce() -> T) -> (T, Vec<String>) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::config::tests::scratch_dir(name);
+        let script = dir.join("ddcutil");
+        let log = dir.join("calls.log");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$*\" >> '{}'\ncase \"$*\" in *getvcp*) echo 'VCP 60 SNC x0f';; esac\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let result =
+            crate::config::tests::with_env(&[("MONITORCTL_DDCUTIL_PATH", script.to_str())], f);
+        let calls = fs::read_to_string(&log)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let _ = fs::remove_dir_all(&dir);
+        (result, calls)
+    }
+
+    fn backend(wake_before_write: bool) -> LinuxDdcutilBackend {
+        LinuxDdcutilBackend::new(BackendOptions {
+            wake_before_write,
+            ..BackendOptions::default()
+        })
+    }
+
+    #[test]
+    fn wake_before_write_reads_before_the_write() {
+        let (result, calls) = with_fake_ddcutil("wake", || backend(true).set_input("2", 15));
+        result.unwrap();
+        assert_eq!(
+            calls,
+            ["--display 2 --brief getvcp 60", "--display 2 setvcp 60 15"]
+        );
+
+        let (result, calls) = with_fake_ddcutil("no-wake", || backend(false).set_input("2", 15));
+        result.unwrap();
+        assert_eq!(calls, ["--display 2 setvcp 60 15"]);
+    }
+}
@@ -1,27 +1,49 @@
 use std::{
+    io,
     path::{Path, PathBuf},
     process::Command,
+    sync::Mutex,
+    thread,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 
-use super::{DisplayInfo, DisplayListReport, DoctorReport};
+use super::{
+    looks_internal, BackendOptions, DisplayInfo, DisplayListReport, DoctorReport, DoctorStatus,
+};
 
-pub struct M1DdcBackend;
+pub struct M1DdcBackend {
+    // The PATH fallback spawns a login shell, so keep the result for the lifetime of the
+    // backend (i.e. the whole tray session).
+    cached_path: Mutex<Option<PathBuf>>,
+    extra_args: Vec<String>,
+    wake_before_write: bool,
+}
 
 impl M1DdcBackend {
-    pub fn new() -> Self {
-        Self
+    pub fn new(options: BackendOptions) -> Self {
+        Self {
+            cached_path: Mutex::new(None),
+            extra_args: options.m1ddc_extra_args,
+            wake_before_write: options.wake_before_write,
+        }
     }
 
-    fn resolve_m1ddc_path() -> Option<PathBuf> {
-        if let Ok(path) = std::env::var("MONITORCTL_M1DDC_PATH") {
-            let path = PathBuf::from(path);
-            if path.is_file() {
-                return Some(path);
-            }
-        }
+    /// Full argument list for one invocation: user extras first, then the command itself.
+    fn command_args<'a>(&'a self, args: &[&'a str]) -> Vec<&'a str> {
+        self.extra_args
+            .iter()
+            .map(String::as_str)
+            .chain(args.iter().copied())
+            .collect()
+    }
 
+    fn env_override_path() -> Option<PathBuf> {
+        let path = PathBuf::from(std::env::var_os("MONITORCTL_M1DDC_PATH")?);
+        path.is_file().then_some(path)
+    }
+
+    fn resolve_m1ddc_path() -> Option<PathBuf> {
         // GUI apps / LaunchAgents often have a minimal PATH. Prefer common Homebrew paths.
         for candidate in ["/opt/homebrew/bin/m1ddc", "/usr/local/bin/m1ddc"] {
             let candidate = Path::new(candidate);
@@ -49,7 +71,20 @@ impl M1DdcBackend {
     }
 
     fn m1ddc_path(&self) -> Result<PathBuf> {
-        Self::resolve_m1ddc_path().ok_or_else(|| {
+        // The env override is cheap to check, so it always wins and is never cached.
+        if let Some(path) = Self::env_override_path() {
+            return Ok(path);
+        }
+
+        let mut cached = self
+            .cached_path
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(path) = cached.as_ref() {
+            return Ok(path.clone());
+        }
+
+        let path = Self::resolve_m1ddc_path().ok_or_else(|| {
             anyhow!(
                 "Missing dependency: `m1ddc`.\n\
 Install: `brew install m1ddc`\n\
@@ -57,15 +92,33 @@ If you launch the tray app from Finder/LaunchAgent, PATH may not include Homebre
 Expected locations: /opt/homebrew/bin/m1ddc or /usr/local/bin/m1ddc\n\
 Override with: MONITORCTL_M1DDC_PATH=/path/to/m1ddc"
             )
-        })
+        })?;
+        *cached = Some(path.clone());
+        Ok(path)
+    }
+
+    fn forget_cached_path(&self) {
+        let mut cached = self
+            .cached_path
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *cached = None;
     }
 
     fn run_m1ddc(&self, args: &[&str]) -> Result<String> {
         let m1ddc = self.m1ddc_path()?;
-        let out = Command::new(&m1ddc)
-            .args(args)
-            .output()
-            .with_context(|| format!("running {} {}", m1ddc.display(), args.join(" ")))?;
+        let args = self.command_args(args);
+        let out = match Command::new(&m1ddc).args(&args).output() {
+            Ok(out) => out,
+            Err(e) => {
+                // The binary was moved/uninstalled since we cached it; resolve again next time.
+                if e.kind() == io::ErrorKind::NotFound {
+                    self.forget_cached_path();
+                }
+                return Err(e)
+                    .with_context(|| format!("running {} {}", m1ddc.display(), args.join(" ")));
+            }
+        };
         if !out.status.success() {
             let stderr = String::from_utf8_lossy(&out.stderr);
             let stdout = String::from_utf8_lossy(&out.stdout);
@@ -76,53 +129,155 @@ Override with: MONITORCTL_M1DDC_PATH=/path/to/m1ddc"
                 stderr.trim()
             );
         }
-        String::from_utf8(out.stdout).context("m1ddc output was not UTF-8")
+        let stdout = String::from_utf8(out.stdout).context("m1ddc output was not UTF-8")?;
+        Ok(strip_ansi(&stdout))
     }
 }
 
-impl super::Backend for M1DdcBackend {
-    fn list_displays(&self) -> Result<DisplayListReport> {
-        let raw = self.run_m1ddc(&["display", "list", "detailed"])?;
-        let mut displays = Vec::new();
-
-        // Example:
-        // [1] XG27ACS (UUID)
-        //  - Product name:  XG27ACS
-        //  - System UUID:   UUID
-        // ...
-        for line in raw.lines() {
-            let line = line.trim_end();
-            if let Some(rest) = line.strip_prefix('[') {
-                if let Some((idx_str, after_idx)) = rest.split_once(']') {
-                    let index: u32 = idx_str.trim().parse().ok().unwrap_or(0);
-                    let mut product_name: Option<String> = None;
-                    let mut system_uuid: Option<String> = None;
-
-                    // Try to extract "Name (UUID)" from header line.
-                    let header = after_idx.trim();
-                    if !header.is_empty() {
-                        if let Some((name, uuid_part)) = header.rsplit_once('(') {
-                            let name = name.trim();
-                            let uuid = uuid_part.trim().trim_end_matches(')');
-                            if !name.is_empty() {
-                                product_name = Some(name.to_string());
-                            }
-                            if !uuid.is_empty() {
-                                system_uuid = Some(uuid.to_string());
-                            }
-                        } else {
-                            product_name = Some(header.to_string());
+/// Removes ANSI escape sequences and BOMs that some m1ddc builds/shells inject, so the line
+/// parsers only ever see plain text.
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{feff}' => {}
+            '\x1b' => match chars.next() {
+                // CSI: `ESC [` params/intermediates, ended by a byte in `@`..=`~`.
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
                         }
                     }
+                }
+                // OSC: `ESC ]` ... terminated by BEL or `ESC \`.
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Other two-byte escapes.
+                _ => {}
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
 
-                    displays.push(DisplayInfo {
-                        index,
-                        product_name,
-                        system_uuid,
-                    });
+/// m1ddc addresses features by name, so only the VCP codes it knows are reachable.
+fn m1ddc_feature(code: u8) -> Option<&'static str> {
+    match code {
+        super::VCP_BRIGHTNESS => Some("luminance"),
+        super::VCP_CONTRAST => Some("contrast"),
+        super::VCP_VOLUME => Some("volume"),
+        _ => None,
+    }
+}
+
+/// The integer that ends m1ddc's output, e.g. `26` or `input: 26`.
+fn parse_trailing_u16(out: &str) -> Option<u16> {
+    let out = out.trim();
+    let digits_at = out
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    out[digits_at..].parse().ok()
+}
+
+/// Splits a `Name (UUID)` header into name and UUID. Only the parenthesized group that ends
+/// the line is the UUID, so names with parentheses of their own ("Dell (Work) U2720Q") stay
+/// whole.
+fn split_header(header: &str) -> (Option<&str>, Option<&str>) {
+    fn non_empty(s: &str) -> Option<&str> {
+        let s = s.trim();
+        (!s.is_empty()).then_some(s)
+    }
+    let Some(inner) = header.strip_suffix(')') else {
+        return (non_empty(header), None);
+    };
+    // Walk back to the '(' that matches the final ')'.
+    let mut depth = 0usize;
+    for (i, c) in inner.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth == 0 => return (non_empty(&inner[..i]), non_empty(&inner[i + 1..])),
+            '(' => depth -= 1,
+            _ => {}
+        }
+    }
+    (non_empty(header), None)
+}
+
+/// Parses `m1ddc display list [detailed]`. Both shapes start each display with a
+/// `[n] Name (UUID)` header; only `detailed` follows it with `- Key: value` lines.
+fn parse_display_list(raw: &str) -> Vec<DisplayInfo> {
+    let mut displays: Vec<DisplayInfo> = Vec::new();
+
+    // Example:
+    // [1] XG27ACS (UUID)
+    //  - Product name:  XG27ACS
+    //  - Alphanumeric serial: ABC123
+    //  - System UUID:   UUID
+    // ...
+    for line in raw.lines() {
+        let line = line.trim_end();
+        if let Some(detail) = line.trim_start().strip_prefix("- ") {
+            // Detail lines belong to the most recent `[n]` header.
+            if let (Some(display), Some((key, value))) =
+                (displays.last_mut(), detail.split_once(':'))
+            {
+                let key = key.to_ascii_lowercase();
+                let value = value.trim();
+                // Prefer the alphanumeric serial when both variants are listed.
+                if key.contains("serial")
+                    && !value.is_empty()
+                    && (display.serial.is_none() || key.contains("alphanumeric"))
+                {
+                    display.serial = Some(value.to_string());
                 }
             }
+            continue;
         }
+        if let Some(rest) = line.strip_prefix('[') {
+            if let Some((idx_str, after_idx)) = rest.split_once(']') {
+                let index: u32 = idx_str.trim().parse().ok().unwrap_or(0);
+                let (name, uuid) = split_header(after_idx.trim());
+                let product_name = name.map(str::to_string);
+                let system_uuid = uuid.map(str::to_string);
+
+                let internal = looks_internal(product_name.as_deref());
+                displays.push(DisplayInfo {
+                    index,
+                    product_name,
+                    system_uuid,
+                    serial: None,
+                    resolution: None,
+                    refresh_hz: None,
+                    internal,
+                    ddc_responsive: None,
+                    connector: None,
+                });
+            }
+        }
+    }
+
+    displays
+}
+
+impl super::Backend for M1DdcBackend {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        // Older m1ddc builds don't know `detailed`; the plain listing still has index, name and
+        // UUID, just no serial.
+        let raw = match self.run_m1ddc(&["display", "list", "detailed"]) {
+            Ok(raw) => raw,
+            Err(detailed) => self.run_m1ddc(&["display", "list"]).with_context(|| {
+                format!("m1ddc display list (after `display list detailed` failed: {detailed:#})")
+            })?,
+        };
+        let displays = parse_display_list(&raw);
 
         if displays.is_empty() {
             return Err(anyhow!(
@@ -138,14 +293,62 @@ impl super::Backend for M1DdcBackend {
     }
 
     fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        if self.wake_before_write {
+            // Any read will do; luminance is the one m1ddc reads reliably. Errors are expected
+            // from a channel that is still asleep.
+            let _ = self.run_m1ddc(&["display", display_selector, "get", "luminance"]);
+            thread::sleep(super::WAKE_DELAY);
+        }
+
         // `m1ddc display <selector> set input <n>`
         let value_str = value.to_string();
         let _ = self.run_m1ddc(&["display", display_selector, "set", "input", &value_str])?;
         Ok(())
     }
 
-    fn get_input(&self, _display_selector: &str) -> Result<u16> {
-        bail!("get-input is not implemented for the macOS m1ddc backend (m1ddc does not reliably expose raw VCP 0x60 reads).");
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        let feature =
+            m1ddc_feature(code).ok_or_else(|| anyhow!("m1ddc cannot read VCP 0x{code:02X}."))?;
+        let out = self.run_m1ddc(&["display", display_selector, "get", feature])?;
+        let current = parse_trailing_u16(&out).ok_or_else(|| {
+            anyhow!(
+                "unexpected m1ddc output for `get {feature}`: {}",
+                out.trim()
+            )
+        })?;
+        // m1ddc doesn't report the maximum.
+        Ok((current, 0))
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        let feature =
+            m1ddc_feature(code).ok_or_else(|| anyhow!("m1ddc cannot write VCP 0x{code:02X}."))?;
+        let value_str = value.to_string();
+        let _ = self.run_m1ddc(&["display", display_selector, "set", feature, &value_str])?;
+        Ok(())
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        // Luminance is the read m1ddc answers most reliably (see `wake_before_write`).
+        Some(
+            self.run_m1ddc(&["display", display_selector, "get", "luminance"])
+                .is_ok(),
+        )
+    }
+
+    fn capabilities(&self, _display_selector: &str) -> Result<String> {
+        // m1ddc can't fetch the MCCS capabilities string; its detailed listing (all displays)
+        // is the closest thing to share.
+        self.run_m1ddc(&["display", "list", "detailed"])
+            .context("m1ddc display list detailed")
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        // `m1ddc display <selector> get input`; older m1ddc builds don't know the feature.
+        self.run_m1ddc(&["display", display_selector, "get", "input"])
+            .ok()
+            .and_then(|out| parse_trailing_u16(&out))
+            .ok_or_else(|| anyhow!("get-input is not supported by this m1ddc build (it does not expose raw VCP 0x60 reads). Update m1ddc: `brew upgrade m1ddc`."))
     }
 
     fn doctor(&self) -> Result<DoctorReport> {
@@ -156,8 +359,11 @@ impl super::Backend for M1DdcBackend {
             Err(e) => {
                 messages.push(e.to_string());
                 return Ok(DoctorReport {
-                    ok: false,
+                    status: DoctorStatus::ToolMissing,
                     message: messages.join("\n"),
+                    probes: Vec::new(),
+                    tool_path: None,
+                    get_input_supported: None,
                 });
             }
         };
@@ -170,24 +376,214 @@ impl super::Backend for M1DdcBackend {
                 if out.is_empty() {
                     messages.push("m1ddc ran but returned no displays.".to_string());
                     return Ok(DoctorReport {
-                        ok: false,
+                        status: DoctorStatus::NoDisplays,
                         message: messages.join("\n"),
+                        probes: Vec::new(),
+                        tool_path: Some(m1ddc),
+                        get_input_supported: None,
                     });
                 }
                 messages.push(format!("Detected displays:\n{}", out));
-                messages.push(
-                    "Note: m1ddc can set input, but does not expose reading raw VCP 0x60 on all monitors."
-                        .to_string(),
-                );
+                // Only recent m1ddc builds know `get input`; ask the first display.
+                let get_input_supported = self.get_input("1").is_ok();
+                messages.push(if get_input_supported {
+                    "get-input: OK".to_string()
+                } else {
+                    "get-input: no answer from display 1. Older m1ddc builds can't read the current input (raw VCP 0x60); `brew upgrade m1ddc` adds it.".to_string()
+                });
                 Ok(DoctorReport {
-                    ok: true,
+                    status: DoctorStatus::Ok,
                     message: messages.join("\n\n"),
+                    probes: Vec::new(),
+                    tool_path: Some(m1ddc),
+                    get_input_supported: Some(get_input_supported),
                 })
             }
             Err(e) => Ok(DoctorReport {
-                ok: false,
+                status: DoctorStatus::EnumerationFailed,
                 message: format!("m1ddc failed to list displays: {e}"),
+                probes: Vec::new(),
+                tool_path: Some(m1ddc),
+                get_input_supported: None,
             }),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Backend;
+
+    #[test]
+    fn extra_args_come_before_the_command() {
+        let backend = M1DdcBackend::new(BackendOptions {
+            m1ddc_extra_args: vec!["--verbose".to_string(), "-x".to_string()],
+            ..BackendOptions::default()
+        });
+        assert_eq!(
+            backend.command_args(&["display", "2", "set", "input", "15"]),
+            ["--verbose", "-x", "display", "2", "set", "input", "15"]
+        );
+
+        let plain = M1DdcBackend::new(BackendOptions::default());
+        assert_eq!(
+            plain.command_args(&["display", "list"]),
+            ["display", "list"]
+        );
+    }
+
+    #[test]
+    fn strip_ansi_removes_escapes_and_boms() {
+        assert_eq!(strip_ansi("plain 26\n"), "plain 26\n");
+        assert_eq!(strip_ansi("\u{feff}[1] DELL"), "[1] DELL");
+        assert_eq!(strip_ansi("\x1b[1;32m[1]\x1b[0m DELL"), "[1] DELL");
+        assert_eq!(strip_ansi("\x1b]0;title\x07input: 26"), "input: 26");
+        assert_eq!(strip_ansi("\x1b]8;;file:///x\x1b\\26"), "26");
+        assert_eq!(strip_ansi("\x1b=26"), "26");
+        // A sequence cut off at the end of the output just drops the rest.
+        assert_eq!(strip_ansi("26\x1b[1;3"), "26");
+    }
+
+    /// Runs `f` with `MONITORCTL_M1DDC_PATH` pointing at a shell script made of `body`.
+    fn with_fake_m1ddc<T>(name: &str, body: &str, f: impl FnOnce() -> T) -> T {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::config::tests::scratch_dir(name);
+        let script = dir.join("m1ddc");
+        std::fs::write(&script, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let result =
+            crate::config::tests::with_env(&[("MONITORCTL_M1DDC_PATH", script.to_str())], f);
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+
+    #[test]
+    fn display_list_parses_both_shapes() {
+        let detailed = "\
+[1] DELL U2720Q (UUID-1)
+ - Product name:  DELL U2720Q
+ - Serial number: 123
+ - Alphanumeric serial: ABC123
+ - System UUID:   UUID-1
+[2] LG HDR (UUID-2)
+ - Serial number: 456
+";
+        let displays = parse_display_list(detailed);
+        let summary = displays
+            .iter()
+            .map(|d| (d.index, d.product_name.as_deref(), d.serial.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (1, Some("DELL U2720Q"), Some("ABC123")),
+                (2, Some("LG HDR"), Some("456")),
+            ]
+        );
+
+        let plain = parse_display_list("[1] DELL U2720Q (UUID-1)\n[2] LG HDR (UUID-2)\n");
+        assert_eq!(plain.len(), 2);
+        assert_eq!(plain[1].system_uuid.as_deref(), Some("UUID-2"));
+        assert!(plain.iter().all(|d| d.serial.is_none()));
+    }
+
+    #[test]
+    fn display_list_falls_back_to_the_plain_listing() {
+        let body = r#"case "$*" in
+  "display list detailed") echo "unknown command" >&2; exit 1;;
+  "display list") echo "[1] DELL U2720Q (UUID-1)";;
+esac"#;
+        let report = with_fake_m1ddc("plain-list", body, || {
+            M1DdcBackend::new(BackendOptions::default()).list_displays()
+        })
+        .unwrap();
+        assert_eq!(report.displays.len(), 1);
+        assert_eq!(
+            report.displays[0].product_name.as_deref(),
+            Some("DELL U2720Q")
+        );
+
+        let e = with_fake_m1ddc("no-list", "exit 1", || {
+            M1DdcBackend::new(BackendOptions::default()).list_displays()
+        })
+        .unwrap_err();
+        assert!(
+            format!("{e:#}").contains("after `display list detailed` failed"),
+            "{e:#}"
+        );
+    }
+
+    #[test]
+    fn only_the_final_parenthesized_group_is_the_uuid() {
+        let cases = [
+            (
+                "DELL U2720Q (UUID-1)",
+                (Some("DELL U2720Q"), Some("UUID-1")),
+            ),
+            (
+                "Dell (Work) U2720Q (UUID-1)",
+                (Some("Dell (Work) U2720Q"), Some("UUID-1")),
+            ),
+            (
+                "LG (HDR (4K)) (UUID-2)",
+                (Some("LG (HDR (4K))"), Some("UUID-2")),
+            ),
+            ("Dell (Work) U2720Q", (Some("Dell (Work) U2720Q"), None)),
+            ("XG27ACS", (Some("XG27ACS"), None)),
+            ("(UUID-3)", (None, Some("UUID-3"))),
+            ("Odd ) name (UUID-4)", (Some("Odd ) name"), Some("UUID-4"))),
+            ("Unbalanced)", (Some("Unbalanced)"), None)),
+            ("", (None, None)),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(split_header(header), expected, "{header:?}");
+        }
+
+        let displays = parse_display_list("[1] Dell (Work) U2720Q (UUID-1)\n");
+        assert_eq!(
+            displays[0].product_name.as_deref(),
+            Some("Dell (Work) U2720Q")
+        );
+        assert_eq!(displays[0].system_uuid.as_deref(), Some("UUID-1"));
+    }
+
+    #[test]
+    fn the_trailing_number_is_the_input() {
+        for out in ["26", "26\n", "input: 26", "  input: 26 \r\n"] {
+            assert_eq!(parse_trailing_u16(out), Some(26), "{out:?}");
+        }
+        for out in [
+            "",
+            "\n",
+            "input:",
+            "unknown feature",
+            "26 (DisplayPort)",
+            "70000",
+        ] {
+            assert_eq!(parse_trailing_u16(out), None, "{out:?}");
+        }
+    }
+
+    #[test]
+    fn get_input_reads_through_m1ddc_and_explains_old_builds() {
+        let value = with_fake_m1ddc(
+            "get-input",
+            r#"[ "$*" = "display 2 get input" ] && echo "input: 17""#,
+            || M1DdcBackend::new(BackendOptions::default()).get_input("2"),
+        );
+        assert_eq!(value.unwrap(), 17);
+
+        for (name, body) in [
+            ("get-input-old", "echo 'Unknown command' >&2; exit 1"),
+            ("get-input-text", "echo 'not supported'"),
+        ] {
+            let e = with_fake_m1ddc(name, body, || {
+                M1DdcBackend::new(BackendOptions::default()).get_input("1")
+            })
+            .unwrap_err();
+            assert!(e.to_string().contains("brew upgrade m1ddc"), "{name}: {e}");
+        }
+    }
+}
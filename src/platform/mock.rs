@@ -0,0 +1,247 @@
+//! In-memory [`Backend`] for unit tests. VCP values live in a map, writes are logged, and
+//! chosen selectors can be made to fail or to answer slowly. Clones share their state, so a
+//! test can hand one to a wrapper and inspect it through another.
+//!
+//! Also compiled into `monitorctl`'s own tests (see `main.rs`), hence the `crate::platform`
+//! paths. Each side uses a different subset of it.
+#![allow(dead_code)]
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::platform::{
+    Backend, DisplayInfo, DisplayListReport, DoctorReport, DoctorStatus, VCP_INPUT,
+};
+
+#[derive(Clone, Default)]
+pub struct MockBackend {
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    displays: Mutex<Vec<DisplayInfo>>,
+    /// Current value of each `(selector, code)`; reads of anything else fail.
+    values: Mutex<HashMap<(String, u8), u16>>,
+    /// Selectors every write to fails.
+    failing: Mutex<HashSet<String>>,
+    /// `(selector, code)` pairs every write to fails.
+    failing_codes: Mutex<HashSet<(String, u8)>>,
+    /// Selectors whose next writes fail, with how many are left to fail.
+    flaky: Mutex<HashMap<String, u32>>,
+    /// Pause at the start of every call.
+    delay: Mutex<Duration>,
+    /// Every write attempt, failed ones included, in order.
+    writes: Mutex<Vec<MockWrite>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockWrite {
+    pub selector: String,
+    pub code: u8,
+    pub value: u16,
+    pub at: Instant,
+    pub ok: bool,
+}
+
+impl MockBackend {
+    /// `count` external displays, indices `1..=count`, named `Mock 1`, `Mock 2`, ...
+    pub fn new(count: u32) -> Self {
+        Self::with_displays(
+            (1..=count)
+                .map(|i| display(i, &format!("Mock {i}")))
+                .collect(),
+        )
+    }
+
+    pub fn with_displays(displays: Vec<DisplayInfo>) -> Self {
+        let mock = Self::default();
+        *mock.state.displays.lock().unwrap() = displays;
+        mock
+    }
+
+    /// Makes `selector` read `value` for `code`.
+    pub fn set(&self, selector: &str, code: u8, value: u16) -> &Self {
+        self.state
+            .values
+            .lock()
+            .unwrap()
+            .insert((selector.to_string(), code), value);
+        self
+    }
+
+    pub fn value(&self, selector: &str, code: u8) -> Option<u16> {
+        let values = self.state.values.lock().unwrap();
+        values.get(&(selector.to_string(), code)).copied()
+    }
+
+    /// Makes every write to `selector` fail.
+    pub fn fail(&self, selector: &str) -> &Self {
+        self.state
+            .failing
+            .lock()
+            .unwrap()
+            .insert(selector.to_string());
+        self
+    }
+
+    /// Makes every write of `code` to `selector` fail; other codes still work.
+    pub fn fail_code(&self, selector: &str, code: u8) -> &Self {
+        self.state
+            .failing_codes
+            .lock()
+            .unwrap()
+            .insert((selector.to_string(), code));
+        self
+    }
+
+    /// Makes the next `times` writes to `selector` fail.
+    pub fn fail_times(&self, selector: &str, times: u32) -> &Self {
+        self.state
+            .flaky
+            .lock()
+            .unwrap()
+            .insert(selector.to_string(), times);
+        self
+    }
+
+    pub fn delay(&self, delay: Duration) -> &Self {
+        *self.state.delay.lock().unwrap() = delay;
+        self
+    }
+
+    pub fn write_log(&self) -> Vec<MockWrite> {
+        self.state.writes.lock().unwrap().clone()
+    }
+
+    /// Successful writes as `(selector, code, value)`.
+    pub fn writes(&self) -> Vec<(String, u8, u16)> {
+        self.write_log()
+            .into_iter()
+            .filter(|w| w.ok)
+            .map(|w| (w.selector, w.code, w.value))
+            .collect()
+    }
+
+    fn pause(&self) {
+        let delay = *self.state.delay.lock().unwrap();
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+
+    fn check_display(&self, selector: &str) -> Result<()> {
+        let known = self
+            .state
+            .displays
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|d| d.index.to_string() == selector);
+        if !known {
+            bail!("no display '{selector}'");
+        }
+        Ok(())
+    }
+
+    fn write(&self, selector: &str, code: u8, value: u16) -> Result<()> {
+        self.pause();
+        self.check_display(selector)?;
+        let failing_code = (selector.to_string(), code);
+        let failing = self.state.failing.lock().unwrap().contains(selector)
+            || self
+                .state
+                .failing_codes
+                .lock()
+                .unwrap()
+                .contains(&failing_code);
+        let flaky = match self.state.flaky.lock().unwrap().get_mut(selector) {
+            Some(left) if *left > 0 => {
+                *left -= 1;
+                true
+            }
+            _ => false,
+        };
+        let ok = !failing && !flaky;
+        self.state.writes.lock().unwrap().push(MockWrite {
+            selector: selector.to_string(),
+            code,
+            value,
+            at: Instant::now(),
+            ok,
+        });
+        if !ok {
+            bail!("mock write to '{selector}' failed");
+        }
+        self.set(selector, code, value);
+        Ok(())
+    }
+
+    fn read(&self, selector: &str, code: u8) -> Result<u16> {
+        self.pause();
+        self.check_display(selector)?;
+        self.value(selector, code)
+            .ok_or_else(|| anyhow!("display '{selector}' has no value for VCP 0x{code:02X}"))
+    }
+}
+
+/// An external display with just an index and a name.
+pub fn display(index: u32, name: &str) -> DisplayInfo {
+    DisplayInfo {
+        index,
+        product_name: Some(name.to_string()),
+        system_uuid: None,
+        serial: None,
+        resolution: None,
+        refresh_hz: None,
+        internal: false,
+        ddc_responsive: None,
+        connector: None,
+    }
+}
+
+impl Backend for MockBackend {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        self.pause();
+        Ok(DisplayListReport {
+            displays: self.state.displays.lock().unwrap().clone(),
+            raw: None,
+        })
+    }
+
+    fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        self.write(display_selector, VCP_INPUT, value)
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        self.read(display_selector, VCP_INPUT)
+    }
+
+    fn doctor(&self) -> Result<DoctorReport> {
+        Ok(DoctorReport {
+            status: DoctorStatus::Ok,
+            message: "mock".to_string(),
+            probes: Vec::new(),
+            tool_path: None,
+            get_input_supported: Some(true),
+        })
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        Some(self.get_input(display_selector).is_ok())
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        Ok((self.read(display_selector, code)?, 100))
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        self.write(display_selector, code, value)
+    }
+}
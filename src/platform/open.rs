@@ -0,0 +1,117 @@
+//! Opening a file or folder with whatever the OS associates with it. Shared by the tray's
+//! "Edit config" / "Open config folder" and `monitorctl config edit`.
+
+use std::{ffi::OsString, path::Path};
+
+use anyhow::Result;
+
+pub fn open_path(path: &Path) -> Result<()> {
+    open_path_with(path, std::env::consts::OS, run)
+}
+
+/// [`open_path`] as it would go on `os` (a `std::env::consts::OS` value), with `run` in place
+/// of actually launching anything.
+fn open_path_with(
+    path: &Path,
+    os: &str,
+    run: impl FnOnce(&[OsString]) -> Result<()>,
+) -> Result<()> {
+    run(&open_command(os, path))
+}
+
+/// What opening `path` runs on `os`: the launcher and its arguments. On Windows there is no
+/// launcher process; the first element is the `ShellExecuteW` verb instead.
+fn open_command(os: &str, path: &Path) -> Vec<OsString> {
+    let launcher = match os {
+        "windows" | "macos" => "open",
+        _ => "xdg-open",
+    };
+    vec![launcher.into(), path.into()]
+}
+
+#[cfg(target_os = "windows")]
+fn run(command: &[OsString]) -> Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use anyhow::anyhow;
+    use windows::{
+        core::PCWSTR,
+        Win32::UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_SHOWNORMAL},
+    };
+
+    let [verb, path] = command else {
+        return Err(anyhow!("expected a verb and a path, got {command:?}"));
+    };
+    let wide = |s: &OsString| s.encode_wide().chain([0]).collect::<Vec<u16>>();
+    let (wverb, wpath) = (wide(verb), wide(path));
+    unsafe {
+        let h = ShellExecuteW(
+            None,
+            PCWSTR::from_raw(wverb.as_ptr()),
+            PCWSTR::from_raw(wpath.as_ptr()),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        );
+        // Per Win32 docs: values <= 32 indicate an error.
+        if (h.0 as isize) <= 32 {
+            return Err(anyhow!("ShellExecuteW failed ({})", h.0 as isize));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn run(command: &[OsString]) -> Result<()> {
+    use anyhow::{anyhow, bail, Context};
+
+    let (launcher, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("empty launcher command"))?;
+    let launcher = launcher.to_string_lossy();
+    let status = std::process::Command::new(&*launcher)
+        .args(args)
+        .status()
+        .with_context(|| format!("running {launcher} {}", args[0].to_string_lossy()))?;
+    if !status.success() {
+        bail!("{launcher} failed (exit={status})");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opened_with(os: &str, path: &str) -> Vec<OsString> {
+        let mut seen = Vec::new();
+        open_path_with(Path::new(path), os, |command| {
+            seen = command.to_vec();
+            Ok(())
+        })
+        .unwrap();
+        seen
+    }
+
+    #[test]
+    fn each_platform_hands_the_path_to_its_launcher() {
+        let path = "/home/me/.config/monitorctl/config.json";
+        assert_eq!(opened_with("macos", path), ["open", path]);
+        assert_eq!(opened_with("linux", path), ["xdg-open", path]);
+        assert_eq!(opened_with("freebsd", path), ["xdg-open", path]);
+        let path = r"C:\Users\me\AppData\Roaming\monitorctl\config.json";
+        assert_eq!(opened_with("windows", path), ["open", path]);
+    }
+
+    #[test]
+    fn paths_with_spaces_stay_one_argument() {
+        let path = "/Users/me/Library/Application Support/monitorctl";
+        assert_eq!(opened_with("macos", path), ["open", path]);
+    }
+
+    #[test]
+    fn runner_errors_are_returned() {
+        let e = open_path_with(Path::new("/x"), "linux", |_| anyhow::bail!("no launcher"));
+        assert_eq!(e.unwrap_err().to_string(), "no launcher");
+    }
+}
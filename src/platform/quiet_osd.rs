@@ -0,0 +1,135 @@
+use anyhow::Result;
+
+use super::{Backend, DisplayListReport, DoctorReport};
+
+/// MCCS "OSD / Button Control". Its low byte is 1 for OSD disabled, 2 for enabled.
+const VCP_OSD: u8 = 0xCA;
+const OSD_DISABLED: u16 = 1;
+
+/// Wraps a backend so input switches happen with the monitor's OSD turned off, hiding the
+/// "No Signal" banner some monitors flash while the new source wakes up. The previous 0xCA
+/// value is written back afterwards, whether or not the switch worked. Monitors that can't
+/// read 0xCA are switched as usual.
+pub struct QuietOsd {
+    inner: Box<dyn Backend>,
+}
+
+impl QuietOsd {
+    pub fn new(inner: Box<dyn Backend>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Backend for QuietOsd {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays()
+    }
+
+    fn list_displays_quick(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays_quick()
+    }
+
+    fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        let Ok((previous, _)) = self.inner.get_vcp_raw(display_selector, VCP_OSD) else {
+            return self.inner.set_input(display_selector, value);
+        };
+        if previous == OSD_DISABLED
+            || self
+                .inner
+                .set_vcp_raw(display_selector, VCP_OSD, OSD_DISABLED)
+                .is_err()
+        {
+            return self.inner.set_input(display_selector, value);
+        }
+        let result = self.inner.set_input(display_selector, value);
+        let restored = self.inner.set_vcp_raw(display_selector, VCP_OSD, previous);
+        // The switch's own error matters more than a failed restore.
+        result?;
+        restored.map_err(|e| e.context("Input switched, but restoring the OSD setting failed"))
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        self.inner.get_input(display_selector)
+    }
+
+    fn forget_displays(&self) {
+        self.inner.forget_displays();
+    }
+
+    fn doctor(&self) -> Result<DoctorReport> {
+        self.inner.doctor()
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        self.inner.set_vcp_raw(display_selector, code, value)
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        self.inner.get_vcp_raw(display_selector, code)
+    }
+
+    fn get_vcp_many(&self, display_selector: &str, codes: &[u8]) -> Result<Vec<(u8, u16, u16)>> {
+        self.inner.get_vcp_many(display_selector, codes)
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        self.inner.probe_ddc(display_selector)
+    }
+
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        self.inner.capabilities(display_selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{mock::MockBackend, VCP_INPUT};
+
+    fn quiet(mock: &MockBackend) -> QuietOsd {
+        QuietOsd::new(Box::new(mock.clone()))
+    }
+
+    #[test]
+    fn the_osd_is_off_during_the_switch_and_restored_after() {
+        let mock = MockBackend::new(1);
+        mock.set("1", VCP_OSD, 2);
+        quiet(&mock).set_input("1", 17).unwrap();
+        assert_eq!(
+            mock.writes(),
+            [
+                ("1".to_string(), VCP_OSD, OSD_DISABLED),
+                ("1".to_string(), VCP_INPUT, 17),
+                ("1".to_string(), VCP_OSD, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_osd_is_restored_when_the_switch_fails() {
+        let mock = MockBackend::new(1);
+        mock.set("1", VCP_OSD, 2).fail_code("1", VCP_INPUT);
+        let e = quiet(&mock).set_input("1", 17).unwrap_err();
+        assert_eq!(e.to_string(), "mock write to '1' failed");
+        assert_eq!(mock.value("1", VCP_OSD), Some(2));
+        assert_eq!(
+            mock.write_log().last().map(|w| (w.code, w.ok)),
+            Some((VCP_OSD, true))
+        );
+    }
+
+    #[test]
+    fn monitors_without_osd_control_switch_as_usual() {
+        let mock = MockBackend::new(1);
+        quiet(&mock).set_input("1", 17).unwrap();
+        assert_eq!(mock.writes(), [("1".to_string(), VCP_INPUT, 17)]);
+    }
+
+    #[test]
+    fn an_already_hidden_osd_is_left_alone() {
+        let mock = MockBackend::new(1);
+        mock.set("1", VCP_OSD, OSD_DISABLED);
+        quiet(&mock).set_input("1", 17).unwrap();
+        assert_eq!(mock.writes(), [("1".to_string(), VCP_INPUT, 17)]);
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+
+use super::{Backend, DisplayListReport, DoctorReport, VCP_INPUT};
+
+/// Wraps a backend so every write fails before reaching the monitor (`--probe-safe`). Reads
+/// pass straight through.
+pub struct ReadOnly {
+    inner: Box<dyn Backend>,
+}
+
+impl ReadOnly {
+    pub fn new(inner: Box<dyn Backend>) -> Self {
+        Self { inner }
+    }
+}
+
+fn refuse(display_selector: &str, code: u8, value: u16) -> Result<()> {
+    bail!(
+        "--probe-safe forbids writes (refused VCP 0x{code:02X} = {value} on display '{display_selector}')"
+    )
+}
+
+impl Backend for ReadOnly {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays()
+    }
+
+    fn list_displays_quick(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays_quick()
+    }
+
+    fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        refuse(display_selector, VCP_INPUT, value)
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        self.inner.get_input(display_selector)
+    }
+
+    fn forget_displays(&self) {
+        self.inner.forget_displays();
+    }
+
+    fn doctor(&self) -> Result<DoctorReport> {
+        self.inner.doctor()
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        refuse(display_selector, code, value)
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        self.inner.get_vcp_raw(display_selector, code)
+    }
+
+    fn get_vcp_many(&self, display_selector: &str, codes: &[u8]) -> Result<Vec<(u8, u16, u16)>> {
+        self.inner.get_vcp_many(display_selector, codes)
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        self.inner.probe_ddc(display_selector)
+    }
+
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        self.inner.capabilities(display_selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{mock::MockBackend, VCP_BRIGHTNESS};
+
+    #[test]
+    fn writes_are_refused_before_reaching_the_backend() {
+        let mock = MockBackend::new(1);
+        let read_only = ReadOnly::new(Box::new(mock.clone()));
+        let e = read_only.set_input("1", 15).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "--probe-safe forbids writes (refused VCP 0x60 = 15 on display '1')"
+        );
+        assert!(read_only.set_vcp_raw("1", VCP_BRIGHTNESS, 50).is_err());
+        assert!(crate::platform::set_input_code(&read_only, "1", 0xDC, 5).is_err());
+        assert!(mock.write_log().is_empty());
+    }
+
+    #[test]
+    fn reads_pass_through() {
+        let mock = MockBackend::new(1);
+        mock.set("1", VCP_INPUT, 15).set("1", VCP_BRIGHTNESS, 40);
+        let read_only = ReadOnly::new(Box::new(mock));
+        assert_eq!(read_only.get_input("1").unwrap(), 15);
+        assert_eq!(
+            read_only.get_vcp_raw("1", VCP_BRIGHTNESS).unwrap(),
+            (40, 100)
+        );
+        assert_eq!(read_only.list_displays().unwrap().displays.len(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+use std::{thread, time::Duration};
+
+use anyhow::Result;
+
+use super::{Backend, DisplayListReport, DoctorReport};
+
+/// Wraps a backend so a failed write is tried again up to `retries` more times, waiting
+/// `delay` before the first retry and twice as long before each one after it. DDC/CI often
+/// drops the first command after a monitor wakes up. Reads aren't retried: they double as
+/// probes, and a backend that can't read at all would only get slower.
+pub struct Retry {
+    inner: Box<dyn Backend>,
+    retries: u32,
+    delay: Duration,
+}
+
+impl Retry {
+    pub fn new(inner: Box<dyn Backend>, retries: u32, delay: Duration) -> Self {
+        Self {
+            inner,
+            retries,
+            delay,
+        }
+    }
+
+    fn write(&self, f: impl Fn() -> Result<()>) -> Result<()> {
+        let mut delay = self.delay;
+        let mut attempt = 0;
+        loop {
+            match f() {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt == self.retries => {
+                    return Err(e.context(format!("failed after {} attempts", attempt + 1)));
+                }
+                Err(_) => {
+                    thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Backend for Retry {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays()
+    }
+
+    fn list_displays_quick(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays_quick()
+    }
+
+    fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        self.write(|| self.inner.set_input(display_selector, value))
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        self.inner.get_input(display_selector)
+    }
+
+    fn forget_displays(&self) {
+        self.inner.forget_displays();
+    }
+
+    fn doctor(&self) -> Result<DoctorReport> {
+        self.inner.doctor()
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        self.write(|| self.inner.set_vcp_raw(display_selector, code, value))
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        self.inner.get_vcp_raw(display_selector, code)
+    }
+
+    fn get_vcp_many(&self, display_selector: &str, codes: &[u8]) -> Result<Vec<(u8, u16, u16)>> {
+        self.inner.get_vcp_many(display_selector, codes)
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        self.inner.probe_ddc(display_selector)
+    }
+
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        self.inner.capabilities(display_selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::platform::{mock::MockBackend, VCP_INPUT};
+
+    fn retrying(mock: &MockBackend, retries: u32) -> Retry {
+        Retry::new(Box::new(mock.clone()), retries, Duration::from_millis(20))
+    }
+
+    #[test]
+    fn a_flaky_write_succeeds_after_growing_pauses() {
+        let mock = MockBackend::new(1);
+        mock.fail_times("1", 2);
+        retrying(&mock, 3).set_input("1", 17).unwrap();
+
+        let log = mock.write_log();
+        assert_eq!(
+            log.iter().map(|w| w.ok).collect::<Vec<_>>(),
+            [false, false, true]
+        );
+        let first = log[1].at - log[0].at;
+        let second = log[2].at - log[1].at;
+        assert!(first >= Duration::from_millis(20), "{first:?}");
+        assert!(second >= Duration::from_millis(40), "{second:?}");
+        assert_eq!(mock.value("1", VCP_INPUT), Some(17));
+    }
+
+    #[test]
+    fn the_last_error_is_returned_once_retries_run_out() {
+        let mock = MockBackend::new(1);
+        mock.fail("1");
+        let e = retrying(&mock, 2).set_vcp_raw("1", 0x10, 50).unwrap_err();
+        assert_eq!(
+            format!("{e:#}"),
+            "failed after 3 attempts: mock write to '1' failed"
+        );
+        assert_eq!(mock.write_log().len(), 3);
+    }
+
+    #[test]
+    fn successes_and_reads_are_not_retried() {
+        let mock = MockBackend::new(1);
+        let backend = retrying(&mock, 3);
+        backend.set_input("1", 17).unwrap();
+        assert_eq!(mock.write_log().len(), 1);
+
+        let started = Instant::now();
+        assert!(backend.get_vcp_raw("1", 0x10).is_err());
+        assert!(started.elapsed() < Duration::from_millis(20));
+    }
+
+    #[test]
+    fn zero_retries_fail_on_the_first_error() {
+        let mock = MockBackend::new(1);
+        mock.fail_times("1", 1);
+        assert!(retrying(&mock, 0).set_input("1", 17).is_err());
+        assert_eq!(mock.write_log().len(), 1);
+    }
+}
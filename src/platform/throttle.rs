@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use super::{Backend, DisplayListReport, DoctorReport};
+
+/// Wraps a backend so writes to the same display selector are at least `interval` apart.
+/// A write that comes too soon waits for its turn instead of being dropped.
+pub struct Throttled {
+    inner: Box<dyn Backend>,
+    interval: Duration,
+    /// End of the last write per selector. Each selector has its own lock, so waiting for one
+    /// display never holds up writes to another.
+    last_write: Mutex<HashMap<String, Arc<Mutex<Option<Instant>>>>>,
+}
+
+impl Throttled {
+    pub fn new(inner: Box<dyn Backend>, interval: Duration) -> Self {
+        Self {
+            inner,
+            interval,
+            last_write: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn write<T>(&self, display_selector: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let slot = Arc::clone(
+            self.last_write
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(display_selector.to_string())
+                .or_default(),
+        );
+        // Held across the wait and the write, so writes to this selector take turns.
+        let mut last_write = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(at) = *last_write {
+            if let Some(wait) = self.interval.checked_sub(at.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+        let result = f();
+        // Measured from the end of the write: the monitor needs the gap after it's done.
+        *last_write = Some(Instant::now());
+        result
+    }
+}
+
+impl Backend for Throttled {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays()
+    }
+
+    fn list_displays_quick(&self) -> Result<DisplayListReport> {
+        self.inner.list_displays_quick()
+    }
+
+    fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        self.write(display_selector, || {
+            self.inner.set_input(display_selector, value)
+        })
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        self.inner.get_input(display_selector)
+    }
+
+    fn forget_displays(&self) {
+        self.inner.forget_displays();
+    }
+
+    fn doctor(&self) -> Result<DoctorReport> {
+        self.inner.doctor()
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        self.write(display_selector, || {
+            self.inner.set_vcp_raw(display_selector, code, value)
+        })
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        self.inner.get_vcp_raw(display_selector, code)
+    }
+
+    fn get_vcp_many(&self, display_selector: &str, codes: &[u8]) -> Result<Vec<(u8, u16, u16)>> {
+        self.inner.get_vcp_many(display_selector, codes)
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        self.inner.probe_ddc(display_selector)
+    }
+
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        self.inner.capabilities(display_selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::mock::MockBackend;
+
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn writes_to_one_display_are_spaced_out() {
+        let mock = MockBackend::new(2);
+        let throttled = Throttled::new(Box::new(mock.clone()), INTERVAL);
+        throttled.set_input("1", 15).unwrap();
+        throttled.set_vcp_raw("1", 0x10, 50).unwrap();
+        throttled.set_input("1", 17).unwrap();
+
+        let log = mock.write_log();
+        assert_eq!(log.len(), 3);
+        for pair in log.windows(2) {
+            assert!(pair[1].at - pair[0].at >= INTERVAL);
+        }
+    }
+
+    #[test]
+    fn other_displays_and_reads_do_not_wait() {
+        let mock = MockBackend::new(2);
+        mock.set("1", 0x60, 15);
+        let throttled = Throttled::new(Box::new(mock.clone()), INTERVAL);
+        let started = Instant::now();
+        throttled.set_input("1", 15).unwrap();
+        throttled.get_input("1").unwrap();
+        throttled.set_input("2", 15).unwrap();
+        assert!(started.elapsed() < INTERVAL);
+    }
+
+    #[test]
+    fn failed_writes_count_too() {
+        let mock = MockBackend::new(1);
+        mock.fail_times("1", 1);
+        let throttled = Throttled::new(Box::new(mock.clone()), INTERVAL);
+        assert!(throttled.set_input("1", 15).is_err());
+        throttled.set_input("1", 15).unwrap();
+        let log = mock.write_log();
+        assert!(log[1].at - log[0].at >= INTERVAL);
+    }
+}
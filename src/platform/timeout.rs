@@ -0,0 +1,167 @@
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use super::{Backend, DisplayListReport, DoctorReport};
+
+/// Wraps a backend so no call takes longer than `timeout`. Each call runs on a worker thread;
+/// past the budget the caller gets an error and the worker is abandoned. An abandoned call
+/// can't be cancelled (a DDC call blocked in the driver, an `m1ddc` child still running), so
+/// it may still complete in the background, writes included.
+pub struct Timeout {
+    inner: Arc<dyn Backend>,
+    timeout: Duration,
+}
+
+impl Timeout {
+    pub fn new(inner: Box<dyn Backend>, timeout: Duration) -> Self {
+        Self {
+            inner: inner.into(),
+            timeout,
+        }
+    }
+
+    fn call<T: Send + 'static>(
+        &self,
+        what: &str,
+        f: impl FnOnce(&dyn Backend) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let inner = Arc::clone(&self.inner);
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("monitorctl-backend".to_string())
+            .spawn(move || {
+                // The receiver is gone once the caller gave up; nothing left to report to.
+                let _ = tx.send(f(&*inner));
+            })
+            .context("spawn backend worker")?;
+        match rx.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(anyhow!(
+                "{what} timed out after {} ms (operation_timeout_ms); the monitor may still apply it",
+                self.timeout.as_millis()
+            )),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(anyhow!("{what}: backend worker panicked")),
+        }
+    }
+}
+
+impl Backend for Timeout {
+    fn list_displays(&self) -> Result<DisplayListReport> {
+        self.call("listing displays", |b| b.list_displays())
+    }
+
+    fn list_displays_quick(&self) -> Result<DisplayListReport> {
+        self.call("listing displays", |b| b.list_displays_quick())
+    }
+
+    fn set_input(&self, display_selector: &str, value: u16) -> Result<()> {
+        let selector = display_selector.to_string();
+        self.call("setting the input", move |b| b.set_input(&selector, value))
+    }
+
+    fn get_input(&self, display_selector: &str) -> Result<u16> {
+        let selector = display_selector.to_string();
+        self.call("reading the input", move |b| b.get_input(&selector))
+    }
+
+    fn forget_displays(&self) {
+        // Only clears in-process state; no worker needed.
+        self.inner.forget_displays();
+    }
+
+    fn doctor(&self) -> Result<DoctorReport> {
+        self.call("doctor", |b| b.doctor())
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        let selector = display_selector.to_string();
+        self.call(&format!("writing VCP 0x{code:02X}"), move |b| {
+            b.set_vcp_raw(&selector, code, value)
+        })
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        let selector = display_selector.to_string();
+        self.call(&format!("reading VCP 0x{code:02X}"), move |b| {
+            b.get_vcp_raw(&selector, code)
+        })
+    }
+
+    fn get_vcp_many(&self, display_selector: &str, codes: &[u8]) -> Result<Vec<(u8, u16, u16)>> {
+        let selector = display_selector.to_string();
+        let codes = codes.to_vec();
+        self.call("reading VCP codes", move |b| {
+            b.get_vcp_many(&selector, &codes)
+        })
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        let selector = display_selector.to_string();
+        // A display too slow to answer in time counts as unknown, not unresponsive.
+        self.call("probing DDC/CI", move |b| Ok(b.probe_ddc(&selector)))
+            .ok()
+            .flatten()
+    }
+
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        let selector = display_selector.to_string();
+        self.call("reading capabilities", move |b| b.capabilities(&selector))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::platform::{mock::MockBackend, VCP_INPUT};
+
+    fn with_timeout(mock: &MockBackend, millis: u64) -> Timeout {
+        Timeout::new(Box::new(mock.clone()), Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn fast_calls_pass_through() {
+        let mock = MockBackend::new(2);
+        mock.set("1", VCP_INPUT, 15);
+        let backend = with_timeout(&mock, 1000);
+        assert_eq!(backend.get_input("1").unwrap(), 15);
+        backend.set_input("2", 17).unwrap();
+        assert_eq!(mock.value("2", VCP_INPUT), Some(17));
+        assert_eq!(backend.list_displays().unwrap().displays.len(), 2);
+        let e = backend.get_input("3").unwrap_err();
+        assert_eq!(e.to_string(), "no display '3'");
+    }
+
+    #[test]
+    fn slow_calls_give_up_but_still_finish_in_the_background() {
+        let mock = MockBackend::new(1);
+        mock.delay(Duration::from_millis(300));
+        let backend = with_timeout(&mock, 50);
+        let started = Instant::now();
+        let e = backend.set_input("1", 17).unwrap_err();
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert_eq!(
+            e.to_string(),
+            "setting the input timed out after 50 ms (operation_timeout_ms); the monitor may still apply it"
+        );
+        assert!(mock.writes().is_empty());
+
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(mock.writes(), [("1".to_string(), VCP_INPUT, 17)]);
+    }
+
+    #[test]
+    fn a_slow_probe_is_unknown() {
+        let mock = MockBackend::new(1);
+        mock.set("1", VCP_INPUT, 15);
+        assert_eq!(with_timeout(&mock, 1000).probe_ddc("1"), Some(true));
+        mock.delay(Duration::from_millis(300));
+        assert_eq!(with_timeout(&mock, 50).probe_ddc("1"), None);
+    }
+}
@@ -1,6 +1,11 @@
+#[cfg(target_os = "windows")]
+use std::sync::{Mutex, PoisonError};
+
 use anyhow::{anyhow, bail, Context, Result};
 
-use super::{DisplayInfo, DisplayListReport, DoctorReport};
+use super::{
+    looks_internal, BackendOptions, DisplayInfo, DisplayListReport, DoctorReport, DoctorStatus,
+};
 
 #[cfg(target_os = "windows")]
 mod win {
@@ -8,19 +13,42 @@ mod win {
         core::Error,
         Win32::{
             Devices::Display::{
-                DestroyPhysicalMonitors, GetNumberOfPhysicalMonitorsFromHMONITOR,
-                GetPhysicalMonitorsFromHMONITOR, GetVCPFeatureAndVCPFeatureReply, SetVCPFeature,
-                MC_VCP_CODE_TYPE,
+                CapabilitiesRequestAndCapabilitiesReply, DestroyPhysicalMonitors,
+                DisplayConfigGetDeviceInfo, GetCapabilitiesStringLength,
+                GetDisplayConfigBufferSizes, GetNumberOfPhysicalMonitorsFromHMONITOR,
+                GetPhysicalMonitorsFromHMONITOR, GetVCPFeatureAndVCPFeatureReply,
+                QueryDisplayConfig, SetVCPFeature, DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+                DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED,
+                DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EXTERNAL,
+                DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_USB_TUNNEL,
+                DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DVI, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HD15,
+                DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HDMI, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL,
+                DISPLAYCONFIG_OUTPUT_TECHNOLOGY_LVDS, DISPLAYCONFIG_PATH_INFO,
+                DISPLAYCONFIG_SOURCE_DEVICE_NAME, DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
+                MC_VCP_CODE_TYPE, QDC_ONLY_ACTIVE_PATHS,
+            },
+            Foundation::{ERROR_SUCCESS, LPARAM, RECT},
+            Graphics::Gdi::{
+                EnumDisplayDevicesW, EnumDisplayMonitors, EnumDisplaySettingsW, GetMonitorInfoW,
+                DEVMODEW, DISPLAY_DEVICEW, ENUM_CURRENT_SETTINGS, HDC, HMONITOR, MONITORINFO,
+                MONITORINFOEXW,
             },
-            Foundation::{LPARAM, RECT},
-            Graphics::Gdi::{EnumDisplayMonitors, HDC, HMONITOR},
+            UI::WindowsAndMessaging::{GetSystemMetrics, SM_CMONITORS, SM_REMOTESESSION},
         },
     };
 
     pub use windows::Win32::Devices::Display::PHYSICAL_MONITOR;
 
     pub unsafe fn enum_physical_monitors() -> windows::core::Result<Vec<PHYSICAL_MONITOR>> {
-        let mut all: Vec<PHYSICAL_MONITOR> = Vec::new();
+        let all = unsafe { enum_physical_monitors_with_owner()? };
+        Ok(all.into_iter().map(|(_hmonitor, mon)| mon).collect())
+    }
+
+    /// Like `enum_physical_monitors`, but keeps the `HMONITOR` each physical monitor belongs to
+    /// so OS-level display info (resolution, refresh) can be correlated.
+    pub unsafe fn enum_physical_monitors_with_owner(
+    ) -> windows::core::Result<Vec<(HMONITOR, PHYSICAL_MONITOR)>> {
+        let mut all: Vec<(HMONITOR, PHYSICAL_MONITOR)> = Vec::new();
 
         unsafe extern "system" fn cb(
             hmonitor: HMONITOR,
@@ -28,7 +56,7 @@ mod win {
             _rc: *mut RECT,
             lparam: LPARAM,
         ) -> windows::core::BOOL {
-            let vec_ptr = lparam.0 as *mut Vec<PHYSICAL_MONITOR>;
+            let vec_ptr = lparam.0 as *mut Vec<(HMONITOR, PHYSICAL_MONITOR)>;
             let vec = unsafe { &mut *vec_ptr };
 
             let mut count: u32 = 0;
@@ -41,13 +69,62 @@ mod win {
 
             let mut monitors = vec![PHYSICAL_MONITOR::default(); count as usize];
             if unsafe { GetPhysicalMonitorsFromHMONITOR(hmonitor, &mut monitors) }.is_ok() {
-                vec.extend(monitors);
+                vec.extend(monitors.into_iter().map(|m| (hmonitor, m)));
+            }
+
+            windows::core::BOOL(1)
+        }
+
+        let vec_ptr = &mut all as *mut Vec<(HMONITOR, PHYSICAL_MONITOR)>;
+        let ok = unsafe { EnumDisplayMonitors(None, None, Some(cb), LPARAM(vec_ptr as isize)) };
+        if !ok.as_bool() {
+            return Err(Error::from_thread());
+        }
+        Ok(all)
+    }
+
+    /// Monitor names for every physical monitor, in the same order as
+    /// `enum_physical_monitors_with_owner`, read from `EnumDisplayDevicesW` without opening any
+    /// physical-monitor (DDC) handles.
+    pub unsafe fn enum_monitor_names() -> windows::core::Result<Vec<(HMONITOR, String)>> {
+        let mut all: Vec<(HMONITOR, String)> = Vec::new();
+
+        unsafe extern "system" fn cb(
+            hmonitor: HMONITOR,
+            _hdc: HDC,
+            _rc: *mut RECT,
+            lparam: LPARAM,
+        ) -> windows::core::BOOL {
+            let vec_ptr = lparam.0 as *mut Vec<(HMONITOR, String)>;
+            let vec = unsafe { &mut *vec_ptr };
+
+            // Counting doesn't open handles, and keeps indices aligned with the DDC path.
+            let mut count: u32 = 0;
+            if unsafe { GetNumberOfPhysicalMonitorsFromHMONITOR(hmonitor, &mut count) }.is_err() {
+                return windows::core::BOOL(1);
+            }
+
+            let device = device_name(hmonitor);
+            for i in 0..count {
+                let name = device
+                    .as_ref()
+                    .and_then(|device| {
+                        let mut dd = DISPLAY_DEVICEW {
+                            cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32,
+                            ..Default::default()
+                        };
+                        let device = windows::core::PCWSTR::from_raw(device.as_ptr());
+                        let ok = unsafe { EnumDisplayDevicesW(device, i, &mut dd, 0) };
+                        ok.as_bool().then(|| wide_to_string(&dd.DeviceString))
+                    })
+                    .unwrap_or_default();
+                vec.push((hmonitor, name));
             }
 
             windows::core::BOOL(1)
         }
 
-        let vec_ptr = &mut all as *mut Vec<PHYSICAL_MONITOR>;
+        let vec_ptr = &mut all as *mut Vec<(HMONITOR, String)>;
         let ok = unsafe { EnumDisplayMonitors(None, None, Some(cb), LPARAM(vec_ptr as isize)) };
         if !ok.as_bool() {
             return Err(Error::from_thread());
@@ -55,6 +132,121 @@ mod win {
         Ok(all)
     }
 
+    /// GDI device name (`\\.\DISPLAY1`) of `hmonitor`, NUL-terminated.
+    fn device_name(hmonitor: HMONITOR) -> Option<[u16; 32]> {
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        let ok = unsafe {
+            GetMonitorInfoW(
+                hmonitor,
+                &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+            )
+        };
+        ok.as_bool().then_some(info.szDevice)
+    }
+
+    /// Connector labels ("HDMI-1", "DP-2") of the active display paths, keyed by GDI device
+    /// name. Numbers count paths of the same type in the order Windows reports them: stable
+    /// while the wiring stays the same, but not the GPU's own port numbering.
+    pub fn connectors() -> Vec<(String, String)> {
+        let flags = QDC_ONLY_ACTIVE_PATHS;
+        let (mut num_paths, mut num_modes) = (0u32, 0u32);
+        if unsafe { GetDisplayConfigBufferSizes(flags, &mut num_paths, &mut num_modes) }
+            != ERROR_SUCCESS
+        {
+            return Vec::new();
+        }
+        let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); num_paths as usize];
+        let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); num_modes as usize];
+        let status = unsafe {
+            QueryDisplayConfig(
+                flags,
+                &mut num_paths,
+                paths.as_mut_ptr(),
+                &mut num_modes,
+                modes.as_mut_ptr(),
+                None,
+            )
+        };
+        if status != ERROR_SUCCESS {
+            return Vec::new();
+        }
+        paths.truncate(num_paths as usize);
+
+        let mut counts: Vec<(&str, u32)> = Vec::new();
+        paths
+            .iter()
+            .filter_map(|path| {
+                let kind = connector_kind(path.targetInfo.outputTechnology)?;
+                let n = match counts.iter_mut().find(|(k, _)| *k == kind) {
+                    Some((_, n)) => {
+                        *n += 1;
+                        *n
+                    }
+                    None => {
+                        counts.push((kind, 1));
+                        1
+                    }
+                };
+
+                let mut source = DISPLAYCONFIG_SOURCE_DEVICE_NAME::default();
+                source.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME;
+                source.header.size = std::mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32;
+                source.header.adapterId = path.sourceInfo.adapterId;
+                source.header.id = path.sourceInfo.id;
+                if unsafe { DisplayConfigGetDeviceInfo(&mut source.header) } != 0 {
+                    return None;
+                }
+                Some((
+                    wide_to_string(&source.viewGdiDeviceName),
+                    format!("{kind}-{n}"),
+                ))
+            })
+            .collect()
+    }
+
+    fn connector_kind(technology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY) -> Option<&'static str> {
+        Some(match technology {
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HDMI => "HDMI",
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EXTERNAL
+            | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_USB_TUNNEL => "DP",
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DVI => "DVI",
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HD15 => "VGA",
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL
+            | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED
+            | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_LVDS => "Internal",
+            _ => return None,
+        })
+    }
+
+    /// Label from [`connectors`] for the display device behind `hmonitor`.
+    pub fn connector(hmonitor: HMONITOR, connectors: &[(String, String)]) -> Option<String> {
+        let device = wide_to_string(&device_name(hmonitor)?);
+        connectors
+            .iter()
+            .find(|(name, _)| *name == device)
+            .map(|(_, label)| label.clone())
+    }
+
+    /// Current resolution and refresh rate of the display device behind `hmonitor`.
+    pub fn display_mode(hmonitor: HMONITOR) -> Option<((u32, u32), Option<u32>)> {
+        let device_name = device_name(hmonitor)?;
+
+        let mut mode = DEVMODEW {
+            dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+            ..Default::default()
+        };
+        let device = windows::core::PCWSTR::from_raw(device_name.as_ptr());
+        let ok = unsafe { EnumDisplaySettingsW(device, ENUM_CURRENT_SETTINGS, &mut mode) };
+        if !ok.as_bool() {
+            return None;
+        }
+
+        // 0 and 1 mean "hardware default" rather than a real rate.
+        let refresh = (mode.dmDisplayFrequency > 1).then_some(mode.dmDisplayFrequency);
+        Some(((mode.dmPelsWidth, mode.dmPelsHeight), refresh))
+    }
+
     pub unsafe fn destroy(monitors: &mut [PHYSICAL_MONITOR]) {
         let _ = unsafe { DestroyPhysicalMonitors(monitors) };
     }
@@ -91,6 +283,30 @@ mod win {
         Ok((current, maximum))
     }
 
+    /// The monitor's MCCS capabilities string. Slow: the monitor answers over DDC/CI in chunks.
+    pub fn get_capabilities(mon: &PHYSICAL_MONITOR) -> windows::core::Result<String> {
+        let mut len: u32 = 0;
+        if unsafe { GetCapabilitiesStringLength(mon.hPhysicalMonitor, &mut len) } == 0 {
+            return Err(Error::from_thread());
+        }
+        let mut buf = vec![0u8; len as usize];
+        if unsafe { CapabilitiesRequestAndCapabilitiesReply(mon.hPhysicalMonitor, &mut buf) } == 0 {
+            return Err(Error::from_thread());
+        }
+        let nul = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+        Ok(String::from_utf8_lossy(&buf[..nul]).into_owned())
+    }
+
+    /// Running inside a Remote Desktop session, whose displays are virtual.
+    pub fn is_remote_session() -> bool {
+        unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+    }
+
+    /// Display monitors Windows knows about, physical or not.
+    pub fn display_monitor_count() -> i32 {
+        unsafe { GetSystemMetrics(SM_CMONITORS) }
+    }
+
     pub fn monitor_desc(mon: &PHYSICAL_MONITOR) -> String {
         // szPhysicalMonitorDescription is [u16; 128] on a packed struct.
         let desc: [u16; 128] = unsafe {
@@ -100,11 +316,93 @@ mod win {
     }
 }
 
-pub struct WindowsDxva2Backend;
+pub struct WindowsDxva2Backend {
+    wake_before_write: bool,
+    read_delay: std::time::Duration,
+    /// Physical monitors kept between calls when `cache_monitors` is set.
+    #[cfg(target_os = "windows")]
+    cache: Option<Mutex<Option<MonitorList>>>,
+}
 
 impl WindowsDxva2Backend {
-    pub fn new() -> Self {
-        Self
+    pub fn new(options: BackendOptions) -> Self {
+        Self {
+            wake_before_write: options.wake_before_write,
+            read_delay: options.read_delay,
+            #[cfg(target_os = "windows")]
+            cache: options.cache_monitors.then(|| Mutex::new(None)),
+        }
+    }
+
+    /// Runs `f` against the physical monitors: a fresh enumeration, or the cached one with
+    /// `cache_monitors`. A failure on cached handles (usually stale after a hotplug) drops the
+    /// cache; with `retry` (writes) the monitors are then enumerated again for a second try.
+    #[cfg(target_os = "windows")]
+    fn with_monitors<T>(
+        &self,
+        retry: bool,
+        f: impl Fn(&[win::PHYSICAL_MONITOR]) -> Result<T>,
+    ) -> Result<T> {
+        let Some(cache) = &self.cache else {
+            return f(&MonitorList::new()?);
+        };
+
+        let mut cached = cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let was_cached = cached.is_some();
+        let result = f(cached_or_enumerate(&mut cached)?);
+        if result.is_ok() {
+            return result;
+        }
+        // Dropping the list destroys its handles.
+        *cached = None;
+        if !(retry && was_cached) {
+            return result;
+        }
+        f(cached_or_enumerate(&mut cached)?)
+    }
+
+    /// `win::get_vcp`, after the configured `ddc_read_delay_ms`.
+    #[cfg(target_os = "windows")]
+    fn read_vcp(&self, mon: &win::PHYSICAL_MONITOR, code: u8) -> windows::core::Result<(u32, u32)> {
+        self.after_read_delay(|| win::get_vcp(mon, code))
+    }
+
+    fn after_read_delay<T>(&self, read: impl FnOnce() -> T) -> T {
+        if !self.read_delay.is_zero() {
+            std::thread::sleep(self.read_delay);
+        }
+        read()
+    }
+}
+
+#[cfg(target_os = "windows")]
+const REMOTE_SESSION: &str = "DDC/CI is unavailable over Remote Desktop: the session only sees \
+     virtual displays. Run monitorctl in the machine's local session instead.";
+
+/// Why Dxva2 found no physical monitors, in terms the user can act on.
+#[cfg(target_os = "windows")]
+fn no_physical_monitors() -> anyhow::Error {
+    if win::is_remote_session() {
+        return anyhow!(REMOTE_SESSION);
+    }
+    match win::display_monitor_count() {
+        0 => anyhow!("No physical monitors found via Dxva2."),
+        n => anyhow!(
+            "Windows reports {n} display(s), but none is a physical monitor Dxva2 can reach. \
+             Virtual display drivers (remote access, streaming or dummy-display software) don't \
+             support DDC/CI."
+        ),
+    }
+}
+
+/// Enumeration errors are opaque Win32 codes; under Remote Desktop the cause is known.
+#[cfg(target_os = "windows")]
+fn enumeration_failed(e: windows::core::Error) -> anyhow::Error {
+    let e = anyhow::Error::from(e).context("enumerating physical monitors");
+    if win::is_remote_session() {
+        e.context(REMOTE_SESSION)
+    } else {
+        e
     }
 }
 
@@ -113,17 +411,29 @@ struct MonitorList {
     mons: Vec<win::PHYSICAL_MONITOR>,
 }
 
+// SAFETY: physical monitor handles aren't tied to the thread that opened them, and the cache
+// hands them out behind a mutex only.
+#[cfg(target_os = "windows")]
+unsafe impl Send for MonitorList {}
+
 #[cfg(target_os = "windows")]
 impl MonitorList {
+    /// Enumerates the physical monitors; finding none is an error.
     fn new() -> Result<Self> {
-        let mons =
-            unsafe { win::enum_physical_monitors().context("enumerating physical monitors")? };
+        let mons = unsafe { win::enum_physical_monitors().map_err(enumeration_failed)? };
+        if mons.is_empty() {
+            return Err(no_physical_monitors());
+        }
         Ok(Self { mons })
     }
+}
 
-    fn is_empty(&self) -> bool {
-        self.mons.is_empty()
+#[cfg(target_os = "windows")]
+fn cached_or_enumerate(slot: &mut Option<MonitorList>) -> Result<&MonitorList> {
+    if slot.is_none() {
+        *slot = Some(MonitorList::new()?);
     }
+    Ok(slot.as_ref().expect("just filled"))
 }
 
 #[cfg(target_os = "windows")]
@@ -144,6 +454,40 @@ impl Drop for MonitorList {
     }
 }
 
+#[cfg(target_os = "windows")]
+fn display_info(
+    i: usize,
+    hmonitor: windows::Win32::Graphics::Gdi::HMONITOR,
+    name: String,
+    connectors: &[(String, String)],
+) -> DisplayInfo {
+    let mode = win::display_mode(hmonitor);
+    DisplayInfo {
+        connector: win::connector(hmonitor, connectors),
+        index: (i + 1) as u32,
+        internal: looks_internal(Some(&name)),
+        product_name: Some(name),
+        system_uuid: None,
+        serial: None,
+        resolution: mode.map(|(res, _hz)| res),
+        refresh_hz: mode.and_then(|(_res, hz)| hz),
+        ddc_responsive: None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn list_report(displays: Vec<DisplayInfo>) -> DisplayListReport {
+    let raw = displays
+        .iter()
+        .map(|d| format!("[{}] {}", d.index, d.product_name.as_deref().unwrap_or("")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    DisplayListReport {
+        displays,
+        raw: Some(raw),
+    }
+}
+
 impl super::Backend for WindowsDxva2Backend {
     fn list_displays(&self) -> Result<DisplayListReport> {
         #[cfg(not(target_os = "windows"))]
@@ -153,34 +497,46 @@ impl super::Backend for WindowsDxva2Backend {
 
         #[cfg(target_os = "windows")]
         unsafe {
-            let mut mons =
-                win::enum_physical_monitors().context("enumerating physical monitors")?;
-            if mons.is_empty() {
-                return Err(anyhow!("No physical monitors found via Dxva2."));
+            let owned = win::enum_physical_monitors_with_owner().map_err(enumeration_failed)?;
+            if owned.is_empty() {
+                return Err(no_physical_monitors());
             }
 
-            let displays = mons
+            let connectors = win::connectors();
+            let displays = owned
                 .iter()
                 .enumerate()
-                .map(|(i, m)| DisplayInfo {
-                    index: (i + 1) as u32,
-                    product_name: Some(win::monitor_desc(m)),
-                    system_uuid: None,
+                .map(|(i, (hmonitor, m))| {
+                    display_info(i, *hmonitor, win::monitor_desc(m), &connectors)
                 })
                 .collect::<Vec<_>>();
+            let mut mons = owned.into_iter().map(|(_h, m)| m).collect::<Vec<_>>();
+            win::destroy(&mut mons);
 
-            let raw = displays
-                .iter()
-                .map(|d| format!("[{}] {}", d.index, d.product_name.as_deref().unwrap_or("")))
-                .collect::<Vec<_>>()
-                .join("\n");
+            Ok(list_report(displays))
+        }
+    }
 
-            win::destroy(&mut mons);
+    fn list_displays_quick(&self) -> Result<DisplayListReport> {
+        #[cfg(not(target_os = "windows"))]
+        {
+            bail!("Windows backend can only run on Windows.");
+        }
 
-            Ok(DisplayListReport {
-                displays,
-                raw: Some(raw),
-            })
+        #[cfg(target_os = "windows")]
+        unsafe {
+            let named = win::enum_monitor_names().context("enumerating monitors")?;
+            if named.is_empty() {
+                return Err(anyhow!("No physical monitors found."));
+            }
+
+            let connectors = win::connectors();
+            let displays = named
+                .into_iter()
+                .enumerate()
+                .map(|(i, (hmonitor, name))| display_info(i, hmonitor, name, &connectors))
+                .collect::<Vec<_>>();
+            Ok(list_report(displays))
         }
     }
 
@@ -192,17 +548,16 @@ impl super::Backend for WindowsDxva2Backend {
         }
 
         #[cfg(target_os = "windows")]
-        {
-            let mons = MonitorList::new()?;
-            if mons.is_empty() {
-                bail!("No physical monitors found via Dxva2.");
+        self.with_monitors(true, |mons| {
+            let mon = &mons[resolve_selector(display_selector, mons)?];
+            if self.wake_before_write {
+                // The read may fail on a channel that's still asleep; that's the point.
+                let _ = win::get_vcp(mon, 0x60);
+                std::thread::sleep(super::WAKE_DELAY);
             }
-
-            let idx = resolve_selector(display_selector, &mons)?;
-            let mon = &mons[idx];
             win::set_vcp(mon, 0x60, value as u32).context("SetVCPFeature(VCP=0x60)")?;
             Ok(())
-        }
+        })
     }
 
     fn get_input(&self, display_selector: &str) -> Result<u16> {
@@ -213,18 +568,90 @@ impl super::Backend for WindowsDxva2Backend {
         }
 
         #[cfg(target_os = "windows")]
+        self.with_monitors(false, |mons| {
+            let mon = &mons[resolve_selector(display_selector, mons)?];
+            let (cur, _max) = self
+                .read_vcp(mon, 0x60)
+                .context("GetVCPFeatureAndVCPFeatureReply(VCP=0x60)")?;
+
+            Ok(u16::try_from(cur).unwrap_or(u16::MAX))
+        })
+    }
+
+    fn probe_ddc(&self, display_selector: &str) -> Option<bool> {
+        // Reading the input is the cheapest VCP round trip, and the one a switch relies on.
+        Some(self.get_input(display_selector).is_ok())
+    }
+
+    fn set_vcp_raw(&self, display_selector: &str, code: u8, value: u16) -> Result<()> {
+        #[cfg(not(target_os = "windows"))]
+        {
+            let _ = (display_selector, code, value);
+            bail!("Windows backend can only run on Windows.");
+        }
+
+        #[cfg(target_os = "windows")]
+        self.with_monitors(true, |mons| {
+            let idx = resolve_selector(display_selector, mons)?;
+            win::set_vcp(&mons[idx], code, value as u32)
+                .with_context(|| format!("SetVCPFeature(VCP=0x{code:02X})"))?;
+            Ok(())
+        })
+    }
+
+    fn capabilities(&self, display_selector: &str) -> Result<String> {
+        #[cfg(not(target_os = "windows"))]
+        {
+            let _ = display_selector;
+            bail!("Windows backend can only run on Windows.");
+        }
+
+        #[cfg(target_os = "windows")]
+        self.with_monitors(false, |mons| {
+            let idx = resolve_selector(display_selector, mons)?;
+            win::get_capabilities(&mons[idx]).context("CapabilitiesRequestAndCapabilitiesReply")
+        })
+    }
+
+    fn get_vcp_raw(&self, display_selector: &str, code: u8) -> Result<(u16, u16)> {
+        let (_code, current, maximum) = self
+            .get_vcp_many(display_selector, &[code])?
+            .pop()
+            .ok_or_else(|| anyhow!("no VCP reply"))?;
+        Ok((current, maximum))
+    }
+
+    fn get_vcp_many(&self, display_selector: &str, codes: &[u8]) -> Result<Vec<(u8, u16, u16)>> {
+        #[cfg(not(target_os = "windows"))]
         {
-            let mons = MonitorList::new()?;
-            if mons.is_empty() {
-                bail!("No physical monitors found via Dxva2.");
-            }
+            let _ = (display_selector, codes);
+            bail!("Windows backend can only run on Windows.");
+        }
 
-            let idx = resolve_selector(display_selector, &mons)?;
-            let mon = &mons[idx];
-            let (cur, _max) =
-                win::get_vcp(mon, 0x60).context("GetVCPFeatureAndVCPFeatureReply(VCP=0x60)")?;
+        // Read every code against the same live handle.
+        #[cfg(target_os = "windows")]
+        self.with_monitors(false, |mons| {
+            let mon = &mons[resolve_selector(display_selector, mons)?];
+            codes
+                .iter()
+                .map(|&code| {
+                    let (cur, max) = self.read_vcp(mon, code).with_context(|| {
+                        format!("GetVCPFeatureAndVCPFeatureReply(VCP=0x{code:02X})")
+                    })?;
+                    Ok((
+                        code,
+                        u16::try_from(cur).unwrap_or(u16::MAX),
+                        u16::try_from(max).unwrap_or(u16::MAX),
+                    ))
+                })
+                .collect()
+        })
+    }
 
-            Ok(u16::try_from(cur).unwrap_or(u16::MAX))
+    fn forget_displays(&self) {
+        #[cfg(target_os = "windows")]
+        if let Some(cache) = &self.cache {
+            *cache.lock().unwrap_or_else(PoisonError::into_inner) = None;
         }
     }
 
@@ -232,8 +659,11 @@ impl super::Backend for WindowsDxva2Backend {
         #[cfg(not(target_os = "windows"))]
         {
             return Ok(DoctorReport {
-                ok: false,
+                status: DoctorStatus::EnumerationFailed,
                 message: "Windows backend can only run on Windows.".to_string(),
+                probes: Vec::new(),
+                tool_path: None,
+                get_input_supported: None,
             });
         }
 
@@ -242,31 +672,61 @@ impl super::Backend for WindowsDxva2Backend {
             let mut mons = match win::enum_physical_monitors() {
                 Ok(m) => m,
                 Err(e) => {
+                    let mut message = format!("Failed to enumerate monitors: {e}");
+                    if win::is_remote_session() {
+                        message = format!("{message}\n\n{REMOTE_SESSION}");
+                    }
                     return Ok(DoctorReport {
-                        ok: false,
-                        message: format!("Failed to enumerate monitors: {e}"),
-                    })
+                        status: DoctorStatus::EnumerationFailed,
+                        message,
+                        probes: Vec::new(),
+                        tool_path: None,
+                        get_input_supported: None,
+                    });
                 }
             };
 
             if mons.is_empty() {
                 return Ok(DoctorReport {
-                    ok: false,
-                    message: "No physical monitors found via Dxva2.".to_string(),
+                    status: DoctorStatus::NoDisplays,
+                    message: no_physical_monitors().to_string(),
+                    probes: Vec::new(),
+                    tool_path: None,
+                    get_input_supported: None,
                 });
             }
 
+            let responsive = mons
+                .iter()
+                .map(|m| self.read_vcp(m, 0x60).is_ok())
+                .collect::<Vec<_>>();
             let list = mons
                 .iter()
+                .zip(&responsive)
                 .enumerate()
-                .map(|(i, m)| format!("[{}] {}", i + 1, win::monitor_desc(m)))
+                .map(|(i, (m, &responsive))| {
+                    format!(
+                        "[{}] {}{}",
+                        i + 1,
+                        win::monitor_desc(m),
+                        if responsive {
+                            ""
+                        } else {
+                            " (no DDC/CI response: reading VCP 0x60 failed)"
+                        }
+                    )
+                })
                 .collect::<Vec<_>>()
                 .join("\n");
             win::destroy(&mut mons);
 
             Ok(DoctorReport {
-                ok: true,
+                status: DoctorStatus::Ok,
                 message: format!("Dxva2: OK\n\nDetected monitors:\n{list}"),
+                probes: Vec::new(),
+                tool_path: None,
+                // Same question Linux and macOS answer: did the first display's input read work.
+                get_input_supported: responsive.first().copied(),
             })
         }
     }
@@ -340,3 +800,31 @@ fn format_monitor_list(mons: &[win::PHYSICAL_MONITOR]) -> String {
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn backend(config: &str) -> WindowsDxva2Backend {
+        let config = crate::config::parse(config.as_bytes(), "test").unwrap();
+        WindowsDxva2Backend::new(crate::config::backend_options(Some(&config)))
+    }
+
+    #[test]
+    fn reads_wait_for_the_configured_delay() {
+        let backend = backend(r#"{ "ddc_read_delay_ms": 50 }"#);
+        let started = Instant::now();
+        assert_eq!(backend.after_read_delay(|| 15), 15);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn reads_are_immediate_by_default() {
+        let backend = backend("{}");
+        assert!(backend.read_delay.is_zero());
+        let started = Instant::now();
+        backend.after_read_delay(|| ());
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+}
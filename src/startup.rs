@@ -0,0 +1,95 @@
+//! "Start at login" for `monitortray`: a LaunchAgent on macOS, the `Run` registry key on
+//! Windows. Shared by the tray's menu toggle and `monitorctl autostart`.
+
+use std::{env, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+/// Argument the autostart entry passes to `monitortray`, so it can tell a login launch from a
+/// manual one.
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+/// Whether this process was started by the autostart entry.
+pub fn launched_at_login() -> bool {
+    has_autostart_arg(env::args().skip(1))
+}
+
+fn has_autostart_arg(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == AUTOSTART_ARG)
+}
+
+pub fn is_enabled() -> Result<bool> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::is_enabled().context("read launch agent")
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        windows::is_enabled().context("read registry startup entry")
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        bail!("Start at login is only supported on macOS and Windows.");
+    }
+}
+
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::set_enabled(enabled).context("update launch agent")
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        windows::set_enabled(enabled).context("update registry startup entry")
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let _ = enabled;
+        bail!("Start at login is only supported on macOS and Windows.");
+    }
+}
+
+/// The `monitortray` binary to register: the running executable when called from the tray,
+/// otherwise the one installed next to `monitorctl`.
+pub fn tray_exe() -> Result<PathBuf> {
+    let exe = env::current_exe().context("current_exe")?;
+    let name = format!("monitortray{}", env::consts::EXE_SUFFIX);
+    if exe.file_name().is_some_and(|n| *n == *name) {
+        return Ok(exe);
+    }
+
+    let sibling = exe.with_file_name(&name);
+    if !sibling.exists() {
+        bail!("{name} not found next to {}", exe.display());
+    }
+    Ok(sibling)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn the_autostart_marker_is_recognized() {
+        assert!(has_autostart_arg(args(&["--autostart"])));
+        assert!(has_autostart_arg(args(&["tray", "--autostart"])));
+        assert!(!has_autostart_arg(args(&[])));
+        assert!(!has_autostart_arg(args(&["tray", "--autostart=1"])));
+    }
+}
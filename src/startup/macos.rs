@@ -0,0 +1,125 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command as ProcessCommand,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+const LABEL: &str = "com.monitorctl.monitorctl";
+
+pub fn is_enabled() -> Result<bool> {
+    Ok(plist_path()?.exists())
+}
+
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    if enabled {
+        install()?;
+    } else {
+        uninstall()?;
+    }
+    Ok(())
+}
+
+fn install() -> Result<()> {
+    let path = plist_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+
+    let exe = super::tray_exe()?;
+    let plist = launch_agent_plist(&exe, LABEL);
+    fs::write(&path, plist.as_bytes()).with_context(|| format!("write {}", path.display()))?;
+
+    let uid = gui_uid();
+    let domain = format!("gui/{uid}");
+
+    // Best-effort: unload any previous instance, then load.
+    let _ = launchctl(&["bootout", &domain, &path.to_string_lossy()]);
+    launchctl(&["bootstrap", &domain, &path.to_string_lossy()]).context("launchctl bootstrap")?;
+    let _ = launchctl(&["enable", &format!("{domain}/{LABEL}")]);
+
+    Ok(())
+}
+
+fn uninstall() -> Result<()> {
+    let path = plist_path()?;
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let uid = gui_uid();
+    let domain = format!("gui/{uid}");
+    let _ = launchctl(&["disable", &format!("{domain}/{LABEL}")]);
+    let _ = launchctl(&["bootout", &domain, &path.to_string_lossy()]);
+
+    fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+    Ok(())
+}
+
+fn plist_path() -> Result<PathBuf> {
+    let home = env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+    Ok(PathBuf::from(home)
+        .join("Library")
+        .join("LaunchAgents")
+        .join(format!("{LABEL}.plist")))
+}
+
+fn gui_uid() -> u32 {
+    // LaunchAgents don't always inherit a useful env, so shell out to `id -u`.
+    ProcessCommand::new("id")
+        .arg("-u")
+        .output()
+        .ok()
+        .and_then(|out| {
+            if !out.status.success() {
+                return None;
+            }
+            String::from_utf8(out.stdout)
+                .ok()
+                .and_then(|s| s.trim().parse::<u32>().ok())
+        })
+        .unwrap_or(0)
+}
+
+fn launch_agent_plist(exe: &Path, label: &str) -> String {
+    let exe = exe.display().to_string();
+    let autostart = super::AUTOSTART_ARG;
+    format!(
+        r#"<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+  <key>Label</key><string>{label}</string>
+  <key>ProgramArguments</key>
+  <array>
+<string>{exe}</string>
+<string>{autostart}</string>
+  </array>
+  <key>RunAtLoad</key><true/>
+  <key>ProcessType</key><string>Interactive</string>
+  <key>StandardOutPath</key><string>/tmp/monitortray.out</string>
+  <key>StandardErrorPath</key><string>/tmp/monitortray.err</string>
+</dict>
+</plist>
+"#
+    )
+}
+
+fn launchctl(args: &[&str]) -> Result<()> {
+    let out = ProcessCommand::new("launchctl")
+        .args(args)
+        .output()
+        .with_context(|| format!("running launchctl {}", args.join(" ")))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        return Err(anyhow!(
+            "launchctl failed (exit={}):\nstdout:\n{}\nstderr:\n{}",
+            out.status,
+            stdout.trim(),
+            stderr.trim()
+        ));
+    }
+    Ok(())
+}
@@ -1,27 +1,96 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use crate::config::PresetAdjustments;
+use crate::tray::state::{RecentInput, MAX_RECENT_INPUTS};
 
 pub const CMD_BASE_INPUT: u16 = 2000;
+pub const CMD_BASE_BRIGHTNESS: u16 = 3000;
 pub const CMD_RELOAD: u16 = 5000;
 pub const CMD_QUIT: u16 = 5001;
 pub const CMD_TOGGLE_STARTUP: u16 = 5002;
 pub const CMD_EDIT_CONFIG: u16 = 5003;
 pub const CMD_OPEN_CONFIG_FOLDER: u16 = 5004;
+pub const CMD_DOCTOR: u16 = 5005;
+pub const CMD_COPY_CONFIG_PATH: u16 = 5006;
+pub const CMD_BASE_RECENT: u16 = 5100;
+
+/// Ids available to input presets and brightness presets. Each range ends where the next one
+/// starts, so the menu has to stop numbering presets there (see `common::numbered_presets`).
+pub const CMD_INPUT_IDS: Range<u16> = CMD_BASE_INPUT..CMD_BASE_BRIGHTNESS;
+pub const CMD_BRIGHTNESS_IDS: Range<u16> = CMD_BASE_BRIGHTNESS..CMD_RELOAD;
+/// "Recent inputs" entries; the n-th id is the n-th most recent input.
+pub const CMD_RECENT_IDS: Range<u16> = CMD_BASE_RECENT..CMD_BASE_RECENT + MAX_RECENT_INPUTS as u16;
 
 pub type InputsMap = BTreeMap<u16, (String, u16)>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// One display's input presets. Command ids are unique across all targets of a menu.
+#[derive(Debug, Clone)]
+pub struct InputTarget {
+    pub title: String,
+    pub display_selector: String,
+    pub inputs: InputsMap,
+    /// `brightness_presets`, numbered from `CMD_BASE_BRIGHTNESS`.
+    pub brightness: InputsMap,
+    /// Adjustments applied after switching, keyed by preset name (see `ResolvedConfig`).
+    pub adjustments: HashMap<String, PresetAdjustments>,
+    /// Ask before switching this display (`confirm_switch` in the config).
+    pub confirm: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Command {
-    Input(u16),
+    Input {
+        selector: String,
+        value: u16,
+        adjust: PresetAdjustments,
+    },
+    Brightness {
+        selector: String,
+        value: u16,
+    },
     Reload,
     Quit,
     ToggleStartup,
     EditConfig,
     OpenConfigFolder,
+    CopyConfigPath,
+    Doctor,
 }
 
-pub fn decode(cmd_id: u16, inputs: &InputsMap) -> Option<Command> {
-    if let Some((_name, value)) = inputs.get(&cmd_id) {
-        return Some(Command::Input(*value));
+pub fn decode(cmd_id: u16, targets: &[InputTarget], recent: &[RecentInput]) -> Option<Command> {
+    if CMD_RECENT_IDS.contains(&cmd_id) {
+        let entry = recent.get(usize::from(cmd_id - CMD_BASE_RECENT))?;
+        // Same adjustments as clicking the preset itself, if it's still configured.
+        let adjust = targets
+            .iter()
+            .filter(|t| t.display_selector == entry.selector)
+            .find_map(|t| {
+                let (name, _value) = t.inputs.values().find(|(_name, v)| *v == entry.value)?;
+                t.adjustments.get(name).copied()
+            })
+            .unwrap_or_default();
+        return Some(Command::Input {
+            selector: entry.selector.clone(),
+            value: entry.value,
+            adjust,
+        });
+    }
+
+    for target in targets {
+        if let Some((name, value)) = target.inputs.get(&cmd_id) {
+            return Some(Command::Input {
+                selector: target.display_selector.clone(),
+                value: *value,
+                adjust: target.adjustments.get(name).copied().unwrap_or_default(),
+            });
+        }
+        if let Some((_name, value)) = target.brightness.get(&cmd_id) {
+            return Some(Command::Brightness {
+                selector: target.display_selector.clone(),
+                value: *value,
+            });
+        }
     }
 
     match cmd_id {
@@ -30,6 +99,86 @@ pub fn decode(cmd_id: u16, inputs: &InputsMap) -> Option<Command> {
         CMD_TOGGLE_STARTUP => Some(Command::ToggleStartup),
         CMD_EDIT_CONFIG => Some(Command::EditConfig),
         CMD_OPEN_CONFIG_FOLDER => Some(Command::OpenConfigFolder),
+        CMD_COPY_CONFIG_PATH => Some(Command::CopyConfigPath),
+        CMD_DOCTOR => Some(Command::Doctor),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(selector: &str, inputs: &[(u16, &str, u16)]) -> InputTarget {
+        InputTarget {
+            title: format!("Display {selector}"),
+            display_selector: selector.to_string(),
+            inputs: inputs
+                .iter()
+                .map(|&(id, name, value)| (id, (name.to_string(), value)))
+                .collect(),
+            brightness: InputsMap::from([(CMD_BASE_BRIGHTNESS, ("night".to_string(), 20))]),
+            adjustments: HashMap::from([(
+                "dp1".to_string(),
+                PresetAdjustments {
+                    contrast: Some(60),
+                    ..PresetAdjustments::default()
+                },
+            )]),
+            confirm: false,
+        }
+    }
+
+    fn input(selector: &str, value: u16, adjust: PresetAdjustments) -> Option<Command> {
+        Some(Command::Input {
+            selector: selector.to_string(),
+            value,
+            adjust,
+        })
+    }
+
+    #[test]
+    fn recent_ids_decode_to_their_entry_with_the_preset_adjustments() {
+        let targets = [target("1", &[(CMD_BASE_INPUT, "dp1", 15)])];
+        let recent = [
+            RecentInput {
+                selector: "1".to_string(),
+                value: 17,
+            },
+            RecentInput {
+                selector: "1".to_string(),
+                value: 15,
+            },
+        ];
+        assert_eq!(
+            decode(CMD_BASE_RECENT, &targets, &recent),
+            input("1", 17, PresetAdjustments::default())
+        );
+        assert_eq!(
+            decode(CMD_BASE_RECENT + 1, &targets, &recent),
+            input("1", 15, targets[0].adjustments["dp1"])
+        );
+        assert_eq!(decode(CMD_BASE_RECENT + 2, &targets, &recent), None);
+    }
+
+    #[test]
+    fn preset_and_action_ids_decode() {
+        let targets = [
+            target("1", &[(CMD_BASE_INPUT, "dp1", 15)]),
+            target("2", &[(CMD_BASE_INPUT + 1, "hdmi1", 17)]),
+        ];
+        assert_eq!(
+            decode(CMD_BASE_INPUT + 1, &targets, &[]),
+            input("2", 17, PresetAdjustments::default())
+        );
+        assert_eq!(
+            decode(CMD_BASE_BRIGHTNESS, &targets, &[]),
+            Some(Command::Brightness {
+                selector: "1".to_string(),
+                value: 20,
+            })
+        );
+        assert_eq!(decode(CMD_QUIT, &targets, &[]), Some(Command::Quit));
+        assert_eq!(decode(CMD_BASE_INPUT + 2, &targets, &[]), None);
+    }
+}
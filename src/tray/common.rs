@@ -1,6 +1,12 @@
 use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 
-pub fn pretty_input_label(key: &str) -> &str {
+/// Menu name for preset `key`: its `inputs_meta` label from `labels`, else a built-in name for
+/// the common keys, else the key itself.
+pub fn pretty_input_label<'a>(key: &'a str, labels: &'a HashMap<String, String>) -> &'a str {
+    if let Some(label) = labels.get(key) {
+        return label;
+    }
     match key {
         "dp1" => "DisplayPort 1",
         "dp2" => "DisplayPort 2",
@@ -12,33 +18,46 @@ pub fn pretty_input_label(key: &str) -> &str {
     }
 }
 
-pub fn build_inputs(inputs: &HashMap<String, u16>, base_cmd: u16) -> BTreeMap<u16, (String, u16)> {
+/// `inputs` numbered from `ids`; when there are none, `config::DEFAULT_INPUTS` if
+/// `builtin_defaults` is set, else nothing.
+pub fn build_inputs(
+    inputs: &HashMap<String, u16>,
+    ids: Range<u16>,
+    builtin_defaults: bool,
+) -> BTreeMap<u16, (String, u16)> {
     if inputs.is_empty() {
-        return default_inputs(base_cmd);
+        return if builtin_defaults {
+            default_inputs(ids)
+        } else {
+            BTreeMap::new()
+        };
     }
+    numbered_presets(inputs, ids)
+}
 
-    let mut keys = inputs
+/// `presets` sorted by name, keyed by consecutive command ids from `ids`. Presets that don't
+/// fit in `ids` are left out, so the result can be shorter than `presets`.
+pub fn numbered_presets(
+    presets: &HashMap<String, u16>,
+    ids: Range<u16>,
+) -> BTreeMap<u16, (String, u16)> {
+    let mut keys = presets
         .iter()
         .map(|(k, v)| (k.to_string(), *v))
         .collect::<Vec<_>>();
     keys.sort_by(|a, b| a.0.cmp(&b.0));
 
     let mut out: BTreeMap<u16, (String, u16)> = BTreeMap::new();
-    let mut next_cmd = base_cmd;
-    for (k, v) in keys {
-        out.insert(next_cmd, (k, v));
-        next_cmd += 1;
+    for (cmd, (k, v)) in ids.zip(keys) {
+        out.insert(cmd, (k, v));
     }
     out
 }
 
-pub fn default_inputs(base_cmd: u16) -> BTreeMap<u16, (String, u16)> {
-    // Defaults for your XG27ACS setup; override with config for other monitors.
+pub fn default_inputs(ids: Range<u16>) -> BTreeMap<u16, (String, u16)> {
     let mut inputs: BTreeMap<u16, (String, u16)> = BTreeMap::new();
-    let mut next_cmd = base_cmd;
-    for &(k, v) in crate::config::DEFAULT_INPUTS {
-        inputs.insert(next_cmd, (k.to_string(), v));
-        next_cmd += 1;
+    for (cmd, &(k, v)) in ids.zip(crate::config::DEFAULT_INPUTS) {
+        inputs.insert(cmd, (k.to_string(), v));
     }
     inputs
 }
@@ -64,3 +83,73 @@ where
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(inputs: &BTreeMap<u16, (String, u16)>) -> Vec<(u16, &str, u16)> {
+        inputs
+            .iter()
+            .map(|(id, (name, value))| (*id, name.as_str(), *value))
+            .collect()
+    }
+
+    #[test]
+    fn without_presets_the_builtin_defaults_are_optional() {
+        let none = HashMap::new();
+        let defaults = build_inputs(&none, 10..20, true);
+        assert_eq!(
+            names(&defaults),
+            [
+                (10, "dp1", 15),
+                (11, "dp2", 16),
+                (12, "hdmi1", 17),
+                (13, "hdmi2", 18),
+                (14, "usb_c", 27)
+            ]
+        );
+        assert!(build_inputs(&none, 10..20, false).is_empty());
+    }
+
+    #[test]
+    fn configured_presets_replace_the_defaults_in_name_order() {
+        let presets = HashMap::from([("mac".to_string(), 15), ("pc".to_string(), 17)]);
+        for builtin_defaults in [true, false] {
+            assert_eq!(
+                names(&build_inputs(&presets, 10..20, builtin_defaults)),
+                [(10, "mac", 15), (11, "pc", 17)]
+            );
+        }
+        assert_eq!(
+            names(&build_inputs(&presets, 10..11, true)),
+            [(10, "mac", 15)]
+        );
+    }
+
+    #[test]
+    fn labels_override_the_standard_names() {
+        let labels = HashMap::from([("dp1".to_string(), "MacBook".to_string())]);
+        assert_eq!(pretty_input_label("dp1", &labels), "MacBook");
+        assert_eq!(pretty_input_label("usb_c", &labels), "USB-C");
+        assert_eq!(pretty_input_label("console", &labels), "console");
+    }
+
+    #[test]
+    fn a_startup_pref_is_applied_and_otherwise_read() {
+        let set = |enabled: bool| if enabled { Ok(()) } else { Err("denied") };
+        assert_eq!(
+            apply_startup_pref(Some(true), set, || Ok(false)),
+            (true, None)
+        );
+        assert_eq!(
+            apply_startup_pref(Some(false), set, || Ok(true)),
+            (false, Some("denied".to_string()))
+        );
+        assert_eq!(apply_startup_pref(None, set, || Ok(true)), (true, None));
+        assert_eq!(
+            apply_startup_pref(None, set, || Err("no agent")),
+            (false, Some("no agent".to_string()))
+        );
+    }
+}
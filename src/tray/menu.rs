@@ -13,6 +13,10 @@ impl MenuSpec {
 pub enum MenuItem {
     Header(String),
     Separator,
+    Submenu {
+        title: String,
+        items: Vec<MenuItem>,
+    },
     Action {
         id: u16,
         title: String,
@@ -20,3 +24,53 @@ pub enum MenuItem {
         enabled: bool,
     },
 }
+
+/// Win32 menu labels for `titles` (one menu level): literal `&` is escaped as `&&`, and each
+/// label gets a `&` mnemonic on a letter/digit no earlier label took, preferring word starts.
+/// Labels with no free character are left without one.
+pub fn mnemonic_labels(titles: &[&str]) -> Vec<String> {
+    let mut used: Vec<char> = Vec::new();
+    titles
+        .iter()
+        .map(|title| {
+            let chars = title.chars().collect::<Vec<_>>();
+            let free = |i: &usize| {
+                let c = chars[*i];
+                c.is_ascii_alphanumeric() && !used.contains(&c.to_ascii_lowercase())
+            };
+            let word_start = |i: &usize| *i == 0 || !chars[*i - 1].is_alphanumeric();
+            let pick = (0..chars.len())
+                .filter(|i| word_start(i) && free(i))
+                .chain((0..chars.len()).filter(free))
+                .next();
+            if let Some(i) = pick {
+                used.push(chars[i].to_ascii_lowercase());
+            }
+
+            let mut label = String::with_capacity(title.len() + 2);
+            for (i, c) in chars.iter().enumerate() {
+                if Some(i) == pick {
+                    label.push('&');
+                }
+                if *c == '&' {
+                    label.push('&');
+                }
+                label.push(*c);
+            }
+            label
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icon_prefixed_labels_get_a_mnemonic_on_their_text() {
+        assert_eq!(
+            mnemonic_labels(&["🔌 USB-C (26)", "🖥 DisplayPort 1 (15)", "Dock"]),
+            ["🔌 &USB-C (26)", "🖥 &DisplayPort 1 (15)", "D&ock"]
+        );
+    }
+}
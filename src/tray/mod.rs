@@ -4,3 +4,60 @@ pub mod menu;
 pub mod model;
 pub mod platform;
 pub mod startup;
+pub mod state;
+
+use std::{thread, time::Duration};
+
+use anyhow::Result;
+
+/// Runs the tray app until it quits. Shared by `monitortray` and `monitorctl tray`.
+pub fn run() -> Result<()> {
+    let delay = startup_delay(
+        crate::startup::launched_at_login(),
+        crate::config::load_optional().ok().flatten().as_ref(),
+    );
+    if !delay.is_zero() {
+        thread::sleep(delay);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        platform::windows::run()
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        platform::macos::run()
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        anyhow::bail!("The tray is only supported on Windows and macOS.");
+    }
+}
+
+/// How long to wait before the first display lookup: `startup_delay_ms` when launched at login,
+/// so it doesn't race the display stack, and nothing otherwise. A config that fails to load
+/// (`None` here) is reported later by the tray itself.
+fn startup_delay(at_login: bool, cfg: Option<&crate::config::Config>) -> Duration {
+    match cfg {
+        Some(cfg) if at_login => Duration::from_millis(cfg.startup_delay_ms),
+        _ => Duration::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn only_login_launches_wait() {
+        let cfg: Config = serde_json::from_str(r#"{ "startup_delay_ms": 1500 }"#).unwrap();
+        assert_eq!(startup_delay(true, Some(&cfg)), Duration::from_millis(1500));
+        assert_eq!(startup_delay(false, Some(&cfg)), Duration::ZERO);
+        assert_eq!(startup_delay(true, None), Duration::ZERO);
+        let unset: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(startup_delay(true, Some(&unset)), Duration::ZERO);
+    }
+}
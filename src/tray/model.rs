@@ -1,50 +1,131 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
 
-use crate::platform::Backend;
+use crate::config::{MonitorMatch, PresetAdjustments};
+use crate::platform::{Backend, BackendOptions, DisplayInfo};
 use crate::tray::commands::{
-    Command, InputsMap, CMD_BASE_INPUT, CMD_EDIT_CONFIG, CMD_OPEN_CONFIG_FOLDER, CMD_QUIT,
-    CMD_RELOAD, CMD_TOGGLE_STARTUP,
+    Command, InputTarget, InputsMap, CMD_BRIGHTNESS_IDS, CMD_COPY_CONFIG_PATH, CMD_DOCTOR,
+    CMD_EDIT_CONFIG, CMD_INPUT_IDS, CMD_OPEN_CONFIG_FOLDER, CMD_QUIT, CMD_RECENT_IDS, CMD_RELOAD,
+    CMD_TOGGLE_STARTUP,
 };
 use crate::tray::menu::{MenuItem, MenuSpec};
 use crate::tray::startup::StartupManager;
+use crate::tray::state::{self, RecentInput, MAX_RECENT_INPUTS};
 use crate::{config, platform, tray::common};
 
 pub struct TrayModel {
-    inputs: InputsMap,
+    targets: Vec<InputTarget>,
     display_selector: String,
+    displays: Vec<DisplayInfo>,
+    linked_groups: Vec<Vec<MonitorMatch>>,
     backend: Box<dyn Backend>,
+    backend_options: BackendOptions,
     last_error: Option<String>,
     start_enabled: bool,
     start_pref: Option<bool>,
+    show_input_in_titlebar: bool,
+    flash_on_switch: bool,
+    notifications: bool,
+    unknown_input_values: Vec<u16>,
+    /// `inputs_meta` icons by preset name, prefixed to menu labels.
+    input_icons: HashMap<String, String>,
+    /// `inputs_meta` labels by preset name (see `common::pretty_input_label`).
+    input_labels: HashMap<String, String>,
+    /// `config::editor`; `None` opens the config with the OS association.
+    editor: Option<String>,
+    /// No presets are configured, so the menu offers `config::DEFAULT_INPUTS`, or nothing
+    /// without `builtin_defaults`.
+    standard_inputs: bool,
+    builtin_defaults: bool,
+    current_input: Option<u16>,
+    /// Input each display was on when the menu was last opened, by selector, for the
+    /// checkmarks. Displays that couldn't be read are missing.
+    active_inputs: HashMap<String, u16>,
+    debounce: Duration,
+    cooldown: Duration,
+    /// Selector, value and time of the last successful switch, for `debounce` and `cooldown`.
+    last_switch: Option<(String, u16, Instant)>,
+    /// Inputs switched to from the tray, newest first; persisted in the state file.
+    recent_inputs: Vec<RecentInput>,
+    /// Display dimmed by `flash_on_switch` and the brightness [`TrayModel::end_flash`] puts
+    /// back.
+    flashed: Option<(String, u16)>,
 }
 
+/// How long `flash_on_switch` keeps the display dimmed.
+const FLASH: Duration = Duration::from_millis(300);
+
 #[derive(Debug, Default, Clone)]
 pub struct ModelUpdate {
     pub refresh_menu: bool,
     pub refresh_tooltip: bool,
+    pub refresh_title: bool,
     pub quit: bool,
     pub open_path: Option<PathBuf>,
+    /// Rebuild the menu again after this long (when the switch cooldown ends).
+    pub refresh_menu_after: Option<Duration>,
+    /// Call [`TrayModel::end_flash`] after this long, from the UI thread like everything else.
+    pub end_flash_after: Option<Duration>,
+    /// Text for the platform to show in an informational dialog.
+    pub show_message: Option<String>,
+    /// Text for the platform to post as a desktop notification.
+    pub notification: Option<String>,
+    /// Text for the platform to put on the clipboard.
+    pub copy_text: Option<String>,
 }
 
 impl TrayModel {
     pub fn new() -> Result<Self> {
-        let backend = platform::backend().context("select backend")?;
-        let (display_selector, inputs, start_pref, load_error) = load_display_and_inputs(&*backend);
+        let backend_options = current_backend_options();
+        let backend =
+            platform::backend_with_options(backend_options.clone()).context("select backend")?;
+        Ok(Self::with_backend(backend, backend_options))
+    }
 
-        Ok(Self {
-            inputs,
-            display_selector,
+    /// [`TrayModel::new`] around an existing backend. Config and tray state are still loaded
+    /// from disk.
+    fn with_backend(backend: Box<dyn Backend>, backend_options: BackendOptions) -> Self {
+        let loaded = load_display_and_inputs(&*backend);
+
+        let mut model = Self {
+            targets: loaded.targets,
+            display_selector: loaded.display_selector,
+            displays: loaded.displays,
+            linked_groups: loaded.linked_groups,
             backend,
-            last_error: load_error,
-            start_enabled: start_pref.unwrap_or(false),
-            start_pref,
-        })
+            backend_options,
+            last_error: loaded.load_error,
+            start_enabled: loaded.start_pref.unwrap_or(false),
+            start_pref: loaded.start_pref,
+            show_input_in_titlebar: loaded.show_input_in_titlebar,
+            flash_on_switch: loaded.flash_on_switch,
+            notifications: loaded.notifications,
+            unknown_input_values: loaded.unknown_input_values,
+            input_icons: loaded.input_icons,
+            input_labels: loaded.input_labels,
+            editor: loaded.editor,
+            standard_inputs: loaded.standard_inputs,
+            builtin_defaults: loaded.builtin_defaults,
+            current_input: None,
+            active_inputs: HashMap::new(),
+            debounce: loaded.debounce,
+            cooldown: loaded.cooldown,
+            last_switch: None,
+            recent_inputs: state::load().recent_inputs,
+            flashed: None,
+        };
+        model.recent_inputs.truncate(MAX_RECENT_INPUTS);
+        model.read_current_input();
+        model
     }
 
-    pub fn inputs(&self) -> &InputsMap {
-        &self.inputs
+    pub fn targets(&self) -> &[InputTarget] {
+        &self.targets
     }
 
     pub fn display_selector(&self) -> &str {
@@ -55,26 +136,87 @@ impl TrayModel {
         self.last_error.as_deref()
     }
 
+    pub fn recent_inputs(&self) -> &[RecentInput] {
+        &self.recent_inputs
+    }
+
     pub fn start_enabled(&self) -> bool {
         self.start_enabled
     }
 
+    /// Compact label for the current input, if `show_input_in_titlebar` is enabled and the
+    /// backend could read it. Callers fall back to the app name on `None`.
+    pub fn status_title(&self) -> Option<String> {
+        if !self.show_input_in_titlebar {
+            return None;
+        }
+        let value = self.current_input?;
+        let primary = self.targets.first()?;
+        // Presets with their own `vcp` don't show up in a 0x60 reading.
+        let label = primary
+            .inputs
+            .values()
+            .find(|(name, v)| *v == value && config::selects_input(&primary.adjustments, name))
+            .map(|(name, _v)| common::pretty_input_label(name, &self.input_labels).to_string())
+            .unwrap_or_else(|| value.to_string());
+        Some(label)
+    }
+
     pub fn menu_spec(&self) -> MenuSpec {
+        // Grayed out during the post-switch cooldown; `handle` asks for a rebuild when it ends.
+        let enabled = self.cooldown_remaining().is_none();
         let mut items = Vec::new();
         items.push(MenuItem::Header("Inputs".to_string()));
+        if self.standard_inputs && !self.builtin_defaults {
+            items.push(MenuItem::Header(
+                "No inputs configured \u{2014} edit config".to_string(),
+            ));
+        } else if self.targets.iter().all(|t| t.inputs.is_empty()) {
+            items.push(MenuItem::Header(
+                "Only a built-in display was found \u{2014} it can't switch inputs".to_string(),
+            ));
+        } else if self.standard_inputs {
+            items.push(MenuItem::Header(
+                "Standard defaults \u{2014} configure `inputs` for your monitor".to_string(),
+            ));
+        }
 
-        for (cmd, (name, value)) in &self.inputs {
-            let label = format!("{} ({value})", common::pretty_input_label(name));
-            items.push(MenuItem::Action {
-                id: *cmd,
-                title: label,
-                checked: false,
-                enabled: true,
-            });
+        if let [target] = self.targets.as_slice() {
+            items.extend(input_items(
+                target,
+                &self.input_icons,
+                &self.input_labels,
+                self.active_inputs.get(&target.display_selector).copied(),
+                enabled,
+            ));
+            items.extend(brightness_submenu(&target.brightness));
+        } else {
+            for target in &self.targets {
+                let mut sub = input_items(
+                    target,
+                    &self.input_icons,
+                    &self.input_labels,
+                    self.active_inputs.get(&target.display_selector).copied(),
+                    enabled,
+                );
+                sub.extend(brightness_submenu(&target.brightness));
+                items.push(MenuItem::Submenu {
+                    title: target.title.clone(),
+                    items: sub,
+                });
+            }
         }
 
+        items.extend(self.recent_submenu(enabled));
+
         items.push(MenuItem::Separator);
         items.push(MenuItem::Header("Actions".to_string()));
+        // Shows which file "Edit config" opens; the path can be long, so it's a header.
+        let config_path = config::resolve_config_path();
+        items.push(MenuItem::Header(match &config_path {
+            Some(path) => format!("Config: {}", path.display()),
+            None => "No config path (set MONITORCTL_CONFIG)".to_string(),
+        }));
         items.push(MenuItem::Action {
             id: CMD_TOGGLE_STARTUP,
             title: "Start at login".to_string(),
@@ -93,6 +235,18 @@ impl TrayModel {
             checked: false,
             enabled: true,
         });
+        items.push(MenuItem::Action {
+            id: CMD_COPY_CONFIG_PATH,
+            title: "Copy config path".to_string(),
+            checked: false,
+            enabled: config_path.is_some(),
+        });
+        items.push(MenuItem::Action {
+            id: CMD_DOCTOR,
+            title: "Run diagnostics".to_string(),
+            checked: false,
+            enabled: true,
+        });
         items.push(MenuItem::Action {
             id: CMD_RELOAD,
             title: "Reload config".to_string(),
@@ -109,13 +263,100 @@ impl TrayModel {
         MenuSpec::new(items)
     }
 
+    /// A "Recent inputs" submenu, newest first, for entries whose display is still in the menu.
+    fn recent_submenu(&self, enabled: bool) -> Option<MenuItem> {
+        let items = CMD_RECENT_IDS
+            .zip(&self.recent_inputs)
+            .filter_map(|(id, recent)| {
+                let target = self
+                    .targets
+                    .iter()
+                    .find(|t| t.display_selector == recent.selector)?;
+                let name = target
+                    .inputs
+                    .values()
+                    .find(|(_name, v)| *v == recent.value)
+                    .map(|(name, _v)| common::pretty_input_label(name, &self.input_labels));
+                let mut title = match name {
+                    Some(name) => format!("{name} ({})", recent.value),
+                    None => recent.value.to_string(),
+                };
+                if self.targets.len() > 1 {
+                    title = format!("{title} \u{2014} {}", target.title);
+                }
+                Some(MenuItem::Action {
+                    id,
+                    title,
+                    checked: false,
+                    enabled,
+                })
+            })
+            .collect::<Vec<_>>();
+        (!items.is_empty()).then(|| MenuItem::Submenu {
+            title: "Recent inputs".to_string(),
+            items,
+        })
+    }
+
+    /// Question to ask before running `cmd`, if its target display has `confirm_switch` set.
+    /// The platform shows it in a dialog and only calls `handle` when the user agrees.
+    pub fn confirmation_prompt(&self, cmd: &Command) -> Option<String> {
+        let Command::Input {
+            selector, value, ..
+        } = cmd
+        else {
+            return None;
+        };
+        let target = self
+            .targets
+            .iter()
+            .find(|t| t.confirm && t.display_selector == *selector)?;
+        let input = target
+            .inputs
+            .values()
+            .find(|(_name, v)| v == value)
+            .map_or_else(|| value.to_string(), |(name, _v)| name.clone());
+        Some(format!("Switch {} to {input}?", target.title))
+    }
+
     pub fn handle(&mut self, cmd: Command, startup: &dyn StartupManager) -> Result<ModelUpdate> {
         let update = match cmd {
-            Command::Input(value) => self
-                .set_input(value)
-                .map(|_| ModelUpdate {
-                    refresh_tooltip: true,
-                    ..Default::default()
+            Command::Input {
+                selector,
+                value,
+                adjust,
+            } => match self.set_input(&selector, value, adjust) {
+                Ok(switched) => {
+                    let refresh_menu_after = self.cooldown_remaining();
+                    ModelUpdate {
+                        refresh_menu: refresh_menu_after.is_some(),
+                        refresh_tooltip: true,
+                        refresh_title: true,
+                        refresh_menu_after,
+                        end_flash_after: self.flashed.is_some().then_some(FLASH),
+                        notification: (self.notifications && switched)
+                            .then(|| format!("Switched to {}", self.input_label(&selector, value))),
+                        ..Default::default()
+                    }
+                }
+                Err(err) => {
+                    let notification = self
+                        .notifications
+                        .then(|| format!("Switch failed: {err:#}"));
+                    ModelUpdate {
+                        notification,
+                        ..self.note_error(err)
+                    }
+                }
+            },
+            Command::Brightness { selector, value } => self
+                .set_brightness(&selector, value)
+                .map(|()| {
+                    self.last_error = None;
+                    ModelUpdate {
+                        refresh_tooltip: true,
+                        ..Default::default()
+                    }
                 })
                 .unwrap_or_else(|err| self.note_error(err)),
             Command::Reload => self
@@ -126,10 +367,6 @@ impl TrayModel {
                 .unwrap_or_else(|err| self.note_error(err)),
             Command::EditConfig => self
                 .edit_config()
-                .map(|path| ModelUpdate {
-                    open_path: Some(path),
-                    ..Default::default()
-                })
                 .unwrap_or_else(|err| self.note_error(err)),
             Command::OpenConfigFolder => self
                 .open_config_folder()
@@ -138,15 +375,42 @@ impl TrayModel {
                     ..Default::default()
                 })
                 .unwrap_or_else(|err| self.note_error(err)),
-            Command::Quit => ModelUpdate {
-                quit: true,
-                ..Default::default()
+            Command::CopyConfigPath => match config::resolve_config_path() {
+                Some(path) => ModelUpdate {
+                    copy_text: Some(path.display().to_string()),
+                    ..Default::default()
+                },
+                None => self.note_error(anyhow::anyhow!("No config path available")),
             },
+            Command::Doctor => self
+                .backend
+                .doctor()
+                .map(|report| ModelUpdate {
+                    show_message: Some(report.message),
+                    ..Default::default()
+                })
+                .unwrap_or_else(|err| self.note_error(err)),
+            Command::Quit => {
+                // Don't leave a display dimmed when quitting mid-flash.
+                self.end_flash();
+                ModelUpdate {
+                    quit: true,
+                    ..Default::default()
+                }
+            }
         };
 
         Ok(update)
     }
 
+    /// Time left before input items are enabled again after the last switch.
+    fn cooldown_remaining(&self) -> Option<Duration> {
+        let (_selector, _value, at) = self.last_switch.as_ref()?;
+        self.cooldown
+            .checked_sub(at.elapsed())
+            .filter(|left| !left.is_zero())
+    }
+
     pub fn note_error(&mut self, err: anyhow::Error) -> ModelUpdate {
         self.last_error = Some(err.to_string());
         ModelUpdate {
@@ -155,20 +419,179 @@ impl TrayModel {
         }
     }
 
-    fn set_input(&mut self, value: u16) -> Result<()> {
-        self.backend
-            .set_input(&self.display_selector, value)
-            .with_context(|| format!("set input {value} on '{}'", self.display_selector))?;
+    /// Returns `false` when the click was dropped as a repeat.
+    fn set_input(&mut self, selector: &str, value: u16, adjust: PresetAdjustments) -> Result<bool> {
+        // A twitchy click can deliver the same menu command twice; only identical repeats are
+        // dropped, a different input or display always goes through.
+        let repeated = self.last_switch.as_ref().is_some_and(|(sel, v, at)| {
+            sel == selector && *v == value && at.elapsed() < self.debounce
+        });
+        if repeated {
+            return Ok(false);
+        }
+
+        let code = adjust.input_code();
+        platform::set_input_code(&*self.backend, selector, code, value)
+            .with_context(|| format!("set input {value} on '{selector}'"))?;
+        adjust.apply(&*self.backend, selector)?;
+        if self.flash_on_switch {
+            self.flash(selector);
+        }
+
+        // Linked displays follow the one that was clicked, adjustments included. This only fans
+        // out from explicit tray actions, so a propagated change can never trigger another round.
+        for linked in config::linked_selectors(&self.linked_groups, &self.displays, selector) {
+            platform::set_input_code(&*self.backend, &linked, code, value)
+                .with_context(|| format!("set input {value} on linked '{linked}'"))?;
+            adjust.apply(&*self.backend, &linked)?;
+        }
+
+        self.last_switch = Some((selector.to_string(), value, Instant::now()));
+        // A write to another code says nothing about what 0x60 reads now.
+        if code == platform::VCP_INPUT {
+            self.active_inputs.insert(selector.to_string(), value);
+            if self.show_input_in_titlebar && selector == self.display_selector {
+                self.current_input = Some(value);
+            }
+        }
+        self.remember_recent(selector, value);
         self.last_error = None;
+        Ok(true)
+    }
+
+    /// `USB-C` for a preset value (plus the display's name when the menu has several), else
+    /// `input 27`.
+    fn input_label(&self, selector: &str, value: u16) -> String {
+        let target = self.targets.iter().find(|t| t.display_selector == selector);
+        let label = target
+            .and_then(|t| t.inputs.values().find(|(_name, v)| *v == value))
+            .map(|(name, _v)| common::pretty_input_label(name, &self.input_labels).to_string())
+            .unwrap_or_else(|| format!("input {value}"));
+        match target {
+            Some(t) if self.targets.len() > 1 => format!("{label} on {}", t.title),
+            _ => label,
+        }
+    }
+
+    /// Moves `selector`/`value` to the front of the recent inputs and saves them.
+    fn remember_recent(&mut self, selector: &str, value: u16) {
+        let entry = RecentInput {
+            selector: selector.to_string(),
+            value,
+        };
+        self.recent_inputs.retain(|r| *r != entry);
+        self.recent_inputs.insert(0, entry);
+        self.recent_inputs.truncate(MAX_RECENT_INPUTS);
+
+        let mut saved = state::load();
+        saved.recent_inputs = self.recent_inputs.clone();
+        state::save(&saved);
+    }
+
+    /// Sets brightness on `selector` and, like an input switch, on its linked displays.
+    fn set_brightness(&mut self, selector: &str, value: u16) -> Result<()> {
+        self.backend
+            .set_vcp_raw(selector, platform::VCP_BRIGHTNESS, value)
+            .with_context(|| format!("set brightness {value} on '{selector}'"))?;
+        for linked in config::linked_selectors(&self.linked_groups, &self.displays, selector) {
+            self.backend
+                .set_vcp_raw(&linked, platform::VCP_BRIGHTNESS, value)
+                .with_context(|| format!("set brightness {value} on linked '{linked}'"))?;
+        }
         Ok(())
     }
 
+    /// Dims `selector` to half its brightness; the platform calls [`TrayModel::end_flash`]
+    /// when [`FLASH`] is up, so the menu stays responsive meanwhile. Best-effort: a backend that
+    /// can't read brightness (so couldn't restore it) isn't touched.
+    fn flash(&mut self, selector: &str) {
+        // A flash still in progress would otherwise be read back as the brightness to restore.
+        self.end_flash();
+
+        let Ok(values) = self
+            .backend
+            .get_vcp_many(selector, &[platform::VCP_BRIGHTNESS])
+        else {
+            return;
+        };
+        let Some(&(_code, current, _max)) = values.first() else {
+            return;
+        };
+        if self
+            .backend
+            .set_vcp_raw(selector, platform::VCP_BRIGHTNESS, current / 2)
+            .is_ok()
+        {
+            self.flashed = Some((selector.to_string(), current));
+        }
+    }
+
+    /// Restores the brightness dimmed by the last flash, if any is still pending.
+    pub fn end_flash(&mut self) {
+        if let Some((selector, brightness)) = self.flashed.take() {
+            let _ = self
+                .backend
+                .set_vcp_raw(&selector, platform::VCP_BRIGHTNESS, brightness);
+        }
+    }
+
+    /// Reads every menu display's input for the checkmarks; call right before the menu is
+    /// shown. Best-effort like `read_current_input`: displays that can't be read (e.g. with an
+    /// m1ddc that has no `get input`) just get no checkmark.
+    pub fn refresh_active_inputs(&mut self) {
+        self.active_inputs = self
+            .targets
+            .iter()
+            .filter(|t| !t.inputs.is_empty())
+            .filter_map(|t| {
+                let value = self.backend.get_input(&t.display_selector).ok()?;
+                (!self.unknown_input_values.contains(&value))
+                    .then(|| (t.display_selector.clone(), value))
+            })
+            .collect();
+    }
+
+    fn read_current_input(&mut self) {
+        // Best-effort: `get_input` is slow or unsupported on some backends, so failures just
+        // leave the title as the app name. So do readings configured as `unknown_input_values`.
+        self.current_input = if self.show_input_in_titlebar {
+            self.backend
+                .get_input(&self.display_selector)
+                .ok()
+                .filter(|v| !self.unknown_input_values.contains(v))
+        } else {
+            None
+        };
+    }
+
     fn reload_config(&mut self, startup: &dyn StartupManager) -> Result<ModelUpdate> {
-        let (display_selector, inputs, start_pref, load_error) =
-            load_display_and_inputs(&*self.backend);
-        self.display_selector = display_selector;
-        self.inputs = inputs;
-        self.start_pref = start_pref;
+        let backend_options = current_backend_options();
+        if backend_options != self.backend_options {
+            self.backend = platform::backend_with_options(backend_options.clone())
+                .context("select backend")?;
+            self.backend_options = backend_options;
+        } else {
+            self.backend.forget_displays();
+        }
+
+        let loaded = load_display_and_inputs(&*self.backend);
+        self.display_selector = loaded.display_selector;
+        self.targets = loaded.targets;
+        self.displays = loaded.displays;
+        self.linked_groups = loaded.linked_groups;
+        self.start_pref = loaded.start_pref;
+        self.show_input_in_titlebar = loaded.show_input_in_titlebar;
+        self.flash_on_switch = loaded.flash_on_switch;
+        self.notifications = loaded.notifications;
+        self.unknown_input_values = loaded.unknown_input_values;
+        self.input_icons = loaded.input_icons;
+        self.input_labels = loaded.input_labels;
+        self.editor = loaded.editor;
+        self.standard_inputs = loaded.standard_inputs;
+        self.builtin_defaults = loaded.builtin_defaults;
+        self.debounce = loaded.debounce;
+        self.cooldown = loaded.cooldown;
+        self.read_current_input();
 
         let (start_enabled, startup_error) = common::apply_startup_pref(
             self.start_pref,
@@ -180,11 +603,12 @@ impl TrayModel {
             || startup.is_enabled().context("read startup setting"),
         );
         self.start_enabled = start_enabled;
-        self.last_error = load_error.or(startup_error);
+        self.last_error = loaded.load_error.or(startup_error);
 
         Ok(ModelUpdate {
             refresh_menu: true,
             refresh_tooltip: true,
+            refresh_title: true,
             ..Default::default()
         })
     }
@@ -204,9 +628,18 @@ impl TrayModel {
         })
     }
 
-    fn edit_config(&mut self) -> Result<PathBuf> {
+    fn edit_config(&mut self) -> Result<ModelUpdate> {
         let path = config::ensure_config_file_exists().context("ensure config exists")?;
-        Ok(path)
+        let Some(editor) = self.editor.as_deref() else {
+            return Ok(ModelUpdate {
+                open_path: Some(path),
+                ..Default::default()
+            });
+        };
+        config::editor_command(editor, &path)
+            .spawn()
+            .with_context(|| format!("launch editor '{editor}'"))?;
+        Ok(ModelUpdate::default())
     }
 
     fn open_config_folder(&mut self) -> Result<PathBuf> {
@@ -220,30 +653,793 @@ impl TrayModel {
     }
 }
 
-fn load_display_and_inputs(
-    backend: &dyn Backend,
-) -> (String, InputsMap, Option<bool>, Option<String>) {
-    let cfg = match config::load_optional() {
+/// Backend options from the current config. A broken config is reported by the regular load
+/// path, so it just yields defaults here.
+fn current_backend_options() -> BackendOptions {
+    tray_backend_options(config::load_optional().ok().flatten().as_ref())
+}
+
+/// The tray switches the same displays over and over, so it keeps their handles open (see
+/// `BackendOptions::cache_monitors`) and spaces writes out unless the config says otherwise.
+fn tray_backend_options(cfg: Option<&config::Config>) -> BackendOptions {
+    let interval = cfg
+        .and_then(|c| c.min_write_interval_ms)
+        .unwrap_or(config::DEFAULT_TRAY_MIN_WRITE_INTERVAL_MS);
+    BackendOptions {
+        cache_monitors: true,
+        min_write_interval: Duration::from_millis(interval),
+        ..config::backend_options(cfg)
+    }
+}
+
+/// `target`'s input actions. `active` is the display's 0x60 reading; presets with their own
+/// `vcp` are never checked, since the reading can't tell whether they're active.
+fn input_items(
+    target: &InputTarget,
+    icons: &HashMap<String, String>,
+    labels: &HashMap<String, String>,
+    active: Option<u16>,
+    enabled: bool,
+) -> Vec<MenuItem> {
+    target
+        .inputs
+        .iter()
+        .map(|(cmd, (name, value))| {
+            let label = format!("{} ({value})", common::pretty_input_label(name, labels));
+            MenuItem::Action {
+                id: *cmd,
+                title: match icons.get(name) {
+                    Some(icon) => format!("{icon} {label}"),
+                    None => label,
+                },
+                checked: active == Some(*value) && config::selects_input(&target.adjustments, name),
+                enabled,
+            }
+        })
+        .collect()
+}
+
+/// A "Brightness" submenu for `presets`, or nothing when none are configured.
+fn brightness_submenu(presets: &InputsMap) -> Option<MenuItem> {
+    if presets.is_empty() {
+        return None;
+    }
+    let items = presets
+        .iter()
+        .map(|(cmd, (name, value))| MenuItem::Action {
+            id: *cmd,
+            title: format!("{name} ({value})"),
+            checked: false,
+            enabled: true,
+        })
+        .collect();
+    Some(MenuItem::Submenu {
+        title: "Brightness".to_string(),
+        items,
+    })
+}
+
+struct LoadedConfig {
+    /// Selector of the primary (first) target.
+    display_selector: String,
+    targets: Vec<InputTarget>,
+    displays: Vec<DisplayInfo>,
+    linked_groups: Vec<Vec<MonitorMatch>>,
+    start_pref: Option<bool>,
+    show_input_in_titlebar: bool,
+    flash_on_switch: bool,
+    notifications: bool,
+    unknown_input_values: Vec<u16>,
+    input_icons: HashMap<String, String>,
+    input_labels: HashMap<String, String>,
+    editor: Option<String>,
+    standard_inputs: bool,
+    builtin_defaults: bool,
+    debounce: Duration,
+    cooldown: Duration,
+    load_error: Option<String>,
+}
+
+/// One non-blank `inputs_meta` field by preset name.
+fn inputs_meta_field(
+    cfg: Option<&config::Config>,
+    field: impl Fn(&config::InputMeta) -> Option<&str>,
+) -> HashMap<String, String> {
+    cfg.map(|c| {
+        c.inputs_meta
+            .iter()
+            .filter_map(|(name, meta)| {
+                let value = field(meta)?.trim();
+                (!value.is_empty()).then(|| (name.clone(), value.to_string()))
+            })
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn load_display_and_inputs(backend: &dyn Backend) -> LoadedConfig {
+    let (cfg, recovered) = match config::load_recovering() {
         Ok(v) => v,
         Err(e) => {
-            return (
-                "1".to_string(),
-                common::default_inputs(CMD_BASE_INPUT),
-                None,
-                Some(e.to_string()),
-            )
+            return LoadedConfig {
+                display_selector: "1".to_string(),
+                targets: vec![InputTarget {
+                    title: "Display 1".to_string(),
+                    display_selector: "1".to_string(),
+                    inputs: common::default_inputs(CMD_INPUT_IDS),
+                    brightness: InputsMap::new(),
+                    adjustments: HashMap::new(),
+                    confirm: false,
+                }],
+                displays: Vec::new(),
+                linked_groups: Vec::new(),
+                start_pref: None,
+                show_input_in_titlebar: false,
+                flash_on_switch: false,
+                notifications: true,
+                unknown_input_values: Vec::new(),
+                input_icons: HashMap::new(),
+                input_labels: HashMap::new(),
+                editor: config::editor(None),
+                standard_inputs: true,
+                builtin_defaults: true,
+                debounce: Duration::from_millis(config::DEFAULT_TRAY_DEBOUNCE_MS),
+                cooldown: Duration::from_millis(config::DEFAULT_SWITCH_COOLDOWN_MS),
+                load_error: Some(e.to_string()),
+            }
         }
     };
 
     let start_pref = cfg.as_ref().and_then(|c| c.start_with_windows);
+    let show_input_in_titlebar = cfg.as_ref().is_some_and(|c| c.show_input_in_titlebar);
+    let flash_on_switch = cfg.as_ref().is_some_and(|c| c.flash_on_switch);
+    let notifications = cfg.as_ref().is_none_or(|c| c.notifications);
+    let unknown_input_values = cfg
+        .as_ref()
+        .map(|c| c.unknown_input_values.clone())
+        .unwrap_or_default();
+    let editor = config::editor(cfg.as_ref());
+    let input_icons = inputs_meta_field(cfg.as_ref(), |meta| meta.icon.as_deref());
+    let input_labels = inputs_meta_field(cfg.as_ref(), |meta| meta.label.as_deref());
+    let standard_inputs = !cfg
+        .as_ref()
+        .is_some_and(|c| !c.inputs.is_empty() || c.monitors.iter().any(|m| !m.inputs.is_empty()));
+    let builtin_defaults = cfg.as_ref().is_none_or(|c| c.use_builtin_default_inputs);
+    let debounce = Duration::from_millis(
+        cfg.as_ref()
+            .map_or(config::DEFAULT_TRAY_DEBOUNCE_MS, |c| c.tray_debounce_ms),
+    );
+    let cooldown = Duration::from_millis(
+        cfg.as_ref()
+            .map_or(config::DEFAULT_SWITCH_COOLDOWN_MS, |c| c.switch_cooldown_ms),
+    );
+    let linked_groups = cfg
+        .as_ref()
+        .map(|c| c.linked_groups.clone())
+        .unwrap_or_default();
+    let brightness_presets = cfg
+        .as_ref()
+        .map(|c| c.brightness_presets.clone())
+        .unwrap_or_default();
 
     let (displays, load_error) = match backend.list_displays() {
         Ok(report) => (report.displays, None),
         Err(e) => (Vec::new(), Some(e.to_string())),
     };
 
-    let resolved = config::resolve(cfg.as_ref(), &displays, None);
-    let inputs = common::build_inputs(&resolved.inputs, CMD_BASE_INPUT);
+    // With several displays and no pinned `default_display`, show a section per display.
+    // Built-in panels are left out; if they're all there is, the menu just says so.
+    let pinned = cfg.as_ref().is_some_and(|c| c.default_display.is_some());
+    let hide_unresponsive = cfg.as_ref().is_some_and(|c| c.hide_unresponsive_displays);
+    let external = displays
+        .iter()
+        .filter(|d| !config::is_internal(cfg.as_ref(), d))
+        .filter(|d| !hide_unresponsive || backend.probe_ddc(&d.index.to_string()) != Some(false))
+        .collect::<Vec<_>>();
+    let mut warnings = cfg.as_ref().map(|c| c.warnings.clone()).unwrap_or_default();
+    let mut ids = CmdIds {
+        builtin_defaults,
+        ..CmdIds::default()
+    };
+    let targets = if external.len() > 1 && !pinned {
+        external
+            .iter()
+            .map(|d| {
+                let resolved = config::resolve_for_display(cfg.as_ref(), d);
+                let inputs = ids.inputs(&resolved.inputs);
+                let brightness = ids.brightness(&brightness_presets);
+                InputTarget {
+                    title: display_title(cfg.as_ref(), d),
+                    confirm: config::confirm_switch(
+                        cfg.as_ref(),
+                        &displays,
+                        &resolved.display_selector,
+                    ),
+                    display_selector: resolved.display_selector,
+                    inputs,
+                    brightness,
+                    adjustments: resolved.adjustments,
+                }
+            })
+            .collect::<Vec<_>>()
+    } else if let ([only], false) = (external.as_slice(), pinned) {
+        let resolved = config::resolve_for_display(cfg.as_ref(), only);
+        vec![InputTarget {
+            title: format!("Display {}", resolved.display_selector),
+            inputs: ids.inputs(&resolved.inputs),
+            brightness: ids.brightness(&brightness_presets),
+            confirm: config::confirm_switch(cfg.as_ref(), &displays, &resolved.display_selector),
+            display_selector: resolved.display_selector,
+            adjustments: resolved.adjustments,
+        }]
+    } else if let ([], [first, ..], false) = (external.as_slice(), displays.as_slice(), pinned) {
+        // Only built-in panels: offering inputs would do nothing.
+        vec![InputTarget {
+            title: display_title(cfg.as_ref(), first),
+            display_selector: first.index.to_string(),
+            inputs: InputsMap::new(),
+            brightness: InputsMap::new(),
+            adjustments: HashMap::new(),
+            confirm: false,
+        }]
+    } else {
+        let resolved = config::resolve(cfg.as_ref(), &displays, None);
+        warnings.extend(resolved.warnings.iter().cloned());
+        let resolved = follow_remembered_target(cfg.as_ref(), &displays, resolved);
+        vec![InputTarget {
+            title: format!("Display {}", resolved.display_selector),
+            inputs: ids.inputs(&resolved.inputs),
+            brightness: ids.brightness(&brightness_presets),
+            confirm: config::confirm_switch(cfg.as_ref(), &displays, &resolved.display_selector),
+            display_selector: resolved.display_selector,
+            adjustments: resolved.adjustments,
+        }]
+    };
+
+    let load_error = load_error.or(recovered).or(warnings.pop()).or_else(|| {
+        ids.truncated.then(|| {
+            format!(
+                "Too many presets for the tray menu; some were left out (at most {} inputs and {} brightness presets across all displays)",
+                CMD_INPUT_IDS.len(),
+                CMD_BRIGHTNESS_IDS.len()
+            )
+        })
+    });
 
-    (resolved.display_selector, inputs, start_pref, load_error)
+    LoadedConfig {
+        display_selector: targets[0].display_selector.clone(),
+        targets,
+        displays,
+        linked_groups,
+        start_pref,
+        show_input_in_titlebar,
+        flash_on_switch,
+        notifications,
+        unknown_input_values,
+        input_icons,
+        input_labels,
+        editor,
+        standard_inputs,
+        builtin_defaults,
+        debounce,
+        cooldown,
+        load_error,
+    }
+}
+
+/// Hands out command ids so they stay unique across all targets of a menu and never run into
+/// the next id range. Presets that don't fit are left out and flagged in `truncated`.
+#[derive(Default)]
+struct CmdIds {
+    /// Passed to `common::build_inputs`.
+    builtin_defaults: bool,
+    inputs_used: u16,
+    brightness_used: u16,
+    truncated: bool,
+}
+
+impl CmdIds {
+    fn inputs(&mut self, inputs: &HashMap<String, u16>) -> InputsMap {
+        let out = common::build_inputs(
+            inputs,
+            CMD_INPUT_IDS.start + self.inputs_used..CMD_INPUT_IDS.end,
+            self.builtin_defaults,
+        );
+        self.inputs_used += out.len() as u16;
+        self.truncated |= out.len() < inputs.len();
+        out
+    }
+
+    fn brightness(&mut self, presets: &HashMap<String, u16>) -> InputsMap {
+        let out = common::numbered_presets(
+            presets,
+            CMD_BRIGHTNESS_IDS.start + self.brightness_used..CMD_BRIGHTNESS_IDS.end,
+        );
+        self.brightness_used += out.len() as u16;
+        self.truncated |= out.len() < presets.len();
+        out
+    }
+}
+
+/// Index selectors (the `"1"` fallback, or `default_display: "2"`) drift when monitors are
+/// re-enumerated in a different order after sleep or replugging. Remember which physical
+/// monitor the rule first meant and keep targeting it while it's connected. The memory is
+/// dropped when the rule itself changes, so editing the config still takes effect.
+fn follow_remembered_target(
+    cfg: Option<&config::Config>,
+    displays: &[DisplayInfo],
+    resolved: config::ResolvedConfig,
+) -> config::ResolvedConfig {
+    let rule = resolved.display_selector.as_str();
+    if rule.parse::<u32>().is_err() {
+        // uuid:/name: selectors already follow the monitor.
+        return resolved;
+    }
+
+    let remembered = state::load();
+    if remembered.target_rule.as_deref() == Some(rule) {
+        let still_there = remembered.target_id.as_deref().and_then(|id| {
+            displays
+                .iter()
+                .find(|d| config::stable_id(d).as_deref() == Some(id))
+        });
+        if let Some(display) = still_there {
+            return config::resolve_for_display(cfg, display);
+        }
+    }
+
+    // First run, a changed rule, or the remembered monitor is gone: capture what the rule
+    // points at now.
+    let current = config::display_for_selector(displays, rule).and_then(config::stable_id);
+    if let Some(id) = current {
+        let next = state::TrayState {
+            target_rule: Some(rule.to_string()),
+            target_id: Some(id),
+            ..remembered.clone()
+        };
+        if next != remembered {
+            state::save(&next);
+        }
+    }
+    resolved
+}
+
+fn display_title(cfg: Option<&config::Config>, display: &DisplayInfo) -> String {
+    match config::display_name(cfg, display) {
+        Some(name) => format!("{name} [{}]", display.index),
+        None => format!("Display {}", display.index),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::with_config;
+    use crate::platform::mock::MockBackend;
+    use crate::tray::commands::CMD_BASE_RECENT;
+
+    struct NoStartup;
+
+    impl StartupManager for NoStartup {
+        fn is_enabled(&self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn set_enabled(&self, _enabled: bool) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn model(backend: &MockBackend) -> TrayModel {
+        TrayModel::with_backend(Box::new(backend.clone()), BackendOptions::default())
+    }
+
+    fn switch(model: &mut TrayModel, selector: &str, value: u16) -> ModelUpdate {
+        let cmd = Command::Input {
+            selector: selector.to_string(),
+            value,
+            adjust: PresetAdjustments::default(),
+        };
+        model.handle(cmd, &NoStartup).unwrap()
+    }
+
+    #[test]
+    fn repeated_clicks_within_the_debounce_are_dropped() {
+        let config = r#"{ "inputs": { "dp1": 15, "hdmi1": 17 }, "tray_debounce_ms": 60000 }"#;
+        with_config("debounce", Some(config), |_| {
+            let backend = MockBackend::new(2);
+            let mut model = model(&backend);
+            switch(&mut model, "1", 15);
+            switch(&mut model, "1", 15);
+            switch(&mut model, "1", 17);
+            switch(&mut model, "2", 17);
+            assert_eq!(
+                backend.writes(),
+                [
+                    ("1".to_string(), platform::VCP_INPUT, 15),
+                    ("1".to_string(), platform::VCP_INPUT, 17),
+                    ("2".to_string(), platform::VCP_INPUT, 17),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn a_zero_debounce_lets_repeats_through() {
+        let config = r#"{ "inputs": { "dp1": 15 }, "tray_debounce_ms": 0 }"#;
+        with_config("no-debounce", Some(config), |_| {
+            let backend = MockBackend::new(1);
+            let mut model = model(&backend);
+            switch(&mut model, "1", 15);
+            switch(&mut model, "1", 15);
+            assert_eq!(backend.writes().len(), 2);
+        });
+    }
+
+    fn with_uuid(index: u32, uuid: &str) -> DisplayInfo {
+        DisplayInfo {
+            system_uuid: Some(uuid.to_string()),
+            ..crate::platform::mock::display(index, "Mock")
+        }
+    }
+
+    fn followed(displays: &[DisplayInfo], rule: &str) -> String {
+        let resolved = config::resolve(None, displays, Some(rule));
+        follow_remembered_target(None, displays, resolved).display_selector
+    }
+
+    #[test]
+    fn index_targets_follow_the_monitor_they_first_meant() {
+        with_config("follow", None, |_| {
+            let before = [with_uuid(1, "A"), with_uuid(2, "B")];
+            let swapped = [with_uuid(1, "B"), with_uuid(2, "A")];
+            assert_eq!(followed(&before, "1"), "1");
+            assert_eq!(state::load().target_id.as_deref(), Some("uuid:A"));
+            assert_eq!(followed(&swapped, "1"), "uuid:A");
+
+            // A different rule re-captures; a monitor that's gone is let go of.
+            assert_eq!(followed(&swapped, "2"), "2");
+            assert_eq!(state::load().target_id.as_deref(), Some("uuid:A"));
+            assert_eq!(followed(&[with_uuid(2, "C")], "2"), "2");
+            assert_eq!(state::load().target_id.as_deref(), Some("uuid:C"));
+        });
+    }
+
+    #[test]
+    fn selectors_that_already_follow_the_monitor_are_kept() {
+        with_config("follow-uuid", None, |_| {
+            let displays = [with_uuid(1, "A")];
+            assert_eq!(followed(&displays, "uuid:A"), "uuid:A");
+            assert_eq!(state::load(), state::TrayState::default());
+        });
+    }
+
+    #[test]
+    fn linked_displays_follow_input_and_adjustments() {
+        let config = r#"{ "linked_groups": [[{ "index": 1 }, { "index": 2 }]] }"#;
+        with_config("linked", Some(config), |_| {
+            let backend = MockBackend::new(2);
+            let mut model = model(&backend);
+            let cmd = Command::Input {
+                selector: "2".to_string(),
+                value: 17,
+                adjust: PresetAdjustments {
+                    contrast: Some(60),
+                    ..PresetAdjustments::default()
+                },
+            };
+            model.handle(cmd, &NoStartup).unwrap();
+            assert_eq!(
+                backend.writes(),
+                [
+                    ("2".to_string(), platform::VCP_INPUT, 17),
+                    ("2".to_string(), platform::VCP_CONTRAST, 60),
+                    ("1".to_string(), platform::VCP_INPUT, 17),
+                    ("1".to_string(), platform::VCP_CONTRAST, 60),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn tray_spaces_writes_unless_configured() {
+        let options = |json: &str| {
+            let cfg = config::parse(json.as_bytes(), "test").unwrap();
+            tray_backend_options(Some(&cfg)).min_write_interval
+        };
+        let default = Duration::from_millis(config::DEFAULT_TRAY_MIN_WRITE_INTERVAL_MS);
+        assert!(!default.is_zero());
+        assert_eq!(options("{}"), default);
+        assert_eq!(tray_backend_options(None).min_write_interval, default);
+        assert_eq!(
+            options(r#"{ "min_write_interval_ms": 500 }"#),
+            Duration::from_millis(500)
+        );
+        assert_eq!(options(r#"{ "min_write_interval_ms": 0 }"#), Duration::ZERO);
+        assert!(tray_backend_options(None).cache_monitors);
+        // The CLI keeps writes unthrottled unless asked.
+        assert_eq!(
+            config::backend_options(None).min_write_interval,
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn brightness_follows_linked_displays() {
+        let config = r#"{ "linked_groups": [[{ "index": 1 }, { "index": 3 }]],
+            "brightness_presets": { "night": 25 } }"#;
+        with_config("brightness", Some(config), |_| {
+            let backend = MockBackend::new(3);
+            let mut model = model(&backend);
+            let cmd = Command::Brightness {
+                selector: "1".to_string(),
+                value: 25,
+            };
+            model.handle(cmd, &NoStartup).unwrap();
+            assert_eq!(
+                backend.writes(),
+                [
+                    ("1".to_string(), platform::VCP_BRIGHTNESS, 25),
+                    ("3".to_string(), platform::VCP_BRIGHTNESS, 25),
+                ]
+            );
+        });
+    }
+
+    fn presets(names: &[&str]) -> HashMap<String, u16> {
+        names
+            .iter()
+            .zip(1..)
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    #[test]
+    fn cmd_ids_stay_unique_across_targets() {
+        let mut ids = CmdIds::default();
+        let first = ids.inputs(&presets(&["dp1", "hdmi1"]));
+        let second = ids.inputs(&presets(&["dp1", "hdmi1", "usb_c"]));
+        let brightness = ids.brightness(&presets(&["day", "night"]));
+        assert_eq!(first.keys().copied().collect::<Vec<_>>(), [2000, 2001]);
+        assert_eq!(
+            second.keys().copied().collect::<Vec<_>>(),
+            [2002, 2003, 2004]
+        );
+        assert_eq!(brightness.keys().copied().collect::<Vec<_>>(), [3000, 3001]);
+        assert!(!ids.truncated);
+    }
+
+    #[test]
+    fn cmd_ids_leave_out_presets_past_the_range() {
+        let mut ids = CmdIds {
+            inputs_used: CMD_INPUT_IDS.len() as u16 - 2,
+            ..CmdIds::default()
+        };
+        let inputs = ids.inputs(&presets(&["a", "b", "c"]));
+        assert_eq!(
+            inputs.keys().copied().collect::<Vec<_>>(),
+            [CMD_INPUT_IDS.end - 2, CMD_INPUT_IDS.end - 1]
+        );
+        assert!(ids.truncated);
+        assert!(ids.inputs(&presets(&["d"])).is_empty());
+    }
+
+    #[test]
+    fn flash_dims_now_and_restores_when_asked() {
+        let config = r#"{ "flash_on_switch": true }"#;
+        with_config("flash", Some(config), |_| {
+            let backend = MockBackend::new(1);
+            backend.set("1", platform::VCP_BRIGHTNESS, 80);
+            let mut model = model(&backend);
+            let update = switch(&mut model, "1", 15);
+            assert_eq!(update.end_flash_after, Some(FLASH));
+            assert_eq!(backend.value("1", platform::VCP_BRIGHTNESS), Some(40));
+
+            model.end_flash();
+            assert_eq!(backend.value("1", platform::VCP_BRIGHTNESS), Some(80));
+            // A late second call has nothing left to restore.
+            model.end_flash();
+            assert_eq!(backend.writes().len(), 3);
+        });
+    }
+
+    #[test]
+    fn a_new_flash_restores_the_pending_one_first() {
+        let config = r#"{ "flash_on_switch": true, "tray_debounce_ms": 0 }"#;
+        with_config("flash-twice", Some(config), |_| {
+            let backend = MockBackend::new(1);
+            backend.set("1", platform::VCP_BRIGHTNESS, 80);
+            let mut model = model(&backend);
+            switch(&mut model, "1", 15);
+            switch(&mut model, "1", 17);
+            model.end_flash();
+            assert_eq!(backend.value("1", platform::VCP_BRIGHTNESS), Some(80));
+        });
+    }
+
+    #[test]
+    fn no_flash_without_a_readable_brightness() {
+        let config = r#"{ "flash_on_switch": true }"#;
+        with_config("flash-unreadable", Some(config), |_| {
+            let backend = MockBackend::new(1);
+            let mut model = model(&backend);
+            let update = switch(&mut model, "1", 15);
+            assert_eq!(update.end_flash_after, None);
+            assert_eq!(backend.writes().len(), 1);
+        });
+    }
+
+    /// Titles of the input actions in `items`, submenus included, in menu order.
+    fn input_titles(items: &[MenuItem]) -> Vec<String> {
+        items
+            .iter()
+            .flat_map(|item| match item {
+                MenuItem::Action { id, title, .. } if CMD_INPUT_IDS.contains(id) => {
+                    vec![title.clone()]
+                }
+                MenuItem::Submenu { items, .. } => input_titles(items),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn configured_icons_prefix_their_input_labels() {
+        let config = r#"{
+            "inputs": { "dp1": 15, "usb_c": 26, "hdmi1": 17 },
+            "inputs_meta": {
+                "usb_c": { "icon": "🔌" },
+                "hdmi1": { "icon": "  ", "label": "Console" },
+                "dp2": { "icon": "🖥" }
+            }
+        }"#;
+        with_config("icons", Some(config), |_| {
+            let model = model(&MockBackend::new(1));
+            let mut titles = input_titles(&model.menu_spec().items);
+            titles.sort();
+            assert_eq!(
+                titles,
+                ["Console (17)", "DisplayPort 1 (15)", "🔌 USB-C (26)"]
+            );
+        });
+    }
+
+    #[test]
+    fn builtin_defaults_can_be_turned_off() {
+        let headers = |items: &[MenuItem]| {
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    MenuItem::Header(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let config = r#"{ "use_builtin_default_inputs": false }"#;
+        with_config("no-defaults", Some(config), |_| {
+            let spec = model(&MockBackend::new(1)).menu_spec();
+            assert!(input_titles(&spec.items).is_empty());
+            assert!(headers(&spec.items)
+                .contains(&"No inputs configured \u{2014} edit config".to_string()));
+        });
+        with_config("defaults", Some("{}"), |_| {
+            let spec = model(&MockBackend::new(1)).menu_spec();
+            assert_eq!(
+                input_titles(&spec.items).len(),
+                config::DEFAULT_INPUTS.len()
+            );
+            assert!(headers(&spec.items)
+                .iter()
+                .any(|h| h.starts_with("Standard defaults")));
+        });
+    }
+
+    #[test]
+    fn recent_inputs_are_newest_first_capped_and_saved() {
+        let config = r#"{ "inputs": { "dp1": 15, "hdmi1": 17, "usb_c": 27, "pc": 18 }, "tray_debounce_ms": 0 }"#;
+        with_config("recent", Some(config), |_| {
+            let mut model = model(&MockBackend::new(1));
+            for value in [15, 17, 27, 17, 18] {
+                switch(&mut model, "1", value);
+            }
+            let recent = |values: &[u16]| {
+                values
+                    .iter()
+                    .map(|&value| RecentInput {
+                        selector: "1".to_string(),
+                        value,
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(model.recent_inputs(), recent(&[18, 17, 27]));
+            assert_eq!(state::load().recent_inputs, recent(&[18, 17, 27]));
+
+            let Some(MenuItem::Submenu { title, items }) = model.recent_submenu(true) else {
+                panic!("no recent inputs submenu");
+            };
+            assert_eq!(title, "Recent inputs");
+            let titles = items
+                .iter()
+                .map(|item| match item {
+                    MenuItem::Action { id, title, .. } => (*id, title.as_str()),
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                titles,
+                [
+                    (CMD_BASE_RECENT, "pc (18)"),
+                    (CMD_BASE_RECENT + 1, "HDMI 1 (17)"),
+                    (CMD_BASE_RECENT + 2, "USB-C (27)"),
+                ]
+            );
+
+            // A restart picks the list back up.
+            let restarted = self::model(&MockBackend::new(1));
+            assert_eq!(restarted.recent_inputs(), recent(&[18, 17, 27]));
+        });
+    }
+
+    #[test]
+    fn failed_switches_are_not_remembered() {
+        with_config(
+            "recent-failed",
+            Some(r#"{ "inputs": { "dp1": 15 } }"#),
+            |_| {
+                let backend = MockBackend::new(1);
+                backend.fail("1");
+                let mut model = model(&backend);
+                switch(&mut model, "1", 15);
+                assert!(model.last_error().is_some());
+                assert!(model.recent_inputs().is_empty());
+                assert!(model.recent_submenu(true).is_none());
+            },
+        );
+    }
+
+    #[test]
+    fn presets_on_their_own_vcp_are_never_checked() {
+        let config = r#"{
+            "inputs": { "dp1": 15, "pip": { "vcp": 233, "value": 15 } },
+            "show_input_in_titlebar": true
+        }"#;
+        with_config("custom-vcp", Some(config), |_| {
+            let backend = MockBackend::new(1);
+            backend.set("1", platform::VCP_INPUT, 15);
+            let mut model = model(&backend);
+            model.refresh_active_inputs();
+            let checked = |model: &TrayModel| {
+                model
+                    .menu_spec()
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        MenuItem::Action {
+                            title,
+                            checked: true,
+                            ..
+                        } => Some(title.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(checked(&model), ["DisplayPort 1 (15)"]);
+            assert_eq!(model.status_title().as_deref(), Some("DisplayPort 1"));
+
+            // Switching the custom preset leaves the 0x60 state alone.
+            backend.set("1", platform::VCP_INPUT, 17);
+            model.refresh_active_inputs();
+            let cmd = Command::Input {
+                selector: "1".to_string(),
+                value: 15,
+                adjust: PresetAdjustments {
+                    vcp: Some(233),
+                    ..PresetAdjustments::default()
+                },
+            };
+            model.handle(cmd, &NoStartup).unwrap();
+            assert_eq!(backend.value("1", 233), Some(15));
+            assert!(checked(&model).iter().all(|t| !t.contains("(15)")));
+        });
+    }
 }
@@ -1,19 +1,12 @@
 use std::{
-    env,
-    ffi::c_void,
-    fs::OpenOptions,
-    io::Write,
-    os::fd::AsRawFd,
-    path::{Path, PathBuf},
-    process::Command as ProcessCommand,
-    sync::Once,
+    collections::HashMap, ffi::c_void, fs::OpenOptions, io::Write, os::fd::AsRawFd, sync::Once,
 };
 
 use anyhow::{anyhow, Context, Result};
 use cocoa::{
     appkit::{NSApp, NSApplication, NSApplicationActivationPolicyAccessory, NSStatusBar},
-    base::{id, nil},
-    foundation::{NSAutoreleasePool, NSInteger, NSString},
+    base::{id, nil, BOOL, YES},
+    foundation::{NSAutoreleasePool, NSInteger, NSPoint, NSString, NSUInteger},
 };
 use objc::{
     class,
@@ -27,12 +20,15 @@ use crate::tray::commands::{decode, Command};
 use crate::tray::menu::{MenuItem, MenuSpec};
 use crate::tray::model::{ModelUpdate, TrayModel};
 use crate::tray::startup::StartupManager;
+use crate::{platform, startup};
 
 const APP_NAME: &str = "monitorctl";
 
 const MENU_STATE_OFF: NSInteger = 0;
 const MENU_STATE_ON: NSInteger = 1;
 
+const NS_EVENT_TYPE_APPLICATION_DEFINED: NSInteger = 15;
+
 pub fn run() -> Result<()> {
     unsafe {
         // If the user launches the binary from a terminal (e.g. `cargo run --bin monitortray`),
@@ -55,6 +51,7 @@ pub fn run() -> Result<()> {
             .context("install status item")?;
         app_state.rebuild_menu().context("build menu")?;
         app_state.refresh_tooltip();
+        app_state.refresh_title();
 
         let update = app_state
             .model
@@ -62,13 +59,72 @@ pub fn run() -> Result<()> {
             .context("initial reload")?;
         app_state.apply_update(update)?;
 
+        install_termination_handlers();
+        let display_callback_ctx = target as *mut c_void;
+        display::CGDisplayRegisterReconfigurationCallback(
+            on_display_reconfigured,
+            display_callback_ctx,
+        );
+
         app.run();
+        display::CGDisplayRemoveReconfigurationCallback(
+            on_display_reconfigured,
+            display_callback_ctx,
+        );
         drop(app_state);
     }
 
     Ok(())
 }
 
+mod display {
+    use std::ffi::c_void;
+
+    pub type ReconfigurationCallback = extern "C" fn(u32, u32, *mut c_void);
+
+    /// Set on the first (pre-change) callback of each reconfiguration; nothing has changed yet.
+    pub const BEGIN_CONFIGURATION_FLAG: u32 = 1 << 0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        pub fn CGDisplayRegisterReconfigurationCallback(
+            callback: ReconfigurationCallback,
+            user_info: *mut c_void,
+        ) -> i32;
+        pub fn CGDisplayRemoveReconfigurationCallback(
+            callback: ReconfigurationCallback,
+            user_info: *mut c_void,
+        ) -> i32;
+    }
+}
+
+/// Seconds to wait for a burst of reconfiguration callbacks (one per display, per phase) to
+/// finish before reloading.
+const DISPLAY_CHANGE_DEBOUNCE_SECS: f64 = 1.5;
+
+/// Runs on the main thread. Each call pushes the pending `onDisplaysChanged:` back, so the
+/// reload happens once the displays have settled.
+extern "C" fn on_display_reconfigured(_display: u32, flags: u32, user_info: *mut c_void) {
+    if flags & display::BEGIN_CONFIGURATION_FLAG != 0 {
+        return;
+    }
+    unsafe {
+        let target = user_info as id;
+        let _: () = msg_send![
+            class!(NSObject),
+            cancelPreviousPerformRequestsWithTarget: target
+            selector: sel!(onDisplaysChanged:)
+            object: nil
+        ];
+        let _: () = msg_send![
+            target,
+            performSelector: sel!(onDisplaysChanged:)
+            withObject: nil
+            afterDelay: DISPLAY_CHANGE_DEBOUNCE_SECS
+        ];
+    }
+}
+
 fn detach_from_terminal() {
     unsafe {
         // Ignore SIGHUP (terminal close).
@@ -100,6 +156,72 @@ fn detach_from_terminal() {
     }
 }
 
+/// Stops the run loop on SIGTERM (`launchctl bootout`, logout/shutdown) and SIGINT so `run`
+/// returns and `MacApp` is dropped normally instead of the process dying mid-flight.
+fn install_termination_handlers() {
+    unsafe {
+        for signal in [libc::SIGTERM, libc::SIGINT] {
+            // Dispatch signal sources only fire once the default disposition is ignored.
+            libc::signal(signal, libc::SIG_IGN);
+            let source = dispatch::dispatch_source_create(
+                std::ptr::addr_of!(dispatch::_dispatch_source_type_signal) as *const c_void,
+                signal as usize,
+                0,
+                std::ptr::addr_of!(dispatch::_dispatch_main_q) as *mut c_void,
+            );
+            if source.is_null() {
+                continue;
+            }
+            // The handler runs on the main queue, i.e. on the AppKit thread.
+            dispatch::dispatch_source_set_event_handler_f(source, on_termination_signal);
+            dispatch::dispatch_resume(source);
+        }
+    }
+}
+
+extern "C" fn on_termination_signal(_context: *mut c_void) {
+    unsafe {
+        let app = NSApp();
+        let _: () = msg_send![app, stop: nil];
+
+        // `stop:` only takes effect after the next event is processed; post a no-op one so
+        // `run` returns right away.
+        let event: id = msg_send![class!(NSEvent),
+            otherEventWithType: NS_EVENT_TYPE_APPLICATION_DEFINED
+            location: NSPoint::new(0.0, 0.0)
+            modifierFlags: 0 as NSUInteger
+            timestamp: 0.0f64
+            windowNumber: 0 as NSInteger
+            context: nil
+            subtype: 0i16
+            data1: 0 as NSInteger
+            data2: 0 as NSInteger];
+        let _: () = msg_send![app, postEvent: event atStart: true];
+    }
+}
+
+mod dispatch {
+    use std::ffi::c_void;
+
+    // Minimal libdispatch bindings (libSystem is always linked on macOS).
+    extern "C" {
+        pub static _dispatch_main_q: u8;
+        pub static _dispatch_source_type_signal: u8;
+
+        pub fn dispatch_source_create(
+            source_type: *const c_void,
+            handle: usize,
+            mask: usize,
+            queue: *mut c_void,
+        ) -> *mut c_void;
+        pub fn dispatch_source_set_event_handler_f(
+            source: *mut c_void,
+            handler: extern "C" fn(*mut c_void),
+        );
+        pub fn dispatch_resume(object: *mut c_void);
+    }
+}
+
 struct MacTrayUi {
     status_item: Option<id>,
     menu: Option<id>,
@@ -111,12 +233,10 @@ impl MacTrayUi {
         unsafe {
             let status_item: id =
                 msg_send![NSStatusBar::systemStatusBar(nil), statusItemWithLength: -1.0];
-            let button: id = msg_send![status_item, button];
-            let title = nsstring(APP_NAME);
-            let _: () = msg_send![button, setTitle: title];
 
             self.status_item = Some(status_item);
             self.target = Some(target);
+            self.set_title(APP_NAME);
             self.set_tooltip(APP_NAME);
         }
 
@@ -125,32 +245,10 @@ impl MacTrayUi {
 
     fn rebuild_menu(&mut self, target: id, spec: &MenuSpec) -> Result<()> {
         unsafe {
-            let menu: id = msg_send![class!(NSMenu), alloc];
-            let menu: id = msg_send![menu, initWithTitle: nsstring(APP_NAME)];
-
-            for item in &spec.items {
-                match item {
-                    MenuItem::Header(title) => add_header(menu, title),
-                    MenuItem::Separator => {
-                        let sep: id = msg_send![class!(NSMenuItem), separatorItem];
-                        let _: () = msg_send![menu, addItem: sep];
-                    }
-                    MenuItem::Action {
-                        id,
-                        title,
-                        checked,
-                        enabled,
-                    } => add_action_item(
-                        menu,
-                        title,
-                        sel!(onMenuItem:),
-                        target,
-                        *id as NSInteger,
-                        Some(*checked),
-                        *enabled,
-                    ),
-                }
-            }
+            let menu = new_menu(APP_NAME);
+            add_items(menu, target, &spec.items);
+            // `menuWillOpen:` refreshes the input checkmarks each time the menu is opened.
+            let _: () = msg_send![menu, setDelegate: target];
 
             if let Some(status_item) = self.status_item {
                 let _: () = msg_send![status_item, setMenu: menu];
@@ -161,6 +259,18 @@ impl MacTrayUi {
         Ok(())
     }
 
+    fn set_title(&mut self, text: &str) {
+        unsafe {
+            let status_item = match self.status_item {
+                Some(s) => s,
+                None => return,
+            };
+            let button: id = msg_send![status_item, button];
+            let title = nsstring(text);
+            let _: () = msg_send![button, setTitle: title];
+        }
+    }
+
     fn set_tooltip(&mut self, text: &str) {
         unsafe {
             let status_item = match self.status_item {
@@ -174,15 +284,26 @@ impl MacTrayUi {
     }
 }
 
+impl Drop for MacTrayUi {
+    fn drop(&mut self) {
+        if let Some(status_item) = self.status_item.take() {
+            unsafe {
+                let status_bar = NSStatusBar::systemStatusBar(nil);
+                let _: () = msg_send![status_bar, removeStatusItem: status_item];
+            }
+        }
+    }
+}
+
 struct MacStartupManager;
 
 impl StartupManager for MacStartupManager {
     fn is_enabled(&self) -> Result<bool> {
-        autostart::is_enabled().context("read launch agent")
+        startup::is_enabled()
     }
 
     fn set_enabled(&self, enabled: bool) -> Result<()> {
-        autostart::set_enabled(enabled).context("update launch agent")
+        startup::set_enabled(enabled)
     }
 }
 
@@ -213,15 +334,37 @@ impl MacApp {
         self.ui.rebuild_menu(target, &spec)
     }
 
+    /// Re-reads the inputs and updates the checkmarks of the menu that is about to open. The
+    /// menu can't be swapped out at that point, so its items are updated in place.
+    fn refresh_checkmarks(&mut self) {
+        self.model.refresh_active_inputs();
+        let Some(menu) = self.ui.menu else {
+            return;
+        };
+        let mut checked = HashMap::new();
+        collect_checked(&self.model.menu_spec().items, &mut checked);
+        unsafe { set_checkmarks(menu, &checked) };
+    }
+
     fn refresh_tooltip(&mut self) {
         let tip = self.model.last_error().unwrap_or(APP_NAME);
         self.ui.set_tooltip(tip);
     }
 
+    fn refresh_title(&mut self) {
+        let title = self.model.status_title();
+        self.ui.set_title(title.as_deref().unwrap_or(APP_NAME));
+    }
+
     fn handle_menu_click(&mut self, cmd_id: u16) -> Result<()> {
-        let Some(cmd) = decode(cmd_id, self.model.inputs()) else {
+        let Some(cmd) = decode(cmd_id, self.model.targets(), self.model.recent_inputs()) else {
             return Ok(());
         };
+        if let Some(prompt) = self.model.confirmation_prompt(&cmd) {
+            if !unsafe { confirm(&prompt) } {
+                return Ok(());
+            }
+        }
 
         let update = self.model.handle(cmd, &self.startup)?;
         self.apply_update(update)
@@ -229,21 +372,59 @@ impl MacApp {
 
     fn apply_update(&mut self, update: ModelUpdate) -> Result<()> {
         if let Some(path) = update.open_path {
-            if let Err(err) = shell_open(&path) {
+            if let Err(err) = platform::open_path(&path) {
                 let update = self.model.note_error(err);
                 self.apply_update(update)?;
                 return Ok(());
             }
         }
 
+        if let Some(message) = update.show_message {
+            unsafe { show_message(&message) };
+        }
+
+        if let Some(text) = update.notification {
+            unsafe { post_notification(&text) };
+        }
+
+        if let Some(text) = update.copy_text {
+            unsafe { copy_to_pasteboard(&text) };
+        }
+
         if update.refresh_menu {
             self.rebuild_menu()?;
         }
 
+        if let (Some(after), Some(target)) = (update.refresh_menu_after, self.ui.target) {
+            unsafe {
+                let _: () = msg_send![
+                    target,
+                    performSelector: sel!(onCooldownEnded:)
+                    withObject: nil
+                    afterDelay: after.as_secs_f64()
+                ];
+            }
+        }
+
+        if let (Some(after), Some(target)) = (update.end_flash_after, self.ui.target) {
+            unsafe {
+                let _: () = msg_send![
+                    target,
+                    performSelector: sel!(onFlashEnded:)
+                    withObject: nil
+                    afterDelay: after.as_secs_f64()
+                ];
+            }
+        }
+
         if update.refresh_tooltip {
             self.refresh_tooltip();
         }
 
+        if update.refresh_title {
+            self.refresh_title();
+        }
+
         if update.quit {
             unsafe {
                 let app = NSApp();
@@ -259,6 +440,100 @@ unsafe fn nsstring(s: &str) -> id {
     NSString::alloc(nil).init_str(s)
 }
 
+/// Modal Switch/Cancel `NSAlert`; true if the user picked Switch.
+unsafe fn confirm(prompt: &str) -> bool {
+    // NSAlertFirstButtonReturn
+    const FIRST_BUTTON: NSInteger = 1000;
+
+    // An accessory app isn't frontmost, so bring it forward or the alert opens behind windows.
+    let app = NSApp();
+    let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+
+    let alert: id = msg_send![class!(NSAlert), new];
+    let _: () = msg_send![alert, setMessageText: nsstring(prompt)];
+    let _: id = msg_send![alert, addButtonWithTitle: nsstring("Switch")];
+    let _: id = msg_send![alert, addButtonWithTitle: nsstring("Cancel")];
+    let response: NSInteger = msg_send![alert, runModal];
+    let _: () = msg_send![alert, release];
+    response == FIRST_BUTTON
+}
+
+unsafe fn show_message(message: &str) {
+    let app = NSApp();
+    let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+
+    let alert: id = msg_send![class!(NSAlert), new];
+    let _: () = msg_send![alert, setMessageText: nsstring(message)];
+    let _: id = msg_send![alert, addButtonWithTitle: nsstring("OK")];
+    let _: NSInteger = msg_send![alert, runModal];
+    let _: () = msg_send![alert, release];
+}
+
+/// Posts `text` to Notification Center. `NSUserNotification` is deprecated but, unlike
+/// `UNUserNotificationCenter`, needs no permission prompt or completion-handler blocks.
+unsafe fn post_notification(text: &str) {
+    let notification: id = msg_send![class!(NSUserNotification), new];
+    let _: () = msg_send![notification, setTitle: nsstring(APP_NAME)];
+    let _: () = msg_send![notification, setInformativeText: nsstring(text)];
+    let center: id = msg_send![
+        class!(NSUserNotificationCenter),
+        defaultUserNotificationCenter
+    ];
+    let _: () = msg_send![center, deliverNotification: notification];
+    let _: () = msg_send![notification, release];
+}
+
+unsafe fn copy_to_pasteboard(text: &str) {
+    let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+    let _: NSInteger = msg_send![pasteboard, clearContents];
+    let _: BOOL = msg_send![
+        pasteboard,
+        setString: nsstring(text)
+        forType: nsstring("public.utf8-plain-text")
+    ];
+}
+
+unsafe fn new_menu(title: &str) -> id {
+    let menu: id = msg_send![class!(NSMenu), alloc];
+    msg_send![menu, initWithTitle: nsstring(title)]
+}
+
+unsafe fn add_items(menu: id, target: id, items: &[MenuItem]) {
+    for item in items {
+        match item {
+            MenuItem::Header(title) => add_header(menu, title),
+            MenuItem::Separator => {
+                let sep: id = msg_send![class!(NSMenuItem), separatorItem];
+                let _: () = msg_send![menu, addItem: sep];
+            }
+            MenuItem::Submenu { title, items } => {
+                let submenu = new_menu(title);
+                add_items(submenu, target, items);
+
+                let item: id = msg_send![class!(NSMenuItem), alloc];
+                let empty = nsstring("");
+                let item: id = msg_send![item, initWithTitle: nsstring(title) action: nil keyEquivalent: empty];
+                let _: () = msg_send![item, setSubmenu: submenu];
+                let _: () = msg_send![menu, addItem: item];
+            }
+            MenuItem::Action {
+                id,
+                title,
+                checked,
+                enabled,
+            } => add_action_item(
+                menu,
+                title,
+                sel!(onMenuItem:),
+                target,
+                *id as NSInteger,
+                Some(*checked),
+                *enabled,
+            ),
+        }
+    }
+}
+
 unsafe fn add_header(menu: id, title: &str) {
     let item: id = msg_send![class!(NSMenuItem), alloc];
     let title = nsstring(title);
@@ -298,6 +573,35 @@ unsafe fn add_action_item(
     let _: () = msg_send![menu, addItem: item];
 }
 
+fn collect_checked(items: &[MenuItem], out: &mut HashMap<NSInteger, bool>) {
+    for item in items {
+        match item {
+            MenuItem::Action { id, checked, .. } => {
+                out.insert(*id as NSInteger, *checked);
+            }
+            MenuItem::Submenu { items, .. } => collect_checked(items, out),
+            MenuItem::Header(_) | MenuItem::Separator => {}
+        }
+    }
+}
+
+unsafe fn set_checkmarks(menu: id, checked: &HashMap<NSInteger, bool>) {
+    let count: NSInteger = msg_send![menu, numberOfItems];
+    for i in 0..count {
+        let item: id = msg_send![menu, itemAtIndex: i];
+        let submenu: id = msg_send![item, submenu];
+        if submenu != nil {
+            set_checkmarks(submenu, checked);
+            continue;
+        }
+        let tag: NSInteger = msg_send![item, tag];
+        if let Some(&on) = checked.get(&tag) {
+            let state = if on { MENU_STATE_ON } else { MENU_STATE_OFF };
+            let _: () = msg_send![item, setState: state];
+        }
+    }
+}
+
 fn target_class() -> *const Class {
     static ONCE: Once = Once::new();
     static mut CLS: *const Class = std::ptr::null();
@@ -311,6 +615,22 @@ fn target_class() -> *const Class {
             sel!(onMenuItem:),
             on_menu_item as extern "C" fn(&Object, Sel, id),
         );
+        decl.add_method(
+            sel!(onDisplaysChanged:),
+            on_displays_changed as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(onCooldownEnded:),
+            on_cooldown_ended as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(onFlashEnded:),
+            on_flash_ended as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(menuWillOpen:),
+            on_menu_will_open as extern "C" fn(&Object, Sel, id),
+        );
         CLS = decl.register();
     });
 
@@ -345,145 +665,68 @@ extern "C" fn on_menu_item(this: &Object, _cmd: Sel, sender: id) {
     }
 }
 
-fn log_to_tmp(prefix: &str, msg: &str) {
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/tmp/monitortray.err")
-    {
-        let _ = writeln!(f, "{prefix}: {msg}");
+extern "C" fn on_displays_changed(this: &Object, _cmd: Sel, _arg: id) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar("state_ptr");
+        if state_ptr.is_null() {
+            return;
+        }
+        let app = &mut *(state_ptr as *mut MacApp);
+        let result = app
+            .model
+            .handle(Command::Reload, &app.startup)
+            .and_then(|update| app.apply_update(update));
+        if let Err(err) = result {
+            log_to_tmp("monitortray error", &err.to_string());
+            let update = app.model.note_error(err);
+            let _ = app.apply_update(update);
+        }
     }
 }
 
-fn shell_open(path: &Path) -> Result<()> {
-    let status = ProcessCommand::new("open")
-        .arg(path)
-        .status()
-        .with_context(|| format!("running open {}", path.display()))?;
-    if !status.success() {
-        return Err(anyhow!("open failed (exit={status})"));
+extern "C" fn on_menu_will_open(this: &Object, _cmd: Sel, _menu: id) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar("state_ptr");
+        if state_ptr.is_null() {
+            return;
+        }
+        let app = &mut *(state_ptr as *mut MacApp);
+        app.refresh_checkmarks();
     }
-    Ok(())
 }
 
-mod autostart {
-    use super::*;
-    use std::fs;
-
-    const LABEL: &str = "com.monitorctl.monitorctl";
-
-    pub fn is_enabled() -> Result<bool> {
-        Ok(plist_path()?.exists())
-    }
-
-    pub fn set_enabled(enabled: bool) -> Result<()> {
-        if enabled {
-            install()?;
-        } else {
-            uninstall()?;
+extern "C" fn on_cooldown_ended(this: &Object, _cmd: Sel, _arg: id) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar("state_ptr");
+        if state_ptr.is_null() {
+            return;
         }
-        Ok(())
-    }
-
-    fn install() -> Result<()> {
-        let path = plist_path()?;
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+        let app = &mut *(state_ptr as *mut MacApp);
+        if let Err(err) = app.rebuild_menu() {
+            log_to_tmp("monitortray error", &err.to_string());
+            let update = app.model.note_error(err);
+            let _ = app.apply_update(update);
         }
-
-        let exe = std::env::current_exe().context("current_exe")?;
-        let plist = launch_agent_plist(&exe, LABEL);
-        fs::write(&path, plist.as_bytes()).with_context(|| format!("write {}", path.display()))?;
-
-        let uid = gui_uid();
-        let domain = format!("gui/{uid}");
-
-        // Best-effort: unload any previous instance, then load.
-        let _ = launchctl(&["bootout", &domain, &path.to_string_lossy()]);
-        launchctl(&["bootstrap", &domain, &path.to_string_lossy()])
-            .context("launchctl bootstrap")?;
-        let _ = launchctl(&["enable", &format!("{domain}/{LABEL}")]);
-
-        Ok(())
     }
+}
 
-    fn uninstall() -> Result<()> {
-        let path = plist_path()?;
-        if !path.exists() {
-            return Ok(());
+extern "C" fn on_flash_ended(this: &Object, _cmd: Sel, _arg: id) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar("state_ptr");
+        if state_ptr.is_null() {
+            return;
         }
-
-        let uid = gui_uid();
-        let domain = format!("gui/{uid}");
-        let _ = launchctl(&["disable", &format!("{domain}/{LABEL}")]);
-        let _ = launchctl(&["bootout", &domain, &path.to_string_lossy()]);
-
-        fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
-        Ok(())
+        let app = &mut *(state_ptr as *mut MacApp);
+        app.model.end_flash();
     }
+}
 
-    fn plist_path() -> Result<PathBuf> {
-        let home = env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
-        Ok(PathBuf::from(home)
-            .join("Library")
-            .join("LaunchAgents")
-            .join(format!("{LABEL}.plist")))
-    }
-
-    fn gui_uid() -> u32 {
-        // LaunchAgents don't always inherit a useful env, so shell out to `id -u`.
-        ProcessCommand::new("id")
-            .arg("-u")
-            .output()
-            .ok()
-            .and_then(|out| {
-                if !out.status.success() {
-                    return None;
-                }
-                String::from_utf8(out.stdout)
-                    .ok()
-                    .and_then(|s| s.trim().parse::<u32>().ok())
-            })
-            .unwrap_or(0)
-    }
-
-    fn launch_agent_plist(exe: &Path, label: &str) -> String {
-        let exe = exe.display().to_string();
-        format!(
-            r#"<?xml version=\"1.0\" encoding=\"UTF-8\"?>
-<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
-<plist version=\"1.0\">
-<dict>
-  <key>Label</key><string>{label}</string>
-  <key>ProgramArguments</key>
-  <array>
-    <string>{exe}</string>
-  </array>
-  <key>RunAtLoad</key><true/>
-  <key>ProcessType</key><string>Interactive</string>
-  <key>StandardOutPath</key><string>/tmp/monitortray.out</string>
-  <key>StandardErrorPath</key><string>/tmp/monitortray.err</string>
-</dict>
-</plist>
-"#
-        )
-    }
-
-    fn launchctl(args: &[&str]) -> Result<()> {
-        let out = ProcessCommand::new("launchctl")
-            .args(args)
-            .output()
-            .with_context(|| format!("running launchctl {}", args.join(" ")))?;
-        if !out.status.success() {
-            let stderr = String::from_utf8_lossy(&out.stderr);
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            return Err(anyhow!(
-                "launchctl failed (exit={}):\nstdout:\n{}\nstderr:\n{}",
-                out.status,
-                stdout.trim(),
-                stderr.trim()
-            ));
-        }
-        Ok(())
+fn log_to_tmp(prefix: &str, msg: &str) {
+    if let Ok(mut f) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("/tmp/monitortray.err")
+    {
+        let _ = writeln!(f, "{prefix}: {msg}");
     }
 }
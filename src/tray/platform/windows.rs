@@ -1,44 +1,58 @@
-use std::{mem::size_of, path::Path};
+use std::mem::size_of;
 
 use anyhow::{anyhow, Context, Result};
 use windows::{
     core::{w, Error as WinError, PCWSTR},
     Win32::{
-        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM},
+        Foundation::{GlobalFree, HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM},
         Graphics::Gdi::{
             CreateBitmap, CreateDIBSection, DeleteObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
             DIB_RGB_COLORS, HBITMAP,
         },
-        System::LibraryLoader::GetModuleHandleW,
-        System::Registry::{
-            RegDeleteKeyValueW, RegGetValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ,
-            RRF_RT_REG_SZ,
+        System::{
+            DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData},
+            LibraryLoader::GetModuleHandleW,
+            Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+            Ole::CF_UNICODETEXT,
         },
         UI::{
             Shell::{
-                ShellExecuteW, Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD,
+                Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO, NIM_ADD,
                 NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW, NOTIFY_ICON_MESSAGE,
             },
             WindowsAndMessaging::{
                 AppendMenuW, CreateIconIndirect, CreatePopupMenu, DefWindowProcW, DestroyMenu,
-                DispatchMessageW, GetCursorPos, GetMessageW, LoadIconW, PostQuitMessage,
-                RegisterClassW, SetForegroundWindow, TrackPopupMenu, TranslateMessage,
-                CREATESTRUCTW, HMENU, ICONINFO, MF_CHECKED, MF_DISABLED, MF_GRAYED, MF_SEPARATOR,
-                MF_STRING, MF_UNCHECKED, MSG, SW_SHOWNORMAL, TPM_BOTTOMALIGN, TPM_LEFTALIGN,
-                TPM_RETURNCMD, TPM_RIGHTBUTTON, WM_LBUTTONUP, WM_NCCREATE, WM_RBUTTONUP, WM_USER,
-                WNDCLASSW, WS_OVERLAPPED,
+                DispatchMessageW, GetCursorPos, GetMessageW, KillTimer, LoadIconW, MessageBoxW,
+                PostQuitMessage, RegisterClassW, SetForegroundWindow, SetTimer, TrackPopupMenu,
+                TranslateMessage, CREATESTRUCTW, HMENU, ICONINFO, IDYES, MB_ICONINFORMATION,
+                MB_ICONQUESTION, MB_OK, MB_SETFOREGROUND, MB_YESNO, MF_CHECKED, MF_DISABLED,
+                MF_GRAYED, MF_POPUP, MF_SEPARATOR, MF_STRING, MF_UNCHECKED, MSG, TPM_BOTTOMALIGN,
+                TPM_LEFTALIGN, TPM_RETURNCMD, TPM_RIGHTBUTTON, WM_DEVICECHANGE, WM_DISPLAYCHANGE,
+                WM_LBUTTONUP, WM_NCCREATE, WM_RBUTTONUP, WM_TIMER, WM_USER, WNDCLASSW,
+                WS_OVERLAPPED,
             },
         },
     },
 };
 
 use crate::tray::commands::{decode, Command};
-use crate::tray::menu::{MenuItem, MenuSpec};
+use crate::tray::menu::{mnemonic_labels, MenuItem, MenuSpec};
 use crate::tray::model::{ModelUpdate, TrayModel};
 use crate::tray::startup::StartupManager;
+use crate::{platform, startup};
 
 const WM_TRAYICON: u32 = WM_USER + 1;
 
+/// Timer that coalesces display-change bursts (docking fires several) into one reload.
+const DISPLAY_CHANGE_TIMER: usize = 1;
+/// Rebuilds the menu when the post-switch cooldown ends.
+const COOLDOWN_TIMER: usize = 2;
+/// Restores the brightness `flash_on_switch` dimmed.
+const FLASH_TIMER: usize = 3;
+const DISPLAY_CHANGE_DEBOUNCE_MS: u32 = 1500;
+/// `wParam` of `WM_DEVICECHANGE` broadcast when a device (e.g. a monitor) is added or removed.
+const DBT_DEVNODES_CHANGED: usize = 0x0007;
+
 const TOOLTIP_DEFAULT: &str = "monitortray";
 
 pub fn run() -> Result<()> {
@@ -118,44 +132,7 @@ impl WinTrayUi {
         }
 
         let menu = unsafe { CreatePopupMenu() }.context("CreatePopupMenu")?;
-
-        for item in &spec.items {
-            match item {
-                MenuItem::Header(title) => unsafe {
-                    let wtitle = wide(title);
-                    AppendMenuW(
-                        menu,
-                        MF_STRING | MF_DISABLED | MF_GRAYED,
-                        0,
-                        PCWSTR::from_raw(wtitle.as_ptr()),
-                    )
-                    .context("AppendMenuW(header)")?;
-                },
-                MenuItem::Separator => unsafe {
-                    AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null())
-                        .context("AppendMenuW(separator)")?;
-                },
-                MenuItem::Action {
-                    id,
-                    title,
-                    checked,
-                    enabled,
-                } => unsafe {
-                    let mut flags = MF_STRING;
-                    if *checked {
-                        flags |= MF_CHECKED;
-                    } else {
-                        flags |= MF_UNCHECKED;
-                    }
-                    if !*enabled {
-                        flags |= MF_DISABLED | MF_GRAYED;
-                    }
-                    let wtitle = wide(title);
-                    AppendMenuW(menu, flags, *id as usize, PCWSTR::from_raw(wtitle.as_ptr()))
-                        .context("AppendMenuW(action)")?;
-                },
-            }
-        }
+        append_items(menu, &spec.items)?;
 
         self.menu = Some(menu);
         Ok(())
@@ -189,6 +166,18 @@ impl WinTrayUi {
         Ok(())
     }
 
+    /// Shows `text` as a balloon (a toast on Windows 10+) from the tray icon.
+    fn notify(&self, text: &str) {
+        let mut nid = self.tray;
+        nid.uFlags = NIF_INFO;
+        nid.szInfoTitle = utf16_truncated(TOOLTIP_DEFAULT);
+        nid.szInfo = utf16_truncated(text);
+        nid.dwInfoFlags = NIIF_INFO;
+        unsafe {
+            let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+        }
+    }
+
     fn set_tooltip(&mut self, text: &str) {
         write_tip(&mut self.tray, text);
         unsafe {
@@ -230,15 +219,81 @@ impl WinTrayUi {
     }
 }
 
+fn append_items(menu: HMENU, items: &[MenuItem]) -> Result<()> {
+    // Mnemonics are assigned per menu level, so every item gets a unique one among siblings.
+    let titles = items
+        .iter()
+        .map(|item| match item {
+            MenuItem::Submenu { title, .. } | MenuItem::Action { title, .. } => title.as_str(),
+            MenuItem::Header(_) | MenuItem::Separator => "",
+        })
+        .collect::<Vec<_>>();
+    let labels = mnemonic_labels(&titles);
+
+    for (item, label) in items.iter().zip(&labels) {
+        match item {
+            MenuItem::Header(title) => unsafe {
+                // Headers are disabled, so they only need `&` escaped.
+                let wtitle = wide(&title.replace('&', "&&"));
+                AppendMenuW(
+                    menu,
+                    MF_STRING | MF_DISABLED | MF_GRAYED,
+                    0,
+                    PCWSTR::from_raw(wtitle.as_ptr()),
+                )
+                .context("AppendMenuW(header)")?;
+            },
+            MenuItem::Separator => unsafe {
+                AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null())
+                    .context("AppendMenuW(separator)")?;
+            },
+            MenuItem::Submenu { items, .. } => unsafe {
+                // The submenu is owned by (and destroyed with) its parent menu.
+                let submenu = CreatePopupMenu().context("CreatePopupMenu(submenu)")?;
+                append_items(submenu, items)?;
+                let wtitle = wide(label);
+                AppendMenuW(
+                    menu,
+                    MF_STRING | MF_POPUP,
+                    submenu.0 as usize,
+                    PCWSTR::from_raw(wtitle.as_ptr()),
+                )
+                .context("AppendMenuW(submenu)")?;
+            },
+            MenuItem::Action {
+                id,
+                checked,
+                enabled,
+                ..
+            } => unsafe {
+                let mut flags = MF_STRING;
+                if *checked {
+                    flags |= MF_CHECKED;
+                } else {
+                    flags |= MF_UNCHECKED;
+                }
+                if !*enabled {
+                    flags |= MF_DISABLED | MF_GRAYED;
+                }
+                let wtitle = wide(label);
+                AppendMenuW(menu, flags, *id as usize, PCWSTR::from_raw(wtitle.as_ptr()))
+                    .context("AppendMenuW(action)")?;
+            },
+        }
+    }
+
+    Ok(())
+}
+
 struct WinStartupManager;
 
 impl StartupManager for WinStartupManager {
     fn is_enabled(&self) -> Result<bool> {
-        autostart::is_enabled().context("read registry startup entry")
+        startup::is_enabled()
     }
 
     fn set_enabled(&self, enabled: bool) -> Result<()> {
-        autostart::set_enabled(enabled).context("update registry startup entry")
+        startup::set_enabled(enabled)
     }
 }
 
@@ -272,14 +327,22 @@ impl WinApp {
     }
 
     fn handle_menu_click(&mut self) -> Result<()> {
+        // The inputs may have changed outside the tray since the menu was built.
+        self.model.refresh_active_inputs();
+        self.rebuild_menu()?;
         let cmd_id = self.ui.show_menu()?;
         if cmd_id == 0 {
             return Ok(());
         }
 
-        let Some(cmd) = decode(cmd_id, self.model.inputs()) else {
+        let Some(cmd) = decode(cmd_id, self.model.targets(), self.model.recent_inputs()) else {
             return Ok(());
         };
+        if let Some(prompt) = self.model.confirmation_prompt(&cmd) {
+            if !confirm(self.ui.hwnd()?, &prompt) {
+                return Ok(());
+            }
+        }
 
         let update = self.model.handle(cmd, &self.startup)?;
         self.apply_update(update)
@@ -287,7 +350,8 @@ impl WinApp {
 
     fn apply_update(&mut self, update: ModelUpdate) -> Result<()> {
         if let Some(path) = update.open_path {
-            if let Err(err) = shell_open(&path).with_context(|| format!("open {}", path.display()))
+            if let Err(err) =
+                platform::open_path(&path).with_context(|| format!("open {}", path.display()))
             {
                 let update = self.model.note_error(err);
                 self.apply_update(update)?;
@@ -295,10 +359,48 @@ impl WinApp {
             }
         }
 
+        if let Some(message) = update.show_message {
+            show_message(self.ui.hwnd()?, &message);
+        }
+
+        if let Some(text) = update.notification {
+            self.ui.notify(&text);
+        }
+
+        if let Some(text) = update.copy_text {
+            if let Err(err) = copy_to_clipboard(self.ui.hwnd()?, &text) {
+                let update = self.model.note_error(err.context("copy to clipboard"));
+                self.apply_update(update)?;
+                return Ok(());
+            }
+        }
+
         if update.refresh_menu {
             self.rebuild_menu()?;
         }
 
+        if let Some(after) = update.refresh_menu_after {
+            unsafe {
+                SetTimer(
+                    Some(self.ui.hwnd()?),
+                    COOLDOWN_TIMER,
+                    after.as_millis().try_into().unwrap_or(u32::MAX),
+                    None,
+                );
+            }
+        }
+
+        if let Some(after) = update.end_flash_after {
+            unsafe {
+                SetTimer(
+                    Some(self.ui.hwnd()?),
+                    FLASH_TIMER,
+                    after.as_millis().try_into().unwrap_or(u32::MAX),
+                    None,
+                );
+            }
+        }
+
         if update.refresh_tooltip {
             self.refresh_tooltip();
         }
@@ -348,6 +450,49 @@ unsafe extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam:
                 return LRESULT(0);
             }
         }
+        WM_DISPLAYCHANGE => {
+            schedule_display_reload(hwnd);
+        }
+        WM_DEVICECHANGE if wparam.0 == DBT_DEVNODES_CHANGED => {
+            schedule_display_reload(hwnd);
+        }
+        WM_TIMER if wparam.0 == DISPLAY_CHANGE_TIMER => {
+            let _ = KillTimer(Some(hwnd), DISPLAY_CHANGE_TIMER);
+            let app = get_app(hwnd);
+            if app.is_null() {
+                return LRESULT(0);
+            }
+            let app = &mut *app;
+            let result = app
+                .model
+                .handle(Command::Reload, &app.startup)
+                .and_then(|update| app.apply_update(update));
+            if let Err(err) = result {
+                let update = app.model.note_error(err);
+                let _ = app.apply_update(update);
+            }
+            return LRESULT(0);
+        }
+        WM_TIMER if wparam.0 == COOLDOWN_TIMER => {
+            let _ = KillTimer(Some(hwnd), COOLDOWN_TIMER);
+            let app = get_app(hwnd);
+            if !app.is_null() {
+                let app = &mut *app;
+                if let Err(err) = app.rebuild_menu() {
+                    let update = app.model.note_error(err);
+                    let _ = app.apply_update(update);
+                }
+            }
+            return LRESULT(0);
+        }
+        WM_TIMER if wparam.0 == FLASH_TIMER => {
+            let _ = KillTimer(Some(hwnd), FLASH_TIMER);
+            let app = get_app(hwnd);
+            if !app.is_null() {
+                (*app).model.end_flash();
+            }
+            return LRESULT(0);
+        }
         windows::Win32::UI::WindowsAndMessaging::WM_NCDESTROY => {
             let app = get_app(hwnd);
             if !app.is_null() {
@@ -365,6 +510,19 @@ unsafe extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam:
     DefWindowProcW(hwnd, msg, wparam, lparam)
 }
 
+/// (Re)arms the debounce timer; re-setting an existing timer restarts its countdown, so the
+/// reload runs once the burst of change messages has stopped.
+fn schedule_display_reload(hwnd: HWND) {
+    unsafe {
+        SetTimer(
+            Some(hwnd),
+            DISPLAY_CHANGE_TIMER,
+            DISPLAY_CHANGE_DEBOUNCE_MS,
+            None,
+        );
+    }
+}
+
 fn get_app(hwnd: HWND) -> *mut WinApp {
     unsafe {
         let ptr = windows::Win32::UI::WindowsAndMessaging::GetWindowLongPtrW(
@@ -375,134 +533,57 @@ fn get_app(hwnd: HWND) -> *mut WinApp {
     }
 }
 
-fn shell_open(path: &Path) -> Result<()> {
-    let path = path
-        .to_str()
-        .ok_or_else(|| anyhow!("Non-UTF-8 path: {}", path.display()))?;
-    let wpath = wide(path);
+fn confirm(hwnd: HWND, prompt: &str) -> bool {
+    let text = wide(prompt);
+    let caption = wide("monitortray");
+    let answer = unsafe {
+        MessageBoxW(
+            Some(hwnd),
+            PCWSTR::from_raw(text.as_ptr()),
+            PCWSTR::from_raw(caption.as_ptr()),
+            MB_YESNO | MB_ICONQUESTION | MB_SETFOREGROUND,
+        )
+    };
+    answer == IDYES
+}
+
+fn show_message(hwnd: HWND, message: &str) {
+    let text = wide(message);
+    let caption = wide("monitortray");
     unsafe {
-        let h = ShellExecuteW(
-            None,
-            w!("open"),
-            PCWSTR::from_raw(wpath.as_ptr()),
-            PCWSTR::null(),
-            PCWSTR::null(),
-            SW_SHOWNORMAL,
+        MessageBoxW(
+            Some(hwnd),
+            PCWSTR::from_raw(text.as_ptr()),
+            PCWSTR::from_raw(caption.as_ptr()),
+            MB_OK | MB_ICONINFORMATION | MB_SETFOREGROUND,
         );
-        // Per Win32 docs: values <= 32 indicate an error.
-        if (h.0 as isize) <= 32 {
-            return Err(anyhow!("ShellExecuteW failed ({})", h.0 as isize));
-        }
     }
-    Ok(())
 }
 
-mod autostart {
-    use super::*;
-    use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_PATH_NOT_FOUND, WIN32_ERROR};
-    use windows::Win32::System::Registry::REG_VALUE_TYPE;
-
-    const RUN_SUBKEY: PCWSTR = w!("Software\\Microsoft\\Windows\\CurrentVersion\\Run");
-    const VALUE_NAME: &str = "monitortray";
-    const OK: WIN32_ERROR = WIN32_ERROR(0);
-
-    pub fn is_enabled() -> Result<bool> {
-        Ok(read_value()?.is_some())
-    }
-
-    pub fn set_enabled(enabled: bool) -> Result<()> {
-        if enabled {
-            let cmd = autostart_command()?;
-            write_value(&cmd)?;
-        } else {
-            delete_value()?;
-        }
-        Ok(())
-    }
-
-    fn autostart_command() -> Result<String> {
-        let exe = std::env::current_exe().context("current_exe")?;
-        Ok(format!("\"{}\"", exe.display()))
-    }
-
-    fn read_value() -> Result<Option<String>> {
-        let value_name = wide(VALUE_NAME);
-        let mut typ = REG_VALUE_TYPE::default();
-        let mut bytes: u32 = 0;
-        let status = unsafe {
-            RegGetValueW(
-                HKEY_CURRENT_USER,
-                RUN_SUBKEY,
-                PCWSTR::from_raw(value_name.as_ptr()),
-                RRF_RT_REG_SZ,
-                Some(&mut typ as *mut REG_VALUE_TYPE),
-                None,
-                Some(&mut bytes),
-            )
-        };
-
-        if status == ERROR_FILE_NOT_FOUND {
-            return Ok(None);
-        }
-        if status != OK {
-            return Err(anyhow!("RegGetValueW(size) failed: {status:?}"));
-        }
-
-        let mut buf: Vec<u16> = vec![0u16; (bytes as usize / 2).max(1)];
-        let status = unsafe {
-            RegGetValueW(
-                HKEY_CURRENT_USER,
-                RUN_SUBKEY,
-                PCWSTR::from_raw(value_name.as_ptr()),
-                RRF_RT_REG_SZ,
-                Some(&mut typ as *mut REG_VALUE_TYPE),
-                Some(buf.as_mut_ptr() as *mut _),
-                Some(&mut bytes),
-            )
-        };
-        if status != OK {
-            return Err(anyhow!("RegGetValueW(data) failed: {status:?}"));
-        }
-
-        let len = (bytes as usize / 2).saturating_sub(1);
-        Ok(Some(String::from_utf16_lossy(&buf[..len])))
-    }
-
-    fn write_value(cmd: &str) -> Result<()> {
-        let value_name = wide(VALUE_NAME);
-        let cmd = wide(cmd);
-        let status = unsafe {
-            RegSetKeyValueW(
-                HKEY_CURRENT_USER,
-                RUN_SUBKEY,
-                PCWSTR::from_raw(value_name.as_ptr()),
-                REG_SZ.0,
-                Some(cmd.as_ptr() as *const _),
-                (cmd.len() * 2) as u32,
-            )
-        };
-        if status != OK {
-            return Err(anyhow!("RegSetKeyValueW failed: {status:?}"));
-        }
-        Ok(())
-    }
-
-    fn delete_value() -> Result<()> {
-        let value_name = wide(VALUE_NAME);
-        let status = unsafe {
-            RegDeleteKeyValueW(
-                HKEY_CURRENT_USER,
-                RUN_SUBKEY,
-                PCWSTR::from_raw(value_name.as_ptr()),
-            )
-        };
-        if status == ERROR_FILE_NOT_FOUND || status == ERROR_PATH_NOT_FOUND {
-            return Ok(());
-        }
-        if status != OK {
-            return Err(anyhow!("RegDeleteKeyValueW failed: {status:?}"));
-        }
-        Ok(())
+fn copy_to_clipboard(hwnd: HWND, text: &str) -> Result<()> {
+    let text = wide(text);
+    unsafe {
+        OpenClipboard(Some(hwnd)).context("OpenClipboard")?;
+        let result = (|| -> Result<()> {
+            EmptyClipboard().context("EmptyClipboard")?;
+            let bytes = text.len() * size_of::<u16>();
+            let mem = GlobalAlloc(GMEM_MOVEABLE, bytes).context("GlobalAlloc")?;
+            let dst = GlobalLock(mem) as *mut u16;
+            if dst.is_null() {
+                let _ = GlobalFree(Some(mem));
+                return Err(anyhow!(WinError::from_thread())).context("GlobalLock");
+            }
+            std::ptr::copy_nonoverlapping(text.as_ptr(), dst, text.len());
+            let _ = GlobalUnlock(mem);
+            // On success the clipboard owns `mem`.
+            if let Err(e) = SetClipboardData(CF_UNICODETEXT.0.into(), Some(HANDLE(mem.0))) {
+                let _ = GlobalFree(Some(mem));
+                return Err(anyhow!(e)).context("SetClipboardData");
+            }
+            Ok(())
+        })();
+        let _ = CloseClipboard();
+        result
     }
 }
 
@@ -513,12 +594,22 @@ fn wide(s: &str) -> Vec<u16> {
 }
 
 fn write_tip(nid: &mut NOTIFYICONDATAW, tip: &str) {
-    let tip = tip.chars().take(127).collect::<String>();
-    let mut buf = [0u16; 128];
-    for (i, c) in tip.encode_utf16().take(127).enumerate() {
-        buf[i] = c;
+    // `szTip` holds 128 UTF-16 code units including the NUL terminator.
+    nid.szTip = utf16_truncated(tip);
+}
+
+/// `text` as a NUL-terminated UTF-16 buffer of `N` units. Truncates by code units (not chars)
+/// and backs off one unit rather than leave a dangling high surrogate.
+fn utf16_truncated<const N: usize>(text: &str) -> [u16; N] {
+    let units = text.encode_utf16().collect::<Vec<_>>();
+    let mut len = units.len().min(N - 1);
+    if len < units.len() && (0xD800..=0xDBFF).contains(&units[len - 1]) {
+        len -= 1;
     }
-    nid.szTip = buf;
+
+    let mut buf = [0u16; N];
+    buf[..len].copy_from_slice(&units[..len]);
+    buf
 }
 
 fn shell_notify_icon(action: NOTIFY_ICON_MESSAGE, nid: &NOTIFYICONDATAW) -> Result<()> {
@@ -610,3 +701,42 @@ fn create_tray_icon() -> Result<windows::Win32::UI::WindowsAndMessaging::HICON>
         Ok(icon)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoded<const N: usize>(buf: &[u16; N]) -> String {
+        let len = buf.iter().position(|&u| u == 0).unwrap_or(N);
+        String::from_utf16(&buf[..len]).unwrap()
+    }
+
+    #[test]
+    fn short_text_is_copied_and_terminated() {
+        let buf = utf16_truncated::<8>("Dell");
+        assert_eq!(decoded(&buf), "Dell");
+        assert_eq!(buf[4..], [0; 4]);
+        assert_eq!(decoded(&utf16_truncated::<8>("")), "");
+    }
+
+    #[test]
+    fn long_text_keeps_room_for_the_terminator() {
+        let buf = utf16_truncated::<5>("HDMI 1");
+        assert_eq!(decoded(&buf), "HDMI");
+        assert_eq!(buf[4], 0);
+
+        // Exactly N - 1 units still fits.
+        assert_eq!(decoded(&utf16_truncated::<5>("USB1")), "USB1");
+    }
+
+    #[test]
+    fn truncation_never_splits_a_surrogate_pair() {
+        // "🖥" is two UTF-16 units; cutting after "ab" + its high surrogate must drop it whole.
+        let buf = utf16_truncated::<4>("ab🖥");
+        assert_eq!(decoded(&buf), "ab");
+
+        assert_eq!(decoded(&utf16_truncated::<5>("ab🖥")), "ab🖥");
+        // Non-BMP characters count two units each against the limit; BMP ones count one.
+        assert_eq!(decoded(&utf16_truncated::<5>("é🖥🖥")), "é🖥");
+    }
+}
@@ -0,0 +1,95 @@
+//! Small tray-only state that should survive restarts but isn't user configuration. Lives next
+//! to the config file as `monitortray-state.json`. Everything here is best-effort: a missing or
+//! unreadable file just means "nothing remembered".
+
+use std::{fs, io, path::PathBuf};
+
+use anyhow::{Context, Result};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrayState {
+    /// Selector the config rules resolved to when `target_id` was captured (e.g. `"1"`).
+    #[serde(default)]
+    pub target_rule: Option<String>,
+    /// Stable id (`config::stable_id`) of the display that rule pointed at.
+    #[serde(default)]
+    pub target_id: Option<String>,
+    /// Inputs last switched to from the tray, newest first, at most [`MAX_RECENT_INPUTS`].
+    #[serde(default)]
+    pub recent_inputs: Vec<RecentInput>,
+}
+
+/// How many inputs the tray's "Recent inputs" submenu remembers.
+pub const MAX_RECENT_INPUTS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentInput {
+    pub selector: String,
+    pub value: u16,
+}
+
+pub fn load() -> TrayState {
+    let Some(path) = path() else {
+        return TrayState::default();
+    };
+    let Ok(bytes) = fs::read(&path) else {
+        return TrayState::default();
+    };
+    serde_json::from_slice(&bytes).unwrap_or_else(|_| {
+        // Keep the broken file for inspection instead of silently overwriting it.
+        let _ = config::set_aside(&path);
+        TrayState::default()
+    })
+}
+
+pub fn save(state: &TrayState) {
+    let Some(path) = path() else {
+        return;
+    };
+    if let Ok(s) = serde_json::to_string_pretty(state) {
+        let _ = config::write_atomic(&path, s.as_bytes());
+    }
+}
+
+/// Deletes the state file, so the tray re-captures its target on the next load. A missing file
+/// is fine.
+pub fn clear() -> Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    let config = config::resolve_config_path()?;
+    Some(config.parent()?.join("monitortray-state.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::with_config;
+
+    #[test]
+    fn a_broken_state_file_is_set_aside_and_forgotten() {
+        with_config("state-corrupt", None, |_| {
+            let path = path().unwrap();
+            fs::write(&path, r#"{"target_rule": "1", "target_id""#).unwrap();
+            assert_eq!(load(), TrayState::default());
+            assert!(!path.exists());
+            let aside = path.with_file_name("monitortray-state.json.corrupt");
+            assert!(fs::read_to_string(aside)
+                .unwrap()
+                .starts_with(r#"{"target_rule""#));
+        });
+    }
+}